@@ -0,0 +1,17 @@
+-- Extracted PDF text, split into page/paragraph passages for full-text search
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS paper_passage (
+    id TEXT PRIMARY KEY,
+    paperId TEXT NOT NULL,
+    page INTEGER NOT NULL,
+    paragraph INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    createdAt TEXT NOT NULL,
+    FOREIGN KEY (paperId) REFERENCES paper(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_paper_passage_paper_page ON paper_passage(paperId, page);
+
+-- fileHash of the PDF the passages were extracted from
+ALTER TABLE paper ADD COLUMN textHash TEXT;
@@ -0,0 +1,11 @@
+-- Split search_index into content and label columns so bm25() can weight
+-- them separately. Labels hold short descriptive text such as the tag names
+-- of a note. The table is repopulated by a search rebuild.
+DROP TABLE IF EXISTS search_index;
+
+CREATE VIRTUAL TABLE search_index USING fts5 (
+    content,
+    label,
+    refType UNINDEXED,
+    refId UNINDEXED
+);
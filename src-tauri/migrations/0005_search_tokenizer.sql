@@ -0,0 +1,18 @@
+-- Tokenize search_index with the native `paperflow` tokenizer registered by
+-- the application, so the index stores the original text and snippets match
+-- what the user wrote. search_meta records the tokenizer version the index was
+-- built with; a mismatch triggers a rebuild on startup.
+DROP TABLE IF EXISTS search_index;
+
+CREATE VIRTUAL TABLE search_index USING fts5 (
+    content,
+    label,
+    refType UNINDEXED,
+    refId UNINDEXED,
+    tokenize = 'paperflow'
+);
+
+CREATE TABLE IF NOT EXISTS search_meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Read-only view of the terms in search_index with their document counts,
+-- used to suggest corrections for misspelled query words.
+CREATE VIRTUAL TABLE IF NOT EXISTS search_vocab USING fts5vocab (search_index, 'row');
@@ -0,0 +1,19 @@
+-- Search history and named saved searches, scoped per workspace. Rows without
+-- a name are history entries recorded when a search runs; a NULL workspaceId
+-- means the search covered every workspace.
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS search_history (
+    id TEXT PRIMARY KEY,
+    workspaceId TEXT,
+    name TEXT,
+    term TEXT NOT NULL,
+    pinned INTEGER NOT NULL DEFAULT 0,
+    useCount INTEGER NOT NULL DEFAULT 1,
+    createdAt TEXT NOT NULL,
+    lastUsedAt TEXT NOT NULL,
+    FOREIGN KEY (workspaceId) REFERENCES workspace(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_search_history_workspace
+    ON search_history(workspaceId, lastUsedAt);
@@ -0,0 +1,40 @@
+-- Nested tags. `name` holds the full path (`method/attention`) and `parentId`
+-- points at the tag one level up; deleting a tag deletes its subtree.
+PRAGMA foreign_keys = ON;
+
+ALTER TABLE tag ADD COLUMN parentId TEXT REFERENCES tag(id) ON DELETE CASCADE;
+
+CREATE INDEX IF NOT EXISTS idx_tag_parent ON tag(parentId);
+
+-- Existing names that already look like paths get their missing ancestors.
+WITH RECURSIVE prefix(path, rest) AS (
+    SELECT substr(name, 1, instr(name, '/') - 1), substr(name, instr(name, '/') + 1)
+    FROM tag
+    WHERE instr(name, '/') > 0
+    UNION
+    SELECT path || '/' || substr(rest, 1, instr(rest, '/') - 1), substr(rest, instr(rest, '/') + 1)
+    FROM prefix
+    WHERE instr(rest, '/') > 0
+)
+INSERT INTO tag (id, name, color, createdAt)
+SELECT
+    lower(hex(randomblob(4)) || '-' || hex(randomblob(2)) || '-4' || substr(hex(randomblob(2)), 2)
+        || '-a' || substr(hex(randomblob(2)), 2) || '-' || hex(randomblob(6))),
+    path,
+    NULL,
+    strftime('%Y-%m-%dT%H:%M:%S+00:00', 'now')
+FROM prefix
+WHERE path <> ''
+  AND NOT EXISTS (SELECT 1 FROM tag existing WHERE existing.name = prefix.path COLLATE NOCASE)
+GROUP BY path COLLATE NOCASE;
+
+-- The parent of a tag is the existing tag with the longest matching path prefix.
+UPDATE tag SET parentId = (
+    SELECT parent.id
+    FROM tag parent
+    WHERE parent.id <> tag.id
+      AND lower(substr(tag.name, 1, length(parent.name) + 1)) = lower(parent.name) || '/'
+    ORDER BY length(parent.name) DESC
+    LIMIT 1
+)
+WHERE instr(name, '/') > 0;
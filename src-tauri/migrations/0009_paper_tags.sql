@@ -0,0 +1,17 @@
+-- Tags on papers, a user-set read status and the time a paper was last opened
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS paper_tag (
+    paperId TEXT NOT NULL,
+    tagId TEXT NOT NULL,
+    PRIMARY KEY (paperId, tagId),
+    FOREIGN KEY (paperId) REFERENCES paper(id) ON DELETE CASCADE,
+    FOREIGN KEY (tagId) REFERENCES tag(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_paper_tag_tag ON paper_tag(tagId);
+
+-- One of unread, reading, read
+ALTER TABLE paper ADD COLUMN readStatus TEXT NOT NULL DEFAULT 'unread';
+
+ALTER TABLE paper_stats ADD COLUMN lastOpenedAt TEXT;
@@ -0,0 +1,12 @@
+-- Text anchors for notes created from a selection: the quoted text, hashes of
+-- its surrounding context and its paragraph, plus the PDF version they were
+-- resolved against and whether the note could be found again after the PDF
+-- changed (anchored, relocated, orphaned).
+ALTER TABLE note ADD COLUMN anchorQuote TEXT;
+ALTER TABLE note ADD COLUMN anchorPrefixHash TEXT;
+ALTER TABLE note ADD COLUMN anchorSuffixHash TEXT;
+ALTER TABLE note ADD COLUMN anchorParagraph INTEGER;
+ALTER TABLE note ADD COLUMN anchorStatus TEXT;
+ALTER TABLE note ADD COLUMN anchorFileHash TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_note_anchor_status ON note(paperId, anchorStatus);
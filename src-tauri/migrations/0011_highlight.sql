@@ -0,0 +1,20 @@
+-- Text highlights: one or more rectangles, possibly on several pages, over a
+-- selection, with its text, a color and an optional comment.
+PRAGMA foreign_keys = ON;
+
+CREATE TABLE IF NOT EXISTS highlight (
+    id TEXT PRIMARY KEY,
+    paperId TEXT NOT NULL,
+    -- First page covered by the highlight
+    page INTEGER NOT NULL,
+    -- JSON array of {page, x, y, width, height}
+    rects TEXT NOT NULL,
+    text TEXT NOT NULL,
+    color TEXT,
+    comment TEXT,
+    createdAt TEXT NOT NULL,
+    updatedAt TEXT NOT NULL,
+    FOREIGN KEY (paperId) REFERENCES paper(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_highlight_paper ON highlight(paperId, page);
@@ -0,0 +1,12 @@
+-- Note positions become normalized page coordinates (0..1 across the page as
+-- displayed, origin top-left) together with the page box and rotation they were
+-- recorded against. Legacy positions already inside 0..1 are kept as they are;
+-- the others are PDF user-space points and are flagged so the application can
+-- convert them once it has read the page geometry of the paper's file. Notes
+-- without a position stay without one: they are about the page as a whole.
+ALTER TABLE note ADD COLUMN pageGeometry TEXT;
+ALTER TABLE note ADD COLUMN positionLegacy INTEGER NOT NULL DEFAULT 0;
+
+UPDATE note SET positionLegacy = 1 WHERE x < 0 OR x > 1 OR y < 0 OR y > 1;
+
+CREATE INDEX IF NOT EXISTS idx_note_position_legacy ON note(positionLegacy) WHERE positionLegacy = 1;
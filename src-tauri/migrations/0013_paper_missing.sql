@@ -0,0 +1,3 @@
+-- Set while the file of a paper cannot be found; cleared once it is back or
+-- relocated.
+ALTER TABLE paper ADD COLUMN missingSince TEXT;
@@ -0,0 +1,30 @@
+-- Metadata read from the PDF on import: Info dictionary, XMP and the text of
+-- the first pages
+PRAGMA foreign_keys = ON;
+
+ALTER TABLE paper ADD COLUMN subject TEXT;
+ALTER TABLE paper ADD COLUMN keywords TEXT;
+ALTER TABLE paper ADD COLUMN pdfCreatedAt TEXT;
+ALTER TABLE paper ADD COLUMN pageCount INTEGER;
+ALTER TABLE paper ADD COLUMN arxivId TEXT;
+-- fileHash of the file the metadata was read from; NULL until it was read
+ALTER TABLE paper ADD COLUMN metadataHash TEXT;
+
+-- normalizedName is the lowercased name with collapsed whitespace, so the same
+-- person spelled alike on two papers is one author
+CREATE TABLE IF NOT EXISTS author (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    normalizedName TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS paper_author (
+    paperId TEXT NOT NULL,
+    authorId TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (paperId, authorId),
+    FOREIGN KEY (paperId) REFERENCES paper(id) ON DELETE CASCADE,
+    FOREIGN KEY (authorId) REFERENCES author(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_paper_author_author ON paper_author(authorId);
@@ -0,0 +1,10 @@
+-- Bibliographic fields of papers, editable with paper_update
+PRAGMA foreign_keys = ON;
+
+ALTER TABLE paper ADD COLUMN venue TEXT;
+ALTER TABLE paper ADD COLUMN year INTEGER;
+ALTER TABLE paper ADD COLUMN abstract TEXT;
+ALTER TABLE paper ADD COLUMN url TEXT;
+-- When the fields were last edited by hand; metadata read from the PDF then
+-- no longer replaces the title
+ALTER TABLE paper ADD COLUMN editedAt TEXT;
@@ -0,0 +1,45 @@
+use std::{thread, time::Duration};
+
+use tauri::{AppHandle, State};
+
+use crate::domain::BackupEntry;
+use crate::services::{backup, search, state::AppState};
+use crate::telemetry::IpcResult;
+
+#[tauri::command]
+pub async fn backup_list() -> IpcResult<Vec<BackupEntry>> {
+    backup::list_backups()
+}
+
+#[tauri::command]
+pub async fn backup_create(state: State<'_, AppState>) -> IpcResult<BackupEntry> {
+    backup::create_backup(&state.db)
+}
+
+/// Restores a backup, then rebuilds the search index in the background when
+/// the restored database needs it.
+#[tauri::command]
+pub async fn backup_restore(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    file_name: String,
+) -> IpcResult<BackupEntry> {
+    state.search_rebuild.cancel();
+    let entry = backup::restore_backup(&state.db, &file_name)?;
+    state.sync_file_watch();
+
+    if search::index_outdated(&state.db)? {
+        let state = state.inner().clone();
+        tauri::async_runtime::spawn_blocking(move || {
+            // The rebuild cancelled above releases the control after its
+            // current batch.
+            while state.search_rebuild.is_running() {
+                thread::sleep(Duration::from_millis(50));
+            }
+            if let Err(err) = super::search::start_rebuild(app, &state) {
+                tracing::warn!(target = "search", error = %err, "failed to start search rebuild");
+            }
+        });
+    }
+    Ok(entry)
+}
@@ -0,0 +1,42 @@
+use tauri::State;
+
+use crate::domain::{Highlight, NewHighlight, UpdateHighlight};
+use crate::services::{highlights, state::AppState};
+use crate::telemetry::IpcResult;
+
+#[tauri::command]
+pub async fn highlight_list(
+    state: State<'_, AppState>,
+    paper_id: String,
+) -> IpcResult<Vec<Highlight>> {
+    highlights::list(&state.db, &paper_id)
+}
+
+#[tauri::command]
+pub async fn highlight_get(
+    state: State<'_, AppState>,
+    highlight_id: String,
+) -> IpcResult<Highlight> {
+    highlights::get(&state.db, &highlight_id)
+}
+
+#[tauri::command]
+pub async fn highlight_create(
+    state: State<'_, AppState>,
+    input: NewHighlight,
+) -> IpcResult<Highlight> {
+    highlights::create(&state.db, &input)
+}
+
+#[tauri::command]
+pub async fn highlight_update(
+    state: State<'_, AppState>,
+    input: UpdateHighlight,
+) -> IpcResult<Highlight> {
+    highlights::update(&state.db, &input)
+}
+
+#[tauri::command]
+pub async fn highlight_delete(state: State<'_, AppState>, highlight_id: String) -> IpcResult<()> {
+    highlights::delete(&state.db, &highlight_id)
+}
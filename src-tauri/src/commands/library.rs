@@ -0,0 +1,16 @@
+use tauri::State;
+
+use crate::domain::LibraryScanReport;
+use crate::services::{config, library, state::AppState};
+use crate::telemetry::IpcResult;
+
+/// Imports the files already in the library folders; files added later are
+/// imported by the file watcher.
+#[tauri::command]
+pub async fn library_scan(state: State<'_, AppState>) -> IpcResult<LibraryScanReport> {
+    let settings = config::load_settings()?;
+    let roots = library::library_roots(&settings.library_folders)?;
+    let report = library::scan(&state.db, &roots)?;
+    state.sync_file_watch();
+    Ok(report)
+}
@@ -1,3 +1,6 @@
+pub mod backup;
+pub mod highlight;
+pub mod library;
 pub mod note;
 pub mod paper;
 pub mod preview;
@@ -6,4 +9,5 @@ pub mod search;
 pub mod settings;
 pub mod shortcuts;
 pub mod system;
+pub mod tag;
 pub mod workspace;
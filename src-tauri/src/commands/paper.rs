@@ -1,12 +1,15 @@
 use tauri::State;
 
-use crate::domain::{Paper, PaperImportRequest};
-use crate::services::{repo, state::AppState};
+use crate::domain::{
+    LocateMissingReport, LocateMissingRequest, Paper, PaperImportRequest, PaperListQuery,
+    UpdatePaper,
+};
+use crate::services::{relocate, repo, state::AppState};
 use crate::telemetry::IpcResult;
 
 #[tauri::command]
 pub async fn paper_open(state: State<'_, AppState>, paper_id: String) -> IpcResult<Paper> {
-    repo::get_paper(&state.db, &paper_id)
+    repo::open_paper(&state.db, &paper_id)
 }
 
 #[tauri::command]
@@ -14,18 +17,61 @@ pub async fn paper_import(
     state: State<'_, AppState>,
     request: PaperImportRequest,
 ) -> IpcResult<Vec<Paper>> {
-    repo::import_papers(&state.db, &request)
+    let papers = repo::import_papers(&state.db, &request)?;
+    state.sync_file_watch();
+    // Metadata and text are read after the import returns, like the startup
+    // indexing in main.rs.
+    let db = state.db.clone();
+    let mut imported = papers.clone();
+    tauri::async_runtime::spawn_blocking(move || repo::index_imported(&db, &mut imported));
+    Ok(papers)
 }
 
 #[tauri::command]
 pub async fn paper_list(
     state: State<'_, AppState>,
     workspace_id: Option<String>,
+    query: Option<PaperListQuery>,
 ) -> IpcResult<Vec<Paper>> {
     let workspace_id = workspace_id
         .as_deref()
         .map(str::trim)
         .filter(|id| !id.is_empty())
         .unwrap_or("default_workspace");
-    repo::list_papers(&state.db, workspace_id)
+    repo::query_papers(&state.db, workspace_id, &query.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn paper_set_read_status(
+    state: State<'_, AppState>,
+    paper_id: String,
+    status: String,
+) -> IpcResult<Paper> {
+    repo::set_read_status(&state.db, &paper_id, &status)
+}
+
+#[tauri::command]
+pub async fn paper_update(state: State<'_, AppState>, input: UpdatePaper) -> IpcResult<Paper> {
+    repo::update_paper(&state.db, &input)
+}
+
+#[tauri::command]
+pub async fn paper_relocate(
+    state: State<'_, AppState>,
+    paper_id: String,
+    path: String,
+) -> IpcResult<Paper> {
+    let paper = relocate::relocate(&state.db, &paper_id, &path)?;
+    state.sync_file_watch();
+    Ok(paper)
+}
+
+#[tauri::command]
+pub async fn paper_locate_missing(
+    state: State<'_, AppState>,
+    request: LocateMissingRequest,
+) -> IpcResult<LocateMissingReport> {
+    let report = relocate::locate_missing(&state.db, &request)?;
+    state.sync_file_watch();
+    Ok(report)
 }
@@ -1,8 +1,13 @@
-use tauri::State;
+use tauri::{AppHandle, Manager, State};
 
-use crate::domain::SearchHit;
-use crate::services::{search, state::AppState};
-use crate::telemetry::IpcResult;
+use crate::domain::{
+    SaveSearchRequest, SavedSearch, SearchHit, SearchPage, SearchRebuildProgress, SearchRequest,
+};
+use crate::services::{config, search, state::AppState};
+use crate::telemetry::{IpcError, IpcResult, IpcStatus};
+
+/// Event carrying [`SearchRebuildProgress`] while a rebuild runs.
+const SEARCH_PROGRESS_EVENT: &str = "search://progress";
 
 #[tauri::command]
 pub async fn search_query(
@@ -10,10 +15,131 @@ pub async fn search_query(
     term: String,
     limit: Option<u32>,
 ) -> IpcResult<Vec<SearchHit>> {
-    search::query(&state.db, &term, limit.unwrap_or(20))
+    let settings = config::load_settings()?;
+    search::query(
+        &state.db,
+        &term,
+        limit.unwrap_or(20),
+        &settings.search_ranking,
+    )
+}
+
+#[tauri::command]
+pub async fn search_page(
+    state: State<'_, AppState>,
+    request: SearchRequest,
+) -> IpcResult<SearchPage> {
+    let settings = config::load_settings()?;
+    let page = search::query_page(&state.db, &request, &settings.search_ranking)?;
+    if request.cursor.is_none() {
+        record_history(&state, request.workspace_id.as_deref(), &request.term);
+    }
+    Ok(page)
+}
+
+#[tauri::command]
+pub async fn search_history_list(
+    state: State<'_, AppState>,
+    workspace_id: Option<String>,
+    saved_only: Option<bool>,
+) -> IpcResult<Vec<SavedSearch>> {
+    search::history::list(
+        &state.db,
+        workspace_id.as_deref(),
+        saved_only.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+pub async fn search_save(
+    state: State<'_, AppState>,
+    request: SaveSearchRequest,
+) -> IpcResult<SavedSearch> {
+    search::history::save(&state.db, &request)
+}
+
+#[tauri::command]
+pub async fn search_history_pin(
+    state: State<'_, AppState>,
+    id: String,
+    pinned: bool,
+) -> IpcResult<SavedSearch> {
+    search::history::set_pinned(&state.db, &id, pinned)
+}
+
+/// Runs a history entry or saved search again in its workspace.
+#[tauri::command]
+pub async fn search_history_rerun(
+    state: State<'_, AppState>,
+    id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> IpcResult<SearchPage> {
+    let entry = search::history::touch(&state.db, &id)?;
+    let settings = config::load_settings()?;
+    let request = SearchRequest {
+        term: entry.term,
+        workspace_id: entry.workspace_id,
+        cursor,
+        limit,
+        ..Default::default()
+    };
+    search::query_page(&state.db, &request, &settings.search_ranking)
+}
+
+#[tauri::command]
+pub async fn search_history_delete(state: State<'_, AppState>, id: String) -> IpcResult<()> {
+    search::history::delete(&state.db, &id)
+}
+
+/// History is a convenience; failing to record it must not fail the search.
+fn record_history(state: &AppState, workspace_id: Option<&str>, term: &str) {
+    let workspace_id = workspace_id.filter(|id| !id.trim().is_empty());
+    if let Err(err) = search::history::record(&state.db, workspace_id, term) {
+        tracing::warn!(target = "search", error = %err, "failed to record search history");
+    }
+}
+
+/// Starts a background rebuild of the search index and returns immediately;
+/// progress is reported through `search://progress` events.
+#[tauri::command]
+pub async fn search_rebuild(app: AppHandle, state: State<'_, AppState>) -> IpcResult<()> {
+    start_rebuild(app, &state)
+}
+
+/// Runs [`search::rebuild::rebuild_in_batches`] on a blocking thread, emitting
+/// `search://progress` events. Fails when a rebuild is already running.
+pub(crate) fn start_rebuild(app: AppHandle, state: &AppState) -> IpcResult<()> {
+    let run = state.search_rebuild.begin().ok_or_else(|| {
+        IpcError::new(
+            IpcStatus::Conflict,
+            "A search index rebuild is already running",
+        )
+    })?;
+    let db = state.db.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = search::rebuild::rebuild_in_batches(&db, &run, |progress| {
+            let _ = app.emit_all(SEARCH_PROGRESS_EVENT, progress.clone());
+        });
+        if let Err(err) = result {
+            tracing::error!(target = "search", error = %err, "search index rebuild failed");
+            let _ = app.emit_all(
+                SEARCH_PROGRESS_EVENT,
+                SearchRebuildProgress {
+                    stage: "failed".into(),
+                    ..Default::default()
+                },
+            );
+        }
+    });
+
+    Ok(())
 }
 
+/// Cancels a running rebuild; the previous index stays in place. Returns
+/// `false` when no rebuild was running.
 #[tauri::command]
-pub async fn search_rebuild(state: State<'_, AppState>) -> IpcResult<()> {
-    search::rebuild(&state.db)
+pub async fn search_rebuild_cancel(state: State<'_, AppState>) -> IpcResult<bool> {
+    Ok(state.search_rebuild.cancel())
 }
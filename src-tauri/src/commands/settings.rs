@@ -1,5 +1,7 @@
+use tauri::State;
+
 use crate::domain::AppSettings;
-use crate::services::config;
+use crate::services::{config, library, state::AppState};
 use crate::telemetry::{IpcError, IpcResult, IpcStatus};
 
 #[tauri::command]
@@ -8,7 +10,10 @@ pub async fn settings_get() -> IpcResult<AppSettings> {
 }
 
 #[tauri::command]
-pub async fn settings_set(settings: AppSettings) -> IpcResult<()> {
+pub async fn settings_set(state: State<'_, AppState>, settings: AppSettings) -> IpcResult<()> {
+    let roots = library::library_roots(&settings.library_folders)?;
     config::save_settings(&settings)
-        .map_err(|err| IpcError::new(IpcStatus::Internal, err.to_string()))
+        .map_err(|err| IpcError::new(IpcStatus::Internal, err.to_string()))?;
+    state.set_library(roots);
+    Ok(())
 }
@@ -0,0 +1,92 @@
+use tauri::State;
+
+use crate::domain::{Note, Paper, Tag};
+use crate::services::{state::AppState, tags};
+use crate::telemetry::IpcResult;
+
+#[tauri::command]
+pub async fn tag_list(state: State<'_, AppState>) -> IpcResult<Vec<Tag>> {
+    tags::list(&state.db)
+}
+
+#[tauri::command]
+pub async fn tag_create(
+    state: State<'_, AppState>,
+    name: String,
+    color: Option<String>,
+) -> IpcResult<Tag> {
+    tags::create(&state.db, &name, color.as_deref())
+}
+
+#[tauri::command]
+pub async fn tag_rename(state: State<'_, AppState>, id: String, name: String) -> IpcResult<Tag> {
+    tags::rename(&state.db, &id, &name)
+}
+
+#[tauri::command]
+pub async fn tag_move(
+    state: State<'_, AppState>,
+    id: String,
+    parent_id: Option<String>,
+) -> IpcResult<Tag> {
+    tags::move_to(&state.db, &id, parent_id.as_deref())
+}
+
+#[tauri::command]
+pub async fn tag_recolor(
+    state: State<'_, AppState>,
+    id: String,
+    color: Option<String>,
+) -> IpcResult<Tag> {
+    tags::recolor(&state.db, &id, color.as_deref())
+}
+
+#[tauri::command]
+pub async fn tag_delete(state: State<'_, AppState>, id: String) -> IpcResult<()> {
+    tags::delete(&state.db, &id)
+}
+
+#[tauri::command]
+pub async fn tag_merge(
+    state: State<'_, AppState>,
+    source_ids: Vec<String>,
+    target_id: String,
+) -> IpcResult<Tag> {
+    tags::merge(&state.db, &source_ids, &target_id)
+}
+
+#[tauri::command]
+pub async fn note_tag_add(
+    state: State<'_, AppState>,
+    note_id: String,
+    tag_id: String,
+) -> IpcResult<Note> {
+    tags::add_to_note(&state.db, &note_id, &tag_id)
+}
+
+#[tauri::command]
+pub async fn note_tag_remove(
+    state: State<'_, AppState>,
+    note_id: String,
+    tag_id: String,
+) -> IpcResult<Note> {
+    tags::remove_from_note(&state.db, &note_id, &tag_id)
+}
+
+#[tauri::command]
+pub async fn paper_tag_add(
+    state: State<'_, AppState>,
+    paper_id: String,
+    tag_id: String,
+) -> IpcResult<Paper> {
+    tags::add_to_paper(&state.db, &paper_id, &tag_id)
+}
+
+#[tauri::command]
+pub async fn paper_tag_remove(
+    state: State<'_, AppState>,
+    paper_id: String,
+    tag_id: String,
+) -> IpcResult<Paper> {
+    tags::remove_from_paper(&state.db, &paper_id, &tag_id)
+}
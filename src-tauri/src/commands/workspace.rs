@@ -1,7 +1,9 @@
 use tauri::State;
 
-use crate::domain::Workspace;
-use crate::services::{repo, state::AppState};
+use crate::domain::{
+    Workspace, WorkspaceExportReport, WorkspaceImportReport, WorkspaceImportRequest,
+};
+use crate::services::{bundle, repo, state::AppState};
 use crate::telemetry::IpcResult;
 
 #[tauri::command]
@@ -25,5 +27,26 @@ pub async fn workspace_rename(
 
 #[tauri::command]
 pub async fn workspace_delete(state: State<'_, AppState>, id: String) -> IpcResult<()> {
-    repo::delete_workspace(&state.db, &id)
+    repo::delete_workspace(&state.db, &id)?;
+    state.sync_file_watch();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn workspace_export(
+    state: State<'_, AppState>,
+    id: String,
+    destination: String,
+) -> IpcResult<WorkspaceExportReport> {
+    bundle::export_workspace(&state.db, &id, &destination)
+}
+
+#[tauri::command]
+pub async fn workspace_import(
+    state: State<'_, AppState>,
+    request: WorkspaceImportRequest,
+) -> IpcResult<WorkspaceImportReport> {
+    let report = bundle::import_workspace(&state.db, &request)?;
+    state.sync_file_watch();
+    Ok(report)
 }
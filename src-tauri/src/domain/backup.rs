@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupEntry {
+    pub file_name: String,
+    pub path: String,
+    pub size: i64,
+    pub schema_version: Option<i64>,
+    pub created_at: String,
+}
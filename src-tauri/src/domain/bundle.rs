@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Highlight, Note, NoteStats, Paper, PaperStats, Tag, Workspace};
+
+/// Serialized form of `workspace.json` inside an export bundle.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceBundle {
+    pub format_version: u32,
+    pub exported_at: String,
+    pub workspace: Workspace,
+    pub papers: Vec<Paper>,
+    pub notes: Vec<Note>,
+    #[serde(default)]
+    pub highlights: Vec<Highlight>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub note_tags: Vec<NoteTagLink>,
+    #[serde(default)]
+    pub paper_tags: Vec<PaperTagLink>,
+    #[serde(default)]
+    pub note_stats: Vec<NoteStats>,
+    #[serde(default)]
+    pub paper_stats: Vec<PaperStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteTagLink {
+    pub note_id: String,
+    pub tag_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PaperTagLink {
+    pub paper_id: String,
+    pub tag_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceExportReport {
+    pub path: String,
+    pub papers: usize,
+    pub notes: usize,
+    pub highlights: usize,
+    pub tags: usize,
+    pub attachments: usize,
+    pub missing_attachments: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceImportRequest {
+    /// Bundle directory, or the path to its `workspace.json`.
+    pub source: String,
+    /// Merge into this existing workspace instead of creating one.
+    pub target_workspace_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportCounts {
+    pub papers: usize,
+    pub notes: usize,
+    pub highlights: usize,
+    pub tags: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceImportReport {
+    pub workspace_id: String,
+    pub workspace_created: bool,
+    pub created: ImportCounts,
+    pub merged: ImportCounts,
+    pub skipped: ImportCounts,
+    pub remapped: ImportCounts,
+    pub warnings: Vec<String>,
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+/// A text highlight over one or more rectangles, possibly on several pages.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Highlight {
+    pub id: String,
+    pub paper_id: String,
+    /// First page covered by the highlight.
+    pub page: i32,
+    pub rects: Vec<HighlightRect>,
+    /// Selected text, as sent by the viewer.
+    pub text: String,
+    pub color: Option<String>,
+    pub comment: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// One line box of a highlight, in normalized page coordinates (see
+/// [`super::PageGeometry`]).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightRect {
+    pub page: i32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NewHighlight {
+    pub paper_id: String,
+    pub rects: Vec<HighlightRect>,
+    pub text: String,
+    pub color: Option<String>,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateHighlight {
+    pub id: String,
+    pub rects: Option<Vec<HighlightRect>>,
+    pub text: Option<String>,
+    pub color: Option<String>,
+    /// An empty comment removes it.
+    pub comment: Option<String>,
+}
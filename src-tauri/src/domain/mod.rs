@@ -1,3 +1,6 @@
+pub mod backup;
+pub mod bundle;
+pub mod highlight;
 pub mod note;
 pub mod paper;
 pub mod search;
@@ -6,10 +9,22 @@ pub mod stats;
 pub mod tag;
 pub mod workspace;
 
-pub use note::{NewNote, Note, UpdateNote};
-pub use paper::{Paper, PaperImportRequest};
-pub use search::{SearchHit, SearchRebuildProgress};
-pub use settings::AppSettings;
+pub use backup::BackupEntry;
+pub use bundle::{
+    NoteTagLink, PaperTagLink, WorkspaceBundle, WorkspaceExportReport, WorkspaceImportReport,
+    WorkspaceImportRequest,
+};
+pub use highlight::{Highlight, HighlightRect, NewHighlight, UpdateHighlight};
+pub use note::{NewNote, Note, NoteAnchor, PageGeometry, UpdateNote};
+pub use paper::{
+    LibraryScanReport, LocateMissingReport, LocateMissingRequest, Paper, PaperFileEvent,
+    PaperImportRequest, PaperListQuery, PaperSort, UpdatePaper,
+};
+pub use search::{
+    SaveSearchRequest, SavedSearch, SearchHit, SearchPage, SearchRebuildProgress, SearchRequest,
+    SearchResultHit,
+};
+pub use settings::{AppSettings, LibraryFolder, SearchRanking};
 pub use stats::{NoteStats, PaperStats};
 pub use tag::Tag;
 pub use workspace::Workspace;
@@ -1,17 +1,65 @@
 use serde::{Deserialize, Serialize};
 
+use super::Tag;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Note {
     pub id: String,
     pub paper_id: String,
     pub page: i32,
-    pub x: f32,
-    pub y: f32,
+    /// Position on the page in normalized coordinates, see [`PageGeometry`];
+    /// `None` for notes about the page as a whole.
+    pub x: Option<f32>,
+    pub y: Option<f32>,
     pub content: String,
     pub color: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// Page box and rotation the position was recorded against, when the PDF
+    /// could be read.
+    #[serde(default)]
+    pub page_geometry: Option<PageGeometry>,
+    /// Text the note is attached to, when it was created from a selection.
+    #[serde(default)]
+    pub anchor: Option<NoteAnchor>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+/// Visible box of a page and its rotation.
+///
+/// Note positions and highlight rectangles use normalized page coordinates:
+/// `x` and `y` run from 0 to 1 across the page as the viewer shows it, i.e. the
+/// page box rotated by `rotation`, with the origin at the top-left corner. They
+/// are independent of zoom, rotation and the page box origin.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PageGeometry {
+    /// CropBox, or MediaBox when there is none, in PDF user space:
+    /// `[left, bottom, right, top]`.
+    pub page_box: [f32; 4],
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270.
+    pub rotation: i32,
+}
+
+/// Where a note sits in the text of its paper, independent of coordinates, so
+/// it can be found again in a new version of the PDF.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteAnchor {
+    /// Selected text, as sent by the viewer.
+    pub quote: String,
+    /// Hashes of the folded text just before and after the quote.
+    pub prefix_hash: Option<String>,
+    pub suffix_hash: Option<String>,
+    /// Index of the paragraph holding the quote among the page's passages.
+    pub paragraph: Option<i32>,
+    /// `anchored`, `relocated` (found elsewhere in a new version of the PDF)
+    /// or `orphaned` (no longer found).
+    pub status: String,
+    /// `fileHash` of the PDF version the anchor was resolved against.
+    pub file_hash: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -19,10 +67,17 @@ pub struct Note {
 pub struct NewNote {
     pub paper_id: String,
     pub page: i32,
-    pub x: f32,
-    pub y: f32,
+    /// Normalized page coordinates, see [`PageGeometry`]. Both are left out
+    /// for a note about the whole page.
+    #[serde(default)]
+    pub x: Option<f32>,
+    #[serde(default)]
+    pub y: Option<f32>,
     pub content: String,
     pub color: Option<String>,
+    /// Selected text to anchor the note to.
+    #[serde(default)]
+    pub quote: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::Tag;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct Paper {
@@ -13,6 +15,107 @@ pub struct Paper {
     pub filesize: Option<i64>,
     pub created_at: String,
     pub updated_at: String,
+    /// `unread`, `reading` or `read`.
+    #[serde(default)]
+    pub read_status: String,
+    /// When the file was found gone; `None` while it is in place.
+    #[serde(default)]
+    pub missing_since: Option<String>,
+    /// Author names in byline order.
+    #[serde(default)]
+    pub authors: Vec<String>,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub keywords: Option<String>,
+    /// Creation date recorded in the PDF, as RFC 3339.
+    #[serde(default)]
+    pub pdf_created_at: Option<String>,
+    #[serde(default)]
+    pub page_count: Option<i32>,
+    /// arXiv identifier without version, e.g. `1706.03762`.
+    #[serde(default)]
+    pub arxiv_id: Option<String>,
+    /// Journal or conference the paper appeared in.
+    #[serde(default)]
+    pub venue: Option<String>,
+    #[serde(default)]
+    pub year: Option<i32>,
+    #[serde(default)]
+    pub r#abstract: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    /// When the bibliographic fields were last edited by hand.
+    #[serde(default)]
+    pub edited_at: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+/// Bibliographic fields to change; `None` leaves a field as it is and an
+/// empty string clears it, except for the title, which cannot be cleared.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePaper {
+    pub id: String,
+    pub title: Option<String>,
+    /// Author names in byline order; an empty list removes all authors.
+    pub authors: Option<Vec<String>>,
+    pub doi: Option<String>,
+    pub venue: Option<String>,
+    /// `0` clears the year.
+    pub year: Option<i32>,
+    pub r#abstract: Option<String>,
+    pub arxiv_id: Option<String>,
+    pub url: Option<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+}
+
+/// Payload of the file watcher events about a paper's file.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PaperFileEvent {
+    pub paper_id: String,
+    /// Current location of the file.
+    pub path: String,
+    /// Where the file was before it was relinked.
+    pub previous_path: Option<String>,
+}
+
+/// Folders to search for the files of papers whose `path` no longer exists.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LocateMissingRequest {
+    /// Searched recursively; symlinked folders are not followed.
+    pub roots: Vec<String>,
+    /// Only look for papers of this workspace; all workspaces when `None`.
+    pub workspace_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LocateMissingReport {
+    /// Papers found again, with their new `path`.
+    pub relocated: Vec<Paper>,
+    /// Ids of papers whose file was not found under any root.
+    pub still_missing: Vec<String>,
+    pub files_scanned: usize,
+    /// Files whose size matched a missing paper, or PDFs checked for papers
+    /// without a recorded size, that were hashed.
+    pub files_hashed: usize,
+    /// Folders or files that could not be read.
+    pub warnings: Vec<String>,
+}
+
+/// Outcome of importing the new files of the library folders.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanReport {
+    pub imported: Vec<Paper>,
+    pub files_scanned: usize,
+    /// Folders that could not be read and files that failed to import.
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -21,3 +124,29 @@ pub struct PaperImportRequest {
     pub paths: Vec<String>,
     pub workspace_id: String,
 }
+
+/// Filters and ordering for `paper_list`. Every filter is optional.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PaperListQuery {
+    /// Papers must carry each of these tags or one of its descendants.
+    #[serde(default)]
+    pub tag_ids: Vec<String>,
+    pub read_status: Option<String>,
+    pub has_doi: Option<bool>,
+    #[serde(default)]
+    pub sort: PaperSort,
+    /// Defaults to ascending for titles and descending otherwise.
+    pub descending: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum PaperSort {
+    #[default]
+    UpdatedAt,
+    Title,
+    CreatedAt,
+    LastOpened,
+    ReadTime,
+}
@@ -7,6 +7,72 @@ pub struct SearchHit {
     pub ref_id: String,
     pub snippet: Option<String>,
     pub score: f32,
+    pub paper_id: Option<String>,
+    pub page: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchRequest {
+    pub term: String,
+    /// Restricts hits to papers of this workspace.
+    pub workspace_id: Option<String>,
+    /// Restricts hits to these papers; empty means all papers.
+    #[serde(default)]
+    pub paper_ids: Vec<String>,
+    /// Opaque cursor returned as `nextCursor` by the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// A search hit with the paper, note and workspace details the result list shows.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultHit {
+    #[serde(flatten)]
+    pub hit: SearchHit,
+    pub paper_title: Option<String>,
+    pub note_color: Option<String>,
+    pub workspace_id: Option<String>,
+    pub workspace_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPage {
+    pub hits: Vec<SearchResultHit>,
+    /// Number of hits across all pages.
+    pub total: usize,
+    pub next_cursor: Option<String>,
+    /// Respellings of the query for queries with few hits, best first.
+    pub suggestions: Vec<String>,
+    /// Set when the query had no hits and the page holds the hits of the
+    /// first suggestion instead.
+    pub corrected_term: Option<String>,
+}
+
+/// A search history entry, or a saved search when `name` is set.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub id: String,
+    pub workspace_id: Option<String>,
+    pub name: Option<String>,
+    pub term: String,
+    pub pinned: bool,
+    pub use_count: i64,
+    pub created_at: String,
+    pub last_used_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SaveSearchRequest {
+    pub workspace_id: Option<String>,
+    pub name: String,
+    pub term: String,
+    #[serde(default)]
+    pub pinned: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
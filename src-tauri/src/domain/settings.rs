@@ -1,9 +1,92 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AppSettings {
     pub theme: String,
     pub default_workspace_id: Option<String>,
     pub global_shortcuts_enabled: bool,
+    #[serde(default)]
+    pub search_ranking: SearchRanking,
+    #[serde(default)]
+    pub library_folders: Vec<LibraryFolder>,
+    /// Number of database backups kept; older copies are pruned.
+    #[serde(default = "default_backup_retention")]
+    pub backup_retention: usize,
+}
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            theme: String::new(),
+            default_workspace_id: None,
+            global_shortcuts_enabled: false,
+            search_ranking: SearchRanking::default(),
+            library_folders: Vec::new(),
+            backup_retention: default_backup_retention(),
+        }
+    }
+}
+
+fn default_backup_retention() -> usize {
+    10
+}
+
+/// A folder whose PDFs are imported into a workspace as they appear.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryFolder {
+    pub workspace_id: String,
+    pub path: String,
+    /// Also import from subfolders.
+    #[serde(default = "default_recursive")]
+    pub recursive: bool,
+    /// Case-insensitive globs matched against the path relative to the folder,
+    /// e.g. `papers/**/*.pdf`; without a `/` they match names at any depth.
+    /// Empty means `*.pdf`.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Globs of files and subfolders to leave out; they win over `include`.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+fn default_recursive() -> bool {
+    true
+}
+
+/// Weights applied on top of FTS5 `bm25()` when ordering search hits.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchRanking {
+    pub note_weight: f64,
+    pub highlight_weight: f64,
+    pub tag_weight: f64,
+    pub pdf_weight: f64,
+    /// Weight of papers matched by their title, abstract or authors.
+    pub paper_weight: f64,
+    /// bm25 weight of the indexed text itself.
+    pub content_column_weight: f64,
+    /// bm25 weight of the entry label, e.g. the tag names of a note.
+    pub label_column_weight: f64,
+    /// Maximum relative boost a just-edited note receives.
+    pub recency_boost: f64,
+    /// Age in days at which a note keeps half of its recency boost.
+    pub recency_half_life_days: f64,
+}
+
+impl Default for SearchRanking {
+    fn default() -> Self {
+        Self {
+            note_weight: 1.0,
+            highlight_weight: 0.9,
+            tag_weight: 0.8,
+            pdf_weight: 0.6,
+            paper_weight: 1.0,
+            content_column_weight: 1.0,
+            label_column_weight: 2.0,
+            recency_boost: 0.25,
+            recency_half_life_days: 30.0,
+        }
+    }
 }
@@ -14,4 +14,6 @@ pub struct PaperStats {
     pub paper_id: String,
     pub total_read_time: i64,
     pub last_opened_page: Option<i64>,
+    #[serde(default)]
+    pub last_opened_at: Option<String>,
 }
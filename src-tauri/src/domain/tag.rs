@@ -4,7 +4,13 @@ use serde::{Deserialize, Serialize};
 #[serde(rename_all = "camelCase")]
 pub struct Tag {
     pub id: String,
+    /// Full path of the tag, e.g. `method/attention`.
     pub name: String,
+    #[serde(default)]
+    pub parent_id: Option<String>,
     pub color: Option<String>,
     pub created_at: String,
+    /// Number of notes and papers carrying the tag.
+    #[serde(default)]
+    pub usage_count: i64,
 }
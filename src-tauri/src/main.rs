@@ -17,26 +17,157 @@ fn main() {
                 telemetry::logging::log_startup_error("config::ensure_directories", &err);
             }
 
-            if let Err(err) = services::migration::ensure_initialized() {
-                telemetry::logging::log_startup_error("migration::ensure_initialized", &err);
-            }
+            let migration = match services::migration::ensure_initialized() {
+                Ok(report) => {
+                    telemetry::logging::log_migration_report(&report);
+                    Some(report)
+                }
+                Err(err) => {
+                    telemetry::logging::log_startup_error("migration::ensure_initialized", &err);
+                    if err.is::<services::migration::SchemaTooNew>() {
+                        return Err(err.into());
+                    }
+                    None
+                }
+            };
 
-            app.manage(services::state::AppState::default());
+            let state = services::state::AppState::default();
+            let reindex = migration.is_some_and(|report| report.reindex_required)
+                || services::search::index_outdated(&state.db).unwrap_or(true);
+            if reindex {
+                // Searches keep using the old index until the rebuild swaps in.
+                if let Err(err) = commands::search::start_rebuild(app.handle(), &state) {
+                    telemetry::logging::log_startup_error("search::start_rebuild", &err.into());
+                }
+            }
+            // Extracts the text of papers imported before it was indexed.
+            let db = state.db.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                if let Err(err) = services::pdf::text::index_stale_papers(&db) {
+                    telemetry::logging::log_startup_error(
+                        "pdf::text::index_stale_papers",
+                        &err.into(),
+                    );
+                }
+            });
+            // Reads the page geometry of every paper that still has notes with
+            // legacy positions, so it runs off the setup thread.
+            let db = state.db.clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                if let Err(err) = services::pdf::geometry::convert_legacy_notes(&db) {
+                    telemetry::logging::log_startup_error(
+                        "pdf::geometry::convert_legacy_notes",
+                        &err.into(),
+                    );
+                }
+            });
+            let handle = app.handle();
+            let watcher = services::file_watch::FileWatcher::start(
+                state.db.clone(),
+                move |event, payload| {
+                    if let Err(err) = handle.emit_all(event, payload) {
+                        tracing::warn!(
+                            target = "file_watch",
+                            error = %err,
+                            "failed to emit file event"
+                        );
+                    }
+                },
+            );
+            match watcher {
+                Ok(watcher) => *state.file_watcher.lock() = Some(watcher),
+                Err(err) => {
+                    telemetry::logging::log_startup_error("file_watch::FileWatcher::start", &err)
+                }
+            }
+            let library = services::config::load_settings()
+                .map_err(Into::into)
+                .and_then(|settings| services::library::library_roots(&settings.library_folders));
+            match library {
+                Ok(roots) => {
+                    state.set_library(roots.clone());
+                    // Imports the files added to the library folders while the
+                    // app was closed.
+                    let db = state.db.clone();
+                    let handle = app.handle();
+                    tauri::async_runtime::spawn_blocking(move || {
+                        match services::library::scan(&db, &roots) {
+                            Ok(report) => {
+                                for paper in report.imported {
+                                    let _ = handle.emit_all(
+                                        services::file_watch::FILE_IMPORTED_EVENT,
+                                        domain::PaperFileEvent {
+                                            paper_id: paper.id,
+                                            path: paper.path,
+                                            previous_path: None,
+                                        },
+                                    );
+                                }
+                                for warning in report.warnings {
+                                    tracing::warn!(target = "library", "{warning}");
+                                }
+                            }
+                            Err(err) => {
+                                tracing::warn!(
+                                    target = "library",
+                                    error = %err,
+                                    "library scan failed"
+                                );
+                            }
+                        }
+                    });
+                }
+                Err(err) => {
+                    telemetry::logging::log_startup_error("library::library_roots", &err.into())
+                }
+            }
+            app.manage(state);
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::system::ping,
+            commands::backup::backup_list,
+            commands::backup::backup_create,
+            commands::backup::backup_restore,
             commands::paper::paper_open,
             commands::paper::paper_import,
             commands::paper::paper_list,
+            commands::paper::paper_set_read_status,
+            commands::paper::paper_update,
+            commands::paper::paper_relocate,
+            commands::paper::paper_locate_missing,
             commands::note::note_create,
             commands::note::note_list,
             commands::note::note_get,
             commands::note::note_update,
             commands::note::note_delete,
+            commands::highlight::highlight_create,
+            commands::highlight::highlight_list,
+            commands::highlight::highlight_get,
+            commands::highlight::highlight_update,
+            commands::highlight::highlight_delete,
+            commands::library::library_scan,
+            commands::tag::tag_list,
+            commands::tag::tag_create,
+            commands::tag::tag_rename,
+            commands::tag::tag_move,
+            commands::tag::tag_recolor,
+            commands::tag::tag_delete,
+            commands::tag::tag_merge,
+            commands::tag::note_tag_add,
+            commands::tag::note_tag_remove,
+            commands::tag::paper_tag_add,
+            commands::tag::paper_tag_remove,
             commands::search::search_query,
+            commands::search::search_page,
+            commands::search::search_history_list,
+            commands::search::search_save,
+            commands::search::search_history_pin,
+            commands::search::search_history_rerun,
+            commands::search::search_history_delete,
             commands::search::search_rebuild,
+            commands::search::search_rebuild_cancel,
             commands::preview::preview_get,
             commands::review::review_summary,
             commands::settings::settings_get,
@@ -46,7 +177,9 @@ fn main() {
             commands::workspace::workspace_list,
             commands::workspace::workspace_create,
             commands::workspace::workspace_rename,
-            commands::workspace::workspace_delete
+            commands::workspace::workspace_delete,
+            commands::workspace::workspace_export,
+            commands::workspace::workspace_import
         ]);
 
     builder
@@ -0,0 +1,108 @@
+//! Authors of papers. An author row is shared by every paper whose byline
+//! spells the name alike (see `normalizedName`); rows no paper refers to
+//! anymore are dropped.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::domain::Paper;
+
+/// Fills in the `authors` of each paper.
+pub(crate) fn attach_paper_authors(
+    conn: &Connection,
+    papers: &mut [Paper],
+) -> rusqlite::Result<()> {
+    if papers.is_empty() {
+        return Ok(());
+    }
+
+    let paper_ids = papers
+        .iter()
+        .map(|paper| paper.id.clone())
+        .collect::<Vec<_>>();
+    let placeholders = vec!["?"; paper_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT paper_author.paperId, author.name FROM paper_author \
+         JOIN author ON author.id = paper_author.authorId \
+         WHERE paper_author.paperId IN ({placeholders}) \
+         ORDER BY paper_author.position ASC"
+    ))?;
+    let rows = stmt.query_map(params_from_iter(&paper_ids), |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+
+    let mut authors: HashMap<String, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (paper_id, name) = row?;
+        authors.entry(paper_id).or_default().push(name);
+    }
+    for paper in papers {
+        paper.authors = authors.remove(&paper.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Replaces the authors of a paper with `names`, in order. Blank names and
+/// repeats of a name are skipped.
+pub(crate) fn set_paper_authors(
+    conn: &Connection,
+    paper_id: &str,
+    names: &[String],
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM paper_author WHERE paperId = ?1",
+        params![paper_id],
+    )?;
+
+    let mut position = 0;
+    for name in names {
+        let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        if name.is_empty() {
+            continue;
+        }
+        let author_id = ensure_author(conn, &name)?;
+        let inserted = conn.execute(
+            "INSERT OR IGNORE INTO paper_author (paperId, authorId, position) VALUES (?1, ?2, ?3)",
+            params![paper_id, &author_id, position],
+        )?;
+        position += inserted as i64;
+    }
+
+    conn.execute(
+        "DELETE FROM author WHERE NOT EXISTS \
+         (SELECT 1 FROM paper_author WHERE paper_author.authorId = author.id)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Lowercased name with collapsed whitespace.
+pub(crate) fn normalize_name(name: &str) -> String {
+    name.split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn ensure_author(conn: &Connection, name: &str) -> rusqlite::Result<String> {
+    let normalized = normalize_name(name);
+    let existing = conn
+        .query_row(
+            "SELECT id FROM author WHERE normalizedName = ?1",
+            params![&normalized],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    if let Some(author_id) = existing {
+        return Ok(author_id);
+    }
+
+    let author_id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO author (id, name, normalizedName) VALUES (?1, ?2, ?3)",
+        params![&author_id, name, &normalized],
+    )?;
+    Ok(author_id)
+}
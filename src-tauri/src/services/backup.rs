@@ -0,0 +1,389 @@
+use std::{
+    convert::TryFrom,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use rusqlite::{backup::Progress, Connection, DatabaseName, OpenFlags};
+
+use crate::{
+    domain::BackupEntry,
+    telemetry::{IpcError, IpcResult, IpcStatus},
+};
+
+use super::{config, migration, search, Db};
+
+const BACKUP_PREFIX: &str = "db-";
+const BACKUP_EXTENSION: &str = "sqlite";
+
+pub fn list_backups() -> IpcResult<Vec<BackupEntry>> {
+    let dir = config::backups_dir();
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|err| io_error(&dir, err, "read backups folder"))? {
+        let entry = entry.map_err(|err| io_error(&dir, err, "read backups folder"))?;
+        let path = entry.path();
+        let Some(file_name) = backup_file_name(&path) else {
+            continue;
+        };
+        let metadata = entry
+            .metadata()
+            .map_err(|err| io_error(&path, err, "read backup metadata"))?;
+        let created_at = metadata
+            .modified()
+            .map(|time| DateTime::<Utc>::from(time).to_rfc3339())
+            .unwrap_or_default();
+
+        entries.push(BackupEntry {
+            schema_version: parse_schema_version(&file_name),
+            file_name,
+            path: path.to_string_lossy().into_owned(),
+            size: i64::try_from(metadata.len()).unwrap_or(i64::MAX),
+            created_at,
+        });
+    }
+
+    // File names embed a sortable UTC timestamp, newest first.
+    entries.sort_by(|a, b| b.file_name.cmp(&a.file_name));
+    Ok(entries)
+}
+
+pub fn create_backup(db: &Db) -> IpcResult<BackupEntry> {
+    let conn = db.connection();
+    backup_connection(&conn)
+}
+
+/// Copies the live database through SQLite's online backup API so pages still
+/// sitting in the WAL are included, then prunes copies beyond the retention limit.
+pub fn backup_connection(conn: &Connection) -> IpcResult<BackupEntry> {
+    let entry = write_backup(conn)?;
+    prune_backups(retention(), None)?;
+    Ok(entry)
+}
+
+/// Replaces the live database with the named backup and migrates it to the
+/// current schema. A safety copy of the current state is taken first so the
+/// restore itself can be undone; it is put back when the migration fails.
+/// Backups written by a newer version are refused. When the migration asks for
+/// a reindex the search index is marked outdated.
+pub fn restore_backup(db: &Db, file_name: &str) -> IpcResult<BackupEntry> {
+    let path = resolve_backup(file_name)?;
+    let entry = list_backups()?
+        .into_iter()
+        .find(|entry| entry.file_name == file_name.trim())
+        .ok_or_else(|| {
+            IpcError::new(IpcStatus::NotFound, format!("Backup {file_name} not found"))
+        })?;
+
+    let version = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|backup| migration::schema_version(&backup))
+        .map_err(db_error)?;
+    let supported = migration::latest_version();
+    if version > supported {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            format!(
+                "Backup {} has schema version {version}, newer than the supported version \
+                 {supported}",
+                entry.file_name
+            ),
+        ));
+    }
+
+    let mut conn = db.connection();
+    let safety = write_backup(&conn)?;
+
+    conn.restore(DatabaseName::Main, &path, None::<fn(Progress)>)
+        .map_err(db_error)?;
+    search::rebuild::discard(&conn).map_err(db_error)?;
+
+    let report = match migration::migrate(&mut conn) {
+        Ok(report) => report,
+        Err(err) => {
+            conn.restore(DatabaseName::Main, &safety.path, None::<fn(Progress)>)
+                .map_err(db_error)?;
+            return Err(IpcError::new(
+                IpcStatus::DbError,
+                format!("Failed to migrate backup {}: {err:#}", entry.file_name),
+            ));
+        }
+    };
+    if report.reindex_required {
+        search::rebuild::mark_outdated(&conn).map_err(db_error)?;
+    }
+
+    prune_backups(retention(), Some(&entry.file_name))?;
+
+    tracing::info!(
+        target = "backup",
+        file_name = %entry.file_name,
+        from_version = report.from_version,
+        to_version = report.to_version,
+        "database restored from backup"
+    );
+
+    Ok(entry)
+}
+
+/// Number of backups to keep, from the settings.
+fn retention() -> usize {
+    config::load_settings()
+        .unwrap_or_default()
+        .backup_retention
+        .max(1)
+}
+
+fn write_backup(conn: &Connection) -> IpcResult<BackupEntry> {
+    let dir = config::backups_dir();
+    fs::create_dir_all(&dir).map_err(|err| io_error(&dir, err, "create backups folder"))?;
+
+    let version = migration::schema_version(conn).map_err(db_error)?;
+    let now = Utc::now();
+    let file_name = format!(
+        "{BACKUP_PREFIX}{}-v{version}.{BACKUP_EXTENSION}",
+        now.format("%Y%m%dT%H%M%S%3fZ")
+    );
+    let path = dir.join(&file_name);
+
+    conn.backup(DatabaseName::Main, &path, None)
+        .map_err(db_error)?;
+
+    let size = fs::metadata(&path)
+        .map(|metadata| i64::try_from(metadata.len()).unwrap_or(i64::MAX))
+        .map_err(|err| io_error(&path, err, "read backup metadata"))?;
+
+    tracing::info!(target = "backup", %file_name, version, "database backup created");
+
+    Ok(BackupEntry {
+        file_name,
+        path: path.to_string_lossy().into_owned(),
+        size,
+        schema_version: Some(version),
+        created_at: now.to_rfc3339(),
+    })
+}
+
+fn resolve_backup(file_name: &str) -> IpcResult<PathBuf> {
+    let trimmed = file_name.trim();
+    if trimmed.is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "Backup file name is required",
+        ));
+    }
+
+    let candidate = Path::new(trimmed);
+    if candidate.file_name().and_then(|name| name.to_str()) != Some(trimmed)
+        || backup_file_name(candidate).is_none()
+    {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            format!("Invalid backup file name: {trimmed}"),
+        ));
+    }
+
+    let path = config::backups_dir().join(trimmed);
+    if !path.is_file() {
+        return Err(IpcError::new(
+            IpcStatus::NotFound,
+            format!("Backup {trimmed} not found"),
+        ));
+    }
+    Ok(path)
+}
+
+fn prune_backups(keep: usize, protected: Option<&str>) -> IpcResult<()> {
+    let stale = list_backups()?
+        .into_iter()
+        .skip(keep)
+        .filter(|entry| Some(entry.file_name.as_str()) != protected);
+    for stale in stale {
+        let path = PathBuf::from(&stale.path);
+        fs::remove_file(&path).map_err(|err| io_error(&path, err, "remove old backup"))?;
+    }
+    Ok(())
+}
+
+fn backup_file_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let is_backup = name.starts_with(BACKUP_PREFIX)
+        && path.extension().and_then(|ext| ext.to_str()) == Some(BACKUP_EXTENSION);
+    is_backup.then(|| name.to_string())
+}
+
+fn parse_schema_version(file_name: &str) -> Option<i64> {
+    let stem = file_name.strip_suffix(&format!(".{BACKUP_EXTENSION}"))?;
+    let (_, version) = stem.rsplit_once("-v")?;
+    version.parse().ok()
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
+
+fn io_error(path: &Path, err: io::Error, ctx: &str) -> IpcError {
+    IpcError::new(
+        IpcStatus::IoError,
+        format!("Failed to {ctx}: {} ({})", path.display(), err),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::AppSettings,
+        services::{repo, test_support},
+    };
+
+    /// Writes a backup holding a workspace named `workspace` at schema
+    /// `version`; `prepare` runs on the database before it is copied.
+    fn write_backup_file(
+        file_name: &str,
+        version: i64,
+        workspace: &str,
+        prepare: impl FnOnce(&Connection),
+    ) {
+        let db = test_support::migrated_db();
+        repo::create_workspace(&db, workspace).unwrap();
+        let conn = db.connection();
+        prepare(&conn);
+        conn.pragma_update(None, "user_version", version).unwrap();
+        let dir = config::backups_dir();
+        fs::create_dir_all(&dir).unwrap();
+        conn.backup(DatabaseName::Main, dir.join(file_name), None)
+            .unwrap();
+    }
+
+    /// A database in the state of schema 14, before the bibliographic columns
+    /// existed.
+    fn at_version_14(conn: &Connection) {
+        conn.execute_batch(
+            "ALTER TABLE paper DROP COLUMN venue; ALTER TABLE paper DROP COLUMN year; \
+             ALTER TABLE paper DROP COLUMN abstract; ALTER TABLE paper DROP COLUMN url; \
+             ALTER TABLE paper DROP COLUMN editedAt;",
+        )
+        .unwrap();
+    }
+
+    fn live_db(workspace: &str) -> Db {
+        let db = test_support::migrated_db();
+        repo::create_workspace(&db, workspace).unwrap();
+        db
+    }
+
+    fn workspace_names(db: &Db) -> Vec<String> {
+        repo::list_workspaces(db)
+            .unwrap()
+            .into_iter()
+            .map(|workspace| workspace.name)
+            .collect()
+    }
+
+    fn backup_names() -> Vec<String> {
+        list_backups()
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.file_name)
+            .collect()
+    }
+
+    #[test]
+    fn backups_from_a_newer_version_are_refused() {
+        let _data_dir = test_support::data_dir();
+        let newer = migration::latest_version() + 1;
+        let file_name = format!("db-20240101T000000000Z-v{newer}.sqlite");
+        write_backup_file(&file_name, newer, "Newer", |_| {});
+        let db = live_db("Live");
+
+        let err = restore_backup(&db, &file_name).unwrap_err();
+
+        assert!(matches!(err.code, IpcStatus::BadRequest));
+        assert_eq!(workspace_names(&db), ["Live"]);
+        // Refused before the safety copy is taken.
+        assert_eq!(backup_names(), [file_name]);
+    }
+
+    #[test]
+    fn restore_takes_a_safety_copy_and_migrates_the_backup() {
+        let _data_dir = test_support::data_dir();
+        let file_name = "db-20240101T000000000Z-v14.sqlite";
+        write_backup_file(file_name, 14, "Old", at_version_14);
+        let db = live_db("Live");
+
+        let entry = restore_backup(&db, file_name).unwrap();
+
+        assert_eq!(entry.file_name, file_name);
+        assert_eq!(workspace_names(&db), ["Old"]);
+        let conn = db.connection();
+        assert_eq!(
+            migration::schema_version(&conn).unwrap(),
+            migration::latest_version()
+        );
+        conn.execute("UPDATE paper SET venue = NULL", []).unwrap();
+        drop(conn);
+
+        let backups = list_backups().unwrap();
+        assert_eq!(backups.len(), 2);
+        let safety = &backups[0];
+        assert_ne!(safety.file_name, file_name);
+        let safety = Connection::open(&safety.path).unwrap();
+        let name: String = safety
+            .query_row("SELECT name FROM workspace", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(name, "Live");
+    }
+
+    #[test]
+    fn failed_migration_puts_the_safety_copy_back() {
+        let _data_dir = test_support::data_dir();
+        let file_name = "db-20240101T000000000Z-v14.sqlite";
+        // Keeps a column 0015 adds, so the migration fails on it.
+        write_backup_file(file_name, 14, "Old", |conn| {
+            at_version_14(conn);
+            conn.execute_batch("ALTER TABLE paper ADD COLUMN url TEXT;")
+                .unwrap();
+        });
+        let db = live_db("Live");
+
+        let err = restore_backup(&db, file_name).unwrap_err();
+
+        assert!(matches!(err.code, IpcStatus::DbError));
+        assert_eq!(workspace_names(&db), ["Live"]);
+        assert_eq!(
+            migration::schema_version(&db.connection()).unwrap(),
+            migration::latest_version()
+        );
+        assert_eq!(list_backups().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn restore_prunes_to_the_retention_but_keeps_the_restored_backup() {
+        let _data_dir = test_support::data_dir();
+        config::save_settings(&AppSettings {
+            backup_retention: 2,
+            ..AppSettings::default()
+        })
+        .unwrap();
+        let version = migration::latest_version();
+        let names = ["20240101", "20240102", "20240103"]
+            .map(|day| format!("db-{day}T000000000Z-v{version}.sqlite"));
+        for name in &names {
+            write_backup_file(name, version, name, |_| {});
+        }
+        let db = live_db("Live");
+
+        restore_backup(&db, &names[0]).unwrap();
+
+        let backups = backup_names();
+        assert_eq!(backups.len(), 3);
+        // The safety copy is the newest one.
+        assert!(!names.contains(&backups[0]));
+        assert_eq!(backups[1..], [names[2].clone(), names[0].clone()]);
+        assert_eq!(workspace_names(&db), [names[0].clone()]);
+    }
+}
@@ -0,0 +1,1065 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::{params, OptionalExtension};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        NoteStats, NoteTagLink, Paper, PaperStats, PaperTagLink, Tag, WorkspaceBundle,
+        WorkspaceExportReport, WorkspaceImportReport, WorkspaceImportRequest,
+    },
+    telemetry::{IpcError, IpcResult, IpcStatus},
+    utils::time::now_iso,
+};
+
+use super::{authors, config, highlights, pdf, repo, search, tags, Db};
+
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+pub const MANIFEST_FILE: &str = "workspace.json";
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// Writes `workspace.json` plus an `attachments/` folder holding every paper
+/// PDF named by its `fileHash`. The destination must be missing or empty.
+pub fn export_workspace(
+    db: &Db,
+    workspace_id: &str,
+    destination: &str,
+) -> IpcResult<WorkspaceExportReport> {
+    if destination.trim().is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "Export destination is required",
+        ));
+    }
+
+    let workspace = repo::get_workspace(db, workspace_id)?;
+    let papers = repo::list_papers(db, &workspace.id)?;
+    let mut notes = Vec::new();
+    let mut paper_highlights = Vec::new();
+    for paper in &papers {
+        notes.extend(repo::list_notes(db, &paper.id)?);
+        paper_highlights.extend(highlights::list(db, &paper.id)?);
+    }
+
+    let (tags, note_tags, paper_tags, note_stats, paper_stats) = {
+        let conn = db.connection();
+        (
+            list_workspace_tags(&conn, &workspace.id).map_err(db_error)?,
+            list_workspace_note_tags(&conn, &workspace.id).map_err(db_error)?,
+            list_workspace_paper_tags(&conn, &workspace.id).map_err(db_error)?,
+            list_workspace_note_stats(&conn, &workspace.id).map_err(db_error)?,
+            list_workspace_paper_stats(&conn, &workspace.id).map_err(db_error)?,
+        )
+    };
+
+    let root = PathBuf::from(destination.trim());
+    prepare_destination(&root)?;
+    let attachments_dir = root.join(ATTACHMENTS_DIR);
+    fs::create_dir_all(&attachments_dir)
+        .map_err(|err| io_error(&attachments_dir, err, "create attachments folder"))?;
+
+    let mut written = HashSet::new();
+    let mut missing_attachments = Vec::new();
+    for paper in &papers {
+        if written.contains(&paper.file_hash) {
+            continue;
+        }
+
+        let source = [Some(paper.path.as_str()), paper.last_seen_path.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(Path::new)
+            .find(|candidate| candidate.is_file());
+        let Some(source) = source else {
+            missing_attachments.push(paper.id.clone());
+            continue;
+        };
+
+        let target = attachments_dir.join(attachment_file_name(&paper.file_hash));
+        fs::copy(source, &target).map_err(|err| io_error(source, err, "copy attachment"))?;
+        written.insert(paper.file_hash.clone());
+    }
+
+    let report = WorkspaceExportReport {
+        path: root.to_string_lossy().into_owned(),
+        papers: papers.len(),
+        notes: notes.len(),
+        highlights: paper_highlights.len(),
+        tags: tags.len(),
+        attachments: written.len(),
+        missing_attachments,
+    };
+
+    let bundle = WorkspaceBundle {
+        format_version: BUNDLE_FORMAT_VERSION,
+        exported_at: now_iso(),
+        workspace,
+        papers,
+        notes,
+        highlights: paper_highlights,
+        tags,
+        note_tags,
+        paper_tags,
+        note_stats,
+        paper_stats,
+    };
+
+    let manifest_path = root.join(MANIFEST_FILE);
+    let file = fs::File::create(&manifest_path)
+        .map_err(|err| io_error(&manifest_path, err, "create workspace.json"))?;
+    serde_json::to_writer_pretty(io::BufWriter::new(file), &bundle).map_err(|err| {
+        IpcError::new(
+            IpcStatus::Internal,
+            format!("Failed to write workspace.json: {err}"),
+        )
+    })?;
+
+    Ok(report)
+}
+
+/// Reads a `workspace.json + attachments/` bundle into the database in a single
+/// transaction. Papers are deduplicated by `fileHash`, tags by name, and any
+/// bundle id that collides with an unrelated row is replaced by a fresh one.
+pub fn import_workspace(
+    db: &Db,
+    request: &WorkspaceImportRequest,
+) -> IpcResult<WorkspaceImportReport> {
+    let (root, bundle) = read_bundle(&request.source)?;
+    let attachments = resolve_attachments(&root, &bundle.papers)?;
+
+    let mut report = WorkspaceImportReport::default();
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    let now = now_iso();
+
+    // Workspace: merge into the requested target or create one from the bundle.
+    let target_id = request
+        .target_workspace_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty());
+    report.workspace_id = if let Some(target_id) = target_id {
+        if !row_exists(&tx, "workspace", target_id)? {
+            return Err(IpcError::new(
+                IpcStatus::NotFound,
+                format!("Workspace {target_id} not found"),
+            ));
+        }
+        target_id.to_string()
+    } else {
+        let name = bundle.workspace.name.trim();
+        let name = if name.is_empty() { "Imported" } else { name };
+        let workspace_id = if bundle.workspace.id.trim().is_empty()
+            || row_exists(&tx, "workspace", &bundle.workspace.id)?
+        {
+            repo::generate_workspace_id(&tx, name).map_err(db_error)?
+        } else {
+            bundle.workspace.id.clone()
+        };
+        tx.execute(
+            "INSERT INTO workspace (id, name, createdAt, updatedAt) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                &workspace_id,
+                name,
+                non_empty_or(&bundle.workspace.created_at, &now),
+                &now
+            ],
+        )
+        .map_err(db_error)?;
+        report.workspace_created = true;
+        workspace_id
+    };
+
+    // Papers: reuse rows with the same fileHash, otherwise copy the attachment
+    // into the library folder and insert a new row.
+    let paper_stats: HashMap<&str, &PaperStats> = bundle
+        .paper_stats
+        .iter()
+        .map(|stats| (stats.paper_id.as_str(), stats))
+        .collect();
+    let mut paper_ids: HashMap<String, String> = HashMap::new();
+    let mut merged_papers = HashSet::new();
+    let mut created_papers = Vec::new();
+    for paper in &bundle.papers {
+        let existing: Option<String> = tx
+            .query_row(
+                "SELECT id FROM paper WHERE fileHash = ?1 LIMIT 1",
+                params![&paper.file_hash],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)?;
+
+        if let Some(existing_id) = existing {
+            merged_papers.insert(existing_id.clone());
+            paper_ids.insert(paper.id.clone(), existing_id);
+            report.merged.papers += 1;
+            continue;
+        }
+
+        let Some(source) = attachments.get(&paper.id) else {
+            report.skipped.papers += 1;
+            report.warnings.push(format!(
+                "Paper \"{}\" skipped: attachment {} is missing",
+                paper.title,
+                attachment_file_name(&paper.file_hash)
+            ));
+            continue;
+        };
+
+        let stored = store_attachment(source, &paper.file_hash)?;
+        let stored_str = stored.to_string_lossy().into_owned();
+        let paper_id = fresh_id(&tx, "paper", &paper.id)?;
+        if paper_id != paper.id {
+            report.remapped.papers += 1;
+        }
+
+        let read_status = repo::validate_read_status(&paper.read_status).unwrap_or("unread");
+        tx.execute(
+            "INSERT INTO paper \
+             (id, workspaceId, title, doi, path, lastSeenPath, fileHash, filesize, createdAt, \
+              updatedAt, readStatus, subject, keywords, pdfCreatedAt, pageCount, arxivId, \
+              metadataHash, venue, year, abstract, url, editedAt) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?6, \
+              ?16, ?17, ?18, ?19, ?20)",
+            params![
+                &paper_id,
+                &report.workspace_id,
+                &paper.title,
+                paper.doi.as_deref(),
+                &stored_str,
+                &paper.file_hash,
+                paper.filesize,
+                non_empty_or(&paper.created_at, &now),
+                &now,
+                read_status,
+                paper.subject.as_deref(),
+                paper.keywords.as_deref(),
+                paper.pdf_created_at.as_deref(),
+                paper.page_count,
+                paper.arxiv_id.as_deref(),
+                paper.venue.as_deref(),
+                paper.year,
+                paper.r#abstract.as_deref(),
+                paper.url.as_deref(),
+                paper.edited_at.as_deref()
+            ],
+        )
+        .map_err(db_error)?;
+        // The bundle carries the metadata, so it is not read from the file again.
+        authors::set_paper_authors(&tx, &paper_id, &paper.authors).map_err(db_error)?;
+        search::upsert_paper_entry(&tx, &paper_id).map_err(db_error)?;
+
+        let stats = paper_stats.get(paper.id.as_str());
+        tx.execute(
+            "INSERT OR IGNORE INTO paper_stats \
+             (paperId, totalReadTime, lastOpenedPage, lastOpenedAt) \
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                &paper_id,
+                stats.map(|stats| stats.total_read_time).unwrap_or(0),
+                stats.and_then(|stats| stats.last_opened_page),
+                stats.and_then(|stats| stats.last_opened_at.as_deref())
+            ],
+        )
+        .map_err(db_error)?;
+
+        created_papers.push(paper_id.clone());
+        paper_ids.insert(paper.id.clone(), paper_id);
+        report.created.papers += 1;
+    }
+
+    // Tags: paths are unique regardless of case, so an existing tag with the
+    // same path in any case absorbs the bundle tag. Ancestors missing locally
+    // are created.
+    let mut tag_ids: HashMap<String, String> = HashMap::new();
+    for tag in &bundle.tags {
+        let existing: Option<String> = tx
+            .query_row(
+                "SELECT id FROM tag WHERE name = ?1 COLLATE NOCASE",
+                params![&tag.name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_error)?;
+
+        if let Some(existing_id) = existing {
+            tag_ids.insert(tag.id.clone(), existing_id);
+            report.merged.tags += 1;
+            continue;
+        }
+
+        let tag_id = fresh_id(&tx, "tag", &tag.id)?;
+        if tag_id != tag.id {
+            report.remapped.tags += 1;
+        }
+        let (parent_id, name) = tags::resolve_parent(&tx, &tag.name)?;
+        tx.execute(
+            "INSERT INTO tag (id, name, parentId, color, createdAt) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                &tag_id,
+                &name,
+                parent_id.as_deref(),
+                tag.color.as_deref(),
+                non_empty_or(&tag.created_at, &now)
+            ],
+        )
+        .map_err(db_error)?;
+        search::upsert_entry(&tx, search::TAG_REF_TYPE, &tag_id, &name).map_err(db_error)?;
+        tag_ids.insert(tag.id.clone(), tag_id);
+        report.created.tags += 1;
+    }
+
+    // Notes: attach to the mapped paper; on merged papers a note with the same
+    // page and content is treated as already imported.
+    let note_stats: HashMap<&str, &NoteStats> = bundle
+        .note_stats
+        .iter()
+        .map(|stats| (stats.note_id.as_str(), stats))
+        .collect();
+    let mut note_ids: HashMap<String, String> = HashMap::new();
+    for note in &bundle.notes {
+        let Some(paper_id) = paper_ids.get(&note.paper_id) else {
+            report.skipped.notes += 1;
+            continue;
+        };
+
+        if merged_papers.contains(paper_id) {
+            let duplicate: Option<String> = tx
+                .query_row(
+                    "SELECT id FROM note WHERE paperId = ?1 AND page = ?2 AND content = ?3 LIMIT 1",
+                    params![paper_id, note.page, &note.content],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(db_error)?;
+            if let Some(duplicate) = duplicate {
+                note_ids.insert(note.id.clone(), duplicate);
+                report.skipped.notes += 1;
+                continue;
+            }
+        }
+
+        let note_id = fresh_id(&tx, "note", &note.id)?;
+        if note_id != note.id {
+            report.remapped.notes += 1;
+        }
+        let anchor = note.anchor.as_ref();
+        // Bundles written before positions were normalized may hold raw points.
+        let legacy_position = matches!(
+            (note.x, note.y),
+            (Some(x), Some(y)) if pdf::geometry::validate_position(x, y).is_err()
+        );
+        let page_geometry = note
+            .page_geometry
+            .as_ref()
+            .map(pdf::geometry::encode_geometry)
+            .transpose()?;
+        tx.execute(
+            "INSERT INTO note \
+             (id, paperId, page, x, y, content, color, createdAt, updatedAt, pageGeometry, \
+              positionLegacy, anchorQuote, anchorPrefixHash, anchorSuffixHash, anchorParagraph, \
+              anchorStatus, anchorFileHash) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            params![
+                &note_id,
+                paper_id,
+                note.page,
+                note.x,
+                note.y,
+                &note.content,
+                note.color.as_deref(),
+                non_empty_or(&note.created_at, &now),
+                non_empty_or(&note.updated_at, &now),
+                page_geometry,
+                legacy_position,
+                anchor.map(|anchor| anchor.quote.as_str()),
+                anchor.and_then(|anchor| anchor.prefix_hash.as_deref()),
+                anchor.and_then(|anchor| anchor.suffix_hash.as_deref()),
+                anchor.and_then(|anchor| anchor.paragraph),
+                anchor.map(|anchor| anchor.status.as_str()),
+                anchor.and_then(|anchor| anchor.file_hash.as_deref())
+            ],
+        )
+        .map_err(db_error)?;
+
+        let stats = note_stats.get(note.id.as_str());
+        tx.execute(
+            "INSERT OR IGNORE INTO note_stats (noteId, reviewCount, lastReviewedAt) \
+             VALUES (?1, ?2, ?3)",
+            params![
+                &note_id,
+                stats.map(|stats| stats.review_count).unwrap_or(0),
+                stats.and_then(|stats| stats.last_reviewed_at.as_deref())
+            ],
+        )
+        .map_err(db_error)?;
+
+        search::upsert_entry(&tx, search::NOTE_REF_TYPE, &note_id, &note.content)
+            .map_err(db_error)?;
+
+        if merged_papers.contains(paper_id) {
+            report.merged.notes += 1;
+        } else {
+            report.created.notes += 1;
+        }
+        note_ids.insert(note.id.clone(), note_id);
+    }
+
+    // Highlights: same rules as notes, duplicates match on page and text.
+    for highlight in &bundle.highlights {
+        let Some(paper_id) = paper_ids.get(&highlight.paper_id) else {
+            report.skipped.highlights += 1;
+            continue;
+        };
+        let page = match highlights::validate_rects(&highlight.rects) {
+            Ok(page) if !highlight.text.trim().is_empty() => page,
+            _ => {
+                report.skipped.highlights += 1;
+                report
+                    .warnings
+                    .push(format!("Highlight {} is invalid; skipped", highlight.id));
+                continue;
+            }
+        };
+
+        if merged_papers.contains(paper_id) {
+            let duplicate = tx
+                .query_row(
+                    "SELECT 1 FROM highlight WHERE paperId = ?1 AND page = ?2 AND text = ?3 LIMIT 1",
+                    params![paper_id, page, highlight.text.trim()],
+                    |row| row.get::<_, i64>(0),
+                )
+                .optional()
+                .map_err(db_error)?;
+            if duplicate.is_some() {
+                report.skipped.highlights += 1;
+                continue;
+            }
+        }
+
+        let highlight_id = fresh_id(&tx, "highlight", &highlight.id)?;
+        if highlight_id != highlight.id {
+            report.remapped.highlights += 1;
+        }
+        tx.execute(
+            "INSERT INTO highlight \
+             (id, paperId, page, rects, text, color, comment, createdAt, updatedAt) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                &highlight_id,
+                paper_id,
+                page,
+                highlights::encode_rects(&highlight.rects)?,
+                highlight.text.trim(),
+                highlight.color.as_deref(),
+                highlight.comment.as_deref(),
+                non_empty_or(&highlight.created_at, &now),
+                non_empty_or(&highlight.updated_at, &now)
+            ],
+        )
+        .map_err(db_error)?;
+
+        search::upsert_entry(
+            &tx,
+            search::HIGHLIGHT_REF_TYPE,
+            &highlight_id,
+            highlight.text.trim(),
+        )
+        .map_err(db_error)?;
+
+        if merged_papers.contains(paper_id) {
+            report.merged.highlights += 1;
+        } else {
+            report.created.highlights += 1;
+        }
+    }
+
+    let mut tagged_notes = Vec::new();
+    for link in &bundle.note_tags {
+        let (Some(note_id), Some(tag_id)) =
+            (note_ids.get(&link.note_id), tag_ids.get(&link.tag_id))
+        else {
+            continue;
+        };
+        tx.execute(
+            "INSERT OR IGNORE INTO note_tag (noteId, tagId) VALUES (?1, ?2)",
+            params![note_id, tag_id],
+        )
+        .map_err(db_error)?;
+        tagged_notes.push(note_id.clone());
+    }
+    tagged_notes.sort();
+    tagged_notes.dedup();
+    tags::reindex_notes(&tx, &tagged_notes).map_err(db_error)?;
+
+    for link in &bundle.paper_tags {
+        let (Some(paper_id), Some(tag_id)) =
+            (paper_ids.get(&link.paper_id), tag_ids.get(&link.tag_id))
+        else {
+            continue;
+        };
+        tx.execute(
+            "INSERT OR IGNORE INTO paper_tag (paperId, tagId) VALUES (?1, ?2)",
+            params![paper_id, tag_id],
+        )
+        .map_err(db_error)?;
+    }
+
+    tx.commit().map_err(db_error)?;
+    drop(conn);
+
+    let created = created_papers
+        .iter()
+        .map(|paper_id| repo::get_paper(db, paper_id))
+        .collect::<IpcResult<Vec<_>>>()?;
+    pdf::text::index_papers(db, &created);
+    if let Err(err) = pdf::geometry::convert_legacy_notes(db) {
+        tracing::warn!(target = "bundle", error = %err, "failed to convert legacy note positions");
+    }
+
+    Ok(report)
+}
+
+pub fn attachment_file_name(file_hash: &str) -> String {
+    format!("{file_hash}.pdf")
+}
+
+fn prepare_destination(root: &Path) -> IpcResult<()> {
+    if root.exists() {
+        let mut entries =
+            fs::read_dir(root).map_err(|err| io_error(root, err, "read export destination"))?;
+        if entries.next().is_some() {
+            return Err(IpcError::new(
+                IpcStatus::Conflict,
+                format!("Export destination is not empty: {}", root.display()),
+            ));
+        }
+        return Ok(());
+    }
+
+    fs::create_dir_all(root).map_err(|err| io_error(root, err, "create export destination"))
+}
+
+fn read_bundle(source: &str) -> IpcResult<(PathBuf, WorkspaceBundle)> {
+    let trimmed = source.trim();
+    if trimmed.is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "Import source is required",
+        ));
+    }
+
+    let source = PathBuf::from(trimmed);
+    let (root, manifest_path) = if source.is_dir() {
+        (source.clone(), source.join(MANIFEST_FILE))
+    } else {
+        let root = source
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        (root, source)
+    };
+
+    let data = fs::read_to_string(&manifest_path)
+        .map_err(|err| io_error(&manifest_path, err, "read workspace.json"))?;
+    let bundle: WorkspaceBundle = serde_json::from_str(&data).map_err(|err| {
+        IpcError::new(
+            IpcStatus::BadRequest,
+            format!("Invalid workspace.json: {err}"),
+        )
+        .with_details(serde_json::json!({ "line": err.line(), "column": err.column() }))
+    })?;
+
+    if bundle.format_version > BUNDLE_FORMAT_VERSION {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            format!(
+                "Bundle format {} is newer than the supported format {BUNDLE_FORMAT_VERSION}",
+                bundle.format_version
+            ),
+        ));
+    }
+
+    Ok((root, bundle))
+}
+
+/// Maps bundle paper ids to attachment files whose content matches the
+/// declared `fileHash`. A mismatching attachment rejects the whole bundle.
+fn resolve_attachments(root: &Path, papers: &[Paper]) -> IpcResult<HashMap<String, PathBuf>> {
+    let attachments_dir = root.join(ATTACHMENTS_DIR);
+    let mut resolved = HashMap::new();
+    for paper in papers {
+        if paper.file_hash.trim().is_empty() {
+            return Err(IpcError::new(
+                IpcStatus::BadRequest,
+                format!("Paper {} has no fileHash", paper.id),
+            ));
+        }
+
+        let candidate = attachments_dir.join(attachment_file_name(&paper.file_hash));
+        if !candidate.is_file() {
+            continue;
+        }
+
+        let actual = repo::compute_file_hash(&candidate)?;
+        if actual != paper.file_hash {
+            return Err(IpcError::new(
+                IpcStatus::BadRequest,
+                format!("Attachment hash mismatch: {}", candidate.display()),
+            ));
+        }
+        resolved.insert(paper.id.clone(), candidate);
+    }
+    Ok(resolved)
+}
+
+/// Copies an attachment into the library folder. Files are content-addressed,
+/// so an aborted import only leaves behind copies a later import would reuse.
+fn store_attachment(source: &Path, file_hash: &str) -> IpcResult<PathBuf> {
+    let library = config::library_dir();
+    fs::create_dir_all(&library).map_err(|err| io_error(&library, err, "create library folder"))?;
+
+    let target = library.join(attachment_file_name(file_hash));
+    if !target.is_file() {
+        fs::copy(source, &target).map_err(|err| io_error(source, err, "copy attachment"))?;
+    }
+    Ok(fs::canonicalize(&target).unwrap_or(target))
+}
+
+fn row_exists(conn: &rusqlite::Connection, table: &str, id: &str) -> IpcResult<bool> {
+    conn.query_row(
+        &format!("SELECT 1 FROM {table} WHERE id = ?1"),
+        params![id],
+        |row| row.get::<_, i64>(0),
+    )
+    .optional()
+    .map(|value| value.is_some())
+    .map_err(db_error)
+}
+
+fn fresh_id(conn: &rusqlite::Connection, table: &str, preferred: &str) -> IpcResult<String> {
+    if !preferred.trim().is_empty() && !row_exists(conn, table, preferred)? {
+        return Ok(preferred.to_string());
+    }
+    Ok(Uuid::new_v4().to_string())
+}
+
+fn non_empty_or<'a>(value: &'a str, fallback: &'a str) -> &'a str {
+    if value.trim().is_empty() {
+        fallback
+    } else {
+        value
+    }
+}
+
+fn list_workspace_tags(
+    conn: &rusqlite::Connection,
+    workspace_id: &str,
+) -> rusqlite::Result<Vec<Tag>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.name, t.parentId, t.color, t.createdAt, COUNT(*) AS usageCount \
+         FROM tag t \
+         JOIN ( \
+           SELECT nt.tagId FROM note_tag nt \
+           JOIN note n ON n.id = nt.noteId \
+           JOIN paper p ON p.id = n.paperId \
+           WHERE p.workspaceId = ?1 \
+           UNION ALL \
+           SELECT pt.tagId FROM paper_tag pt \
+           JOIN paper p ON p.id = pt.paperId \
+           WHERE p.workspaceId = ?1 \
+         ) used ON used.tagId = t.id \
+         GROUP BY t.id \
+         ORDER BY t.name ASC",
+    )?;
+    let tags = stmt
+        .query_map(params![workspace_id], |row| {
+            Ok(Tag {
+                id: row.get("id")?,
+                name: row.get("name")?,
+                parent_id: row.get("parentId")?,
+                color: row.get("color")?,
+                created_at: row.get("createdAt")?,
+                usage_count: row.get("usageCount")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tags)
+}
+
+fn list_workspace_note_tags(
+    conn: &rusqlite::Connection,
+    workspace_id: &str,
+) -> rusqlite::Result<Vec<NoteTagLink>> {
+    let mut stmt = conn.prepare(
+        "SELECT nt.noteId, nt.tagId \
+         FROM note_tag nt \
+         JOIN note n ON n.id = nt.noteId \
+         JOIN paper p ON p.id = n.paperId \
+         WHERE p.workspaceId = ?1",
+    )?;
+    let links = stmt
+        .query_map(params![workspace_id], |row| {
+            Ok(NoteTagLink {
+                note_id: row.get("noteId")?,
+                tag_id: row.get("tagId")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(links)
+}
+
+fn list_workspace_paper_tags(
+    conn: &rusqlite::Connection,
+    workspace_id: &str,
+) -> rusqlite::Result<Vec<PaperTagLink>> {
+    let mut stmt = conn.prepare(
+        "SELECT pt.paperId, pt.tagId \
+         FROM paper_tag pt \
+         JOIN paper p ON p.id = pt.paperId \
+         WHERE p.workspaceId = ?1",
+    )?;
+    let links = stmt
+        .query_map(params![workspace_id], |row| {
+            Ok(PaperTagLink {
+                paper_id: row.get("paperId")?,
+                tag_id: row.get("tagId")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(links)
+}
+
+fn list_workspace_note_stats(
+    conn: &rusqlite::Connection,
+    workspace_id: &str,
+) -> rusqlite::Result<Vec<NoteStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.noteId, s.reviewCount, s.lastReviewedAt \
+         FROM note_stats s \
+         JOIN note n ON n.id = s.noteId \
+         JOIN paper p ON p.id = n.paperId \
+         WHERE p.workspaceId = ?1",
+    )?;
+    let stats = stmt
+        .query_map(params![workspace_id], |row| {
+            Ok(NoteStats {
+                note_id: row.get("noteId")?,
+                review_count: row.get::<_, Option<i64>>("reviewCount")?.unwrap_or(0),
+                last_reviewed_at: row.get("lastReviewedAt")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(stats)
+}
+
+fn list_workspace_paper_stats(
+    conn: &rusqlite::Connection,
+    workspace_id: &str,
+) -> rusqlite::Result<Vec<PaperStats>> {
+    let mut stmt = conn.prepare(
+        "SELECT s.paperId, s.totalReadTime, s.lastOpenedPage, s.lastOpenedAt \
+         FROM paper_stats s \
+         JOIN paper p ON p.id = s.paperId \
+         WHERE p.workspaceId = ?1",
+    )?;
+    let stats = stmt
+        .query_map(params![workspace_id], |row| {
+            Ok(PaperStats {
+                paper_id: row.get("paperId")?,
+                total_read_time: row.get::<_, Option<i64>>("totalReadTime")?.unwrap_or(0),
+                last_opened_page: row.get("lastOpenedPage")?,
+                last_opened_at: row.get("lastOpenedAt")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(stats)
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
+
+fn io_error(path: &Path, err: io::Error, ctx: &str) -> IpcError {
+    IpcError::new(
+        IpcStatus::IoError,
+        format!("Failed to {ctx}: {} ({})", path.display(), err),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::{NewNote, Note, PaperImportRequest, Workspace},
+        services::test_support::{data_dir, migrated_db, TempDir},
+    };
+
+    /// A bundle folder with two papers, a tagged note on the first paper and
+    /// a tag on the second.
+    fn write_bundle(dir: &TempDir) -> (PathBuf, WorkspaceBundle) {
+        let root = dir.path().join("bundle");
+        let mut papers = Vec::new();
+        for (id, title) in [("paper-a", "Attention"), ("paper-b", "Contrastive")] {
+            let source = dir.write_pdf(&format!("sources/{id}.pdf"), title);
+            let file_hash = repo::compute_file_hash(&source).unwrap();
+            let attachment = root
+                .join(ATTACHMENTS_DIR)
+                .join(attachment_file_name(&file_hash));
+            fs::create_dir_all(attachment.parent().unwrap()).unwrap();
+            fs::copy(&source, &attachment).unwrap();
+            papers.push(Paper {
+                id: id.to_string(),
+                workspace_id: "ws-bundle".to_string(),
+                title: title.to_string(),
+                path: source.to_string_lossy().into_owned(),
+                file_hash,
+                filesize: Some(fs::metadata(&source).unwrap().len() as i64),
+                read_status: "unread".to_string(),
+                ..Paper::default()
+            });
+        }
+
+        let bundle = WorkspaceBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            workspace: Workspace {
+                id: "ws-bundle".to_string(),
+                name: "Bundle".to_string(),
+                ..Workspace::default()
+            },
+            papers,
+            notes: vec![Note {
+                id: "note-a".to_string(),
+                paper_id: "paper-a".to_string(),
+                page: 1,
+                content: "scaled dot-product attention".to_string(),
+                ..Note::default()
+            }],
+            tags: vec![
+                Tag {
+                    id: "tag-attention".to_string(),
+                    name: "method/Attention".to_string(),
+                    ..Tag::default()
+                },
+                Tag {
+                    id: "tag-other".to_string(),
+                    name: "method/other".to_string(),
+                    ..Tag::default()
+                },
+            ],
+            note_tags: vec![NoteTagLink {
+                note_id: "note-a".to_string(),
+                tag_id: "tag-attention".to_string(),
+            }],
+            paper_tags: vec![PaperTagLink {
+                paper_id: "paper-b".to_string(),
+                tag_id: "tag-other".to_string(),
+            }],
+            ..WorkspaceBundle::default()
+        };
+        fs::write(
+            root.join(MANIFEST_FILE),
+            serde_json::to_string(&bundle).unwrap(),
+        )
+        .unwrap();
+        (root, bundle)
+    }
+
+    fn import(db: &Db, root: &Path) -> IpcResult<WorkspaceImportReport> {
+        import_workspace(
+            db,
+            &WorkspaceImportRequest {
+                source: root.to_string_lossy().into_owned(),
+                target_workspace_id: None,
+            },
+        )
+    }
+
+    fn count(db: &Db, table: &str) -> i64 {
+        db.connection()
+            .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn reimporting_a_bundle_reuses_papers_with_the_same_file_hash() {
+        let dir = data_dir();
+        let (root, _) = write_bundle(&dir);
+        let db = migrated_db();
+
+        let first = import(&db, &root).unwrap();
+        assert_eq!(first.created.papers, 2);
+        assert_eq!(first.created.notes, 1);
+        assert_eq!(first.created.tags, 2);
+
+        let second = import(&db, &root).unwrap();
+        assert!(second.workspace_created);
+        assert_ne!(second.workspace_id, first.workspace_id);
+        assert_eq!(second.created.papers, 0);
+        assert_eq!(second.merged.papers, 2);
+        assert_eq!(second.skipped.notes, 1);
+        assert_eq!(second.merged.tags, 2);
+        assert_eq!(count(&db, "paper"), 2);
+        assert_eq!(count(&db, "note"), 1);
+        assert_eq!(count(&db, "tag"), 3);
+        assert!(repo::list_papers(&db, &second.workspace_id)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn tags_differing_only_in_case_merge_into_the_existing_tag() {
+        let dir = data_dir();
+        let (root, _) = write_bundle(&dir);
+        let db = migrated_db();
+        let existing = tags::create(&db, "Method/attention", None).unwrap();
+
+        let report = import(&db, &root).unwrap();
+        assert_eq!(report.merged.tags, 1);
+        assert_eq!(report.created.tags, 1);
+
+        let paths = tags::list(&db)
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, ["Method", "Method/attention", "Method/other"]);
+        let note_tag: String = db
+            .connection()
+            .query_row("SELECT tagId FROM note_tag", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(note_tag, existing.id);
+    }
+
+    #[test]
+    fn failed_import_rolls_back_completely() {
+        let dir = data_dir();
+        let (root, bundle) = write_bundle(&dir);
+        let db = migrated_db();
+        // The second attachment cannot be copied over a folder of its name.
+        let blocked = config::library_dir().join(attachment_file_name(&bundle.papers[1].file_hash));
+        fs::create_dir_all(&blocked).unwrap();
+
+        let err = import(&db, &root).unwrap_err();
+        assert!(matches!(err.code, IpcStatus::IoError), "{}", err.message);
+        for table in [
+            "workspace",
+            "paper",
+            "paper_author",
+            "note",
+            "tag",
+            "note_tag",
+            "paper_tag",
+            "search_index",
+        ] {
+            assert_eq!(count(&db, table), 0, "{table}");
+        }
+    }
+
+    /// A paper as `(title, fileHash, tag names, notes)`, each note as
+    /// `(page, content, tag names)`.
+    type PaperSnapshot = (String, String, Vec<String>, Vec<(i32, String, Vec<String>)>);
+
+    /// The papers of a workspace with their notes and tags, in a stable order.
+    fn snapshot(db: &Db, workspace_id: &str) -> Vec<PaperSnapshot> {
+        let tag_names = |tags: &[Tag]| tags.iter().map(|tag| tag.name.clone()).collect::<Vec<_>>();
+        let mut papers = repo::list_papers(db, workspace_id)
+            .unwrap()
+            .into_iter()
+            .map(|paper| {
+                let mut notes = repo::list_notes(db, &paper.id)
+                    .unwrap()
+                    .into_iter()
+                    .map(|note| (note.page, note.content, tag_names(&note.tags)))
+                    .collect::<Vec<_>>();
+                notes.sort();
+                (paper.title, paper.file_hash, tag_names(&paper.tags), notes)
+            })
+            .collect::<Vec<_>>();
+        papers.sort();
+        papers
+    }
+
+    #[test]
+    fn exported_workspace_imports_into_an_empty_database_unchanged() {
+        let dir = data_dir();
+        let source = migrated_db();
+        let workspace = repo::create_workspace(&source, "Reading group").unwrap();
+        let papers = repo::import_papers(
+            &source,
+            &PaperImportRequest {
+                paths: ["attention", "contrastive"]
+                    .map(|name| {
+                        let path = dir.write_pdf(&format!("papers/{name}.pdf"), name);
+                        path.to_string_lossy().into_owned()
+                    })
+                    .to_vec(),
+                workspace_id: workspace.id.clone(),
+            },
+        )
+        .unwrap();
+        let note = repo::create_note(
+            &source,
+            &NewNote {
+                paper_id: papers[0].id.clone(),
+                page: 3,
+                content: "scaled dot-product attention".to_string(),
+                ..NewNote::default()
+            },
+        )
+        .unwrap();
+        let method = tags::create(&source, "method/attention", None).unwrap();
+        let unread = tags::create(&source, "to-read", Some("#ff0000")).unwrap();
+        tags::add_to_note(&source, &note.id, &method.id).unwrap();
+        tags::add_to_paper(&source, &papers[1].id, &unread.id).unwrap();
+
+        let destination = dir.path().join("export");
+        let exported =
+            export_workspace(&source, &workspace.id, &destination.to_string_lossy()).unwrap();
+        assert_eq!(exported.papers, 2);
+        assert_eq!(exported.notes, 1);
+        assert_eq!(exported.attachments, 2);
+        assert!(exported.missing_attachments.is_empty());
+
+        let target = migrated_db();
+        let imported = import(&target, &destination).unwrap();
+        assert!(imported.workspace_created);
+        assert!(imported.warnings.is_empty(), "{:?}", imported.warnings);
+
+        assert_eq!(
+            repo::get_workspace(&target, &imported.workspace_id)
+                .unwrap()
+                .name,
+            "Reading group"
+        );
+        assert_eq!(
+            snapshot(&target, &imported.workspace_id),
+            snapshot(&source, &workspace.id)
+        );
+        let tag_colors = |db: &Db| {
+            let mut tags = tags::list(db)
+                .unwrap()
+                .into_iter()
+                .map(|tag| (tag.name, tag.color))
+                .collect::<Vec<_>>();
+            tags.sort();
+            tags
+        };
+        assert_eq!(tag_colors(&target), tag_colors(&source));
+        // Attachments land in the library folder with their content intact.
+        for paper in repo::list_papers(&target, &imported.workspace_id).unwrap() {
+            assert!(Path::new(&paper.path).starts_with(config::library_dir()));
+            assert_eq!(
+                repo::compute_file_hash(Path::new(&paper.path)).unwrap(),
+                paper.file_hash
+            );
+        }
+    }
+}
@@ -4,14 +4,38 @@ use crate::domain::AppSettings;
 
 const APP_DATA_DIR: &str = "PaperFlow";
 
-pub fn ensure_directories() -> anyhow::Result<()> {
+#[cfg(test)]
+thread_local! {
+    static TEST_DATA_DIR: std::cell::RefCell<Option<PathBuf>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Points the data folder of the current thread at `dir`, so tests never touch
+/// the user's database, settings, backups or library.
+#[cfg(test)]
+pub fn set_test_data_dir(dir: &std::path::Path) {
+    TEST_DATA_DIR.with(|data_dir| *data_dir.borrow_mut() = Some(dir.to_path_buf()));
+}
+
+/// Folder holding the database, settings, backups and library.
+fn app_data_dir() -> PathBuf {
+    #[cfg(test)]
+    if let Some(dir) = TEST_DATA_DIR.with(|data_dir| data_dir.borrow().clone()) {
+        return dir;
+    }
     let appdata = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
+    appdata.join(APP_DATA_DIR)
+}
+
+pub fn ensure_directories() -> anyhow::Result<()> {
     let local_appdata = dirs::data_local_dir().unwrap_or_else(|| PathBuf::from("."));
 
-    let db_path = appdata.join(APP_DATA_DIR).join("db.sqlite");
+    let db_path = db_path();
     let cache_dir = local_appdata.join(APP_DATA_DIR).join("cache");
     let logs_dir = local_appdata.join(APP_DATA_DIR).join("logs");
-    let config_path = appdata.join(APP_DATA_DIR);
+    let config_path = app_data_dir();
+    let backups_dir = backups_dir();
+    let library_dir = library_dir();
 
     if let Some(parent) = db_path.parent() {
         fs::create_dir_all(parent)?;
@@ -19,18 +43,27 @@ pub fn ensure_directories() -> anyhow::Result<()> {
     fs::create_dir_all(&cache_dir)?;
     fs::create_dir_all(&logs_dir)?;
     fs::create_dir_all(&config_path)?;
+    fs::create_dir_all(&backups_dir)?;
+    fs::create_dir_all(&library_dir)?;
 
     Ok(())
 }
 
 pub fn db_path() -> PathBuf {
-    let appdata = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
-    appdata.join(APP_DATA_DIR).join("db.sqlite")
+    app_data_dir().join("db.sqlite")
+}
+
+pub fn backups_dir() -> PathBuf {
+    app_data_dir().join("backups")
+}
+
+/// Folder holding PDFs copied in from imported workspace bundles.
+pub fn library_dir() -> PathBuf {
+    app_data_dir().join("library")
 }
 
 pub fn settings_path() -> PathBuf {
-    let appdata = dirs::data_dir().unwrap_or_else(|| PathBuf::from("."));
-    appdata.join(APP_DATA_DIR).join("config.json")
+    app_data_dir().join("config.json")
 }
 
 pub fn load_settings() -> anyhow::Result<AppSettings> {
@@ -2,6 +2,8 @@ use parking_lot::{Mutex, MutexGuard};
 use rusqlite::Connection;
 use std::sync::Arc;
 
+use super::search::tokenizer;
+
 #[derive(Clone)]
 pub struct Db {
     inner: Arc<Mutex<Connection>>,
@@ -12,6 +14,7 @@ impl Db {
         let path = super::config::db_path();
         let conn = Connection::open(path)?;
         conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+        tokenizer::register(&conn)?;
         Ok(Self {
             inner: Arc::new(Mutex::new(conn)),
         })
@@ -20,6 +23,7 @@ impl Db {
     pub fn in_memory() -> anyhow::Result<Self> {
         let conn = Connection::open_in_memory()?;
         conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        tokenizer::register(&conn)?;
         Ok(Self {
             inner: Arc::new(Mutex::new(conn)),
         })
@@ -1,11 +1,623 @@
-use anyhow::Result;
+//! Watches the folders holding paper files and keeps `paper` in step with them.
+//!
+//! - A paper whose file disappears gets `missingSince` set and a
+//!   [`FILE_MISSING_EVENT`].
+//! - A paper whose file is rewritten in place gets its new `fileHash` and
+//!   `filesize`, its metadata and text re-read (which re-anchors its notes)
+//!   and a [`FILE_CHANGED_EVENT`].
+//! - A PDF showing up in a watched folder with the `fileHash` of a paper whose
+//!   file is gone becomes that paper's `path` and `lastSeenPath`, followed by a
+//!   [`FILE_RELINKED_EVENT`].
+//! - A paper whose file comes back at its own path gets `missingSince`
+//!   cleared and a [`FILE_RESTORED_EVENT`].
+//! - Any other PDF accepted by a library folder (see [`library`]) is imported,
+//!   followed by a [`FILE_IMPORTED_EVENT`].
+//!
+//! Paper folders are watched non-recursively and library folders as
+//! configured; [`FileWatcher::sync`] follows the set of folders as papers are
+//! imported, moved or deleted. Events are debounced so a file is only hashed
+//! once it has been fully written.
 
-#[allow(dead_code)]
-pub struct FileWatcher;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use notify::{
+    event::{AccessKind, AccessMode, ModifyKind, RenameMode},
+    Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use parking_lot::Mutex;
+use rusqlite::params;
+
+use crate::{domain::PaperFileEvent, utils::time::now_iso};
+
+use super::{
+    library::{self, LibraryRoot},
+    pdf, repo, Db,
+};
+
+pub const FILE_MISSING_EVENT: &str = "file://missing";
+pub const FILE_CHANGED_EVENT: &str = "file://changed";
+pub const FILE_RELINKED_EVENT: &str = "file://relinked";
+pub const FILE_RESTORED_EVENT: &str = "file://restored";
+pub const FILE_IMPORTED_EVENT: &str = "file://imported";
+
+/// How long a file has to stay quiet before its events are handled.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Receives the event name and its payload, e.g. to forward it to the UI.
+type EventSink = Arc<dyn Fn(&'static str, PaperFileEvent) + Send + Sync>;
+
+pub struct FileWatcher {
+    debouncer: Debouncer<RecommendedWatcher, FileIdMap>,
+    folders: HashMap<PathBuf, RecursiveMode>,
+    library: Arc<Mutex<Vec<LibraryRoot>>>,
+    db: Db,
+}
 
 impl FileWatcher {
-    pub fn start() -> Result<Self> {
-        // TODO: wire up notify crate to watch PDF path changes.
-        Ok(Self)
+    /// Checks the file of every paper, then watches the folders holding them.
+    pub fn start(
+        db: Db,
+        sink: impl Fn(&'static str, PaperFileEvent) + Send + Sync + 'static,
+    ) -> Result<Self> {
+        let sink: EventSink = Arc::new(sink);
+        let library = Arc::new(Mutex::new(Vec::new()));
+        let handler = Handler {
+            db: db.clone(),
+            sink,
+            library: library.clone(),
+        };
+        handler.check_papers()?;
+
+        let debouncer = new_debouncer(
+            DEBOUNCE_TIMEOUT,
+            None,
+            move |result: DebounceEventResult| match result {
+                Ok(events) => events.iter().for_each(|event| handler.handle(event)),
+                Err(errors) => errors.iter().for_each(
+                    |err| tracing::warn!(target = "file_watch", error = %err, "watch error"),
+                ),
+            },
+        )
+        .context("failed to create file watcher")?;
+
+        let mut file_watcher = Self {
+            debouncer,
+            folders: HashMap::new(),
+            library,
+            db,
+        };
+        file_watcher.sync()?;
+        Ok(file_watcher)
+    }
+
+    /// Replaces the library folders and watches them.
+    pub fn set_library(&mut self, roots: Vec<LibraryRoot>) -> Result<()> {
+        *self.library.lock() = roots;
+        self.sync()
+    }
+
+    /// Watches the folders of papers added since the last call and stops
+    /// watching folders no paper lives in anymore.
+    pub fn sync(&mut self) -> Result<()> {
+        let wanted = self.wanted_folders()?;
+
+        let stale = self
+            .folders
+            .iter()
+            .filter(|(folder, mode)| wanted.get(*folder) != Some(*mode))
+            .map(|(folder, _)| folder.clone())
+            .collect::<Vec<_>>();
+        for folder in stale {
+            self.folders.remove(&folder);
+            self.debouncer.cache().remove_root(&folder);
+            if let Err(err) = self.debouncer.watcher().unwatch(&folder) {
+                tracing::debug!(
+                    target = "file_watch",
+                    folder = %folder.display(),
+                    error = %err,
+                    "failed to unwatch folder"
+                );
+            }
+        }
+
+        for (folder, mode) in wanted {
+            if self.folders.contains_key(&folder) || !folder.is_dir() {
+                continue;
+            }
+            match self.debouncer.watcher().watch(&folder, mode) {
+                Ok(()) => {
+                    // Lets the debouncer pair both sides of a rename.
+                    self.debouncer.cache().add_root(&folder, mode);
+                    self.folders.insert(folder, mode);
+                }
+                Err(err) => tracing::warn!(
+                    target = "file_watch",
+                    folder = %folder.display(),
+                    error = %err,
+                    "failed to watch folder"
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    /// Library folders, plus the paper folders they do not already cover.
+    fn wanted_folders(&self) -> Result<HashMap<PathBuf, RecursiveMode>> {
+        let mut wanted = HashMap::new();
+        for root in self.library.lock().iter() {
+            let mode = if root.recursive() {
+                RecursiveMode::Recursive
+            } else {
+                RecursiveMode::NonRecursive
+            };
+            // A recursive watch covers a non-recursive one on the same folder.
+            let entry = wanted
+                .entry(root.path().to_path_buf())
+                .or_insert(RecursiveMode::NonRecursive);
+            if mode == RecursiveMode::Recursive {
+                *entry = mode;
+            }
+        }
+
+        let recursive = wanted
+            .iter()
+            .filter(|(_, mode)| **mode == RecursiveMode::Recursive)
+            .map(|(folder, _)| folder.clone())
+            .collect::<Vec<_>>();
+        for folder in paper_folders(&self.db)? {
+            let covered = recursive.iter().any(|root| folder.starts_with(root));
+            if !covered {
+                wanted.entry(folder).or_insert(RecursiveMode::NonRecursive);
+            }
+        }
+        Ok(wanted)
+    }
+}
+
+struct Handler {
+    db: Db,
+    sink: EventSink,
+    library: Arc<Mutex<Vec<LibraryRoot>>>,
+}
+
+impl Handler {
+    fn handle(&self, event: &Event) {
+        let result = match event.kind {
+            // The destination goes first: a paper it claims is relinked away
+            // from the source path, so `removed` no longer reports it missing.
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => self
+                .present(&event.paths[1])
+                .and_then(|()| self.removed(&event.paths[0])),
+            EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                event.paths.iter().try_for_each(|path| self.removed(path))
+            }
+            EventKind::Create(_)
+            | EventKind::Modify(_)
+            | EventKind::Access(AccessKind::Close(AccessMode::Write)) => {
+                event.paths.iter().try_for_each(|path| {
+                    if path.exists() {
+                        self.present(path)
+                    } else {
+                        self.removed(path)
+                    }
+                })
+            }
+            _ => Ok(()),
+        };
+        if let Err(err) = result {
+            tracing::warn!(
+                target = "file_watch",
+                error = %format!("{err:#}"),
+                "failed to handle file event"
+            );
+        }
+    }
+
+    /// Marks papers whose file is gone as missing and clears the mark on those
+    /// whose file is back.
+    fn check_papers(&self) -> Result<()> {
+        let papers = {
+            let conn = self.db.connection();
+            let mut stmt = conn.prepare("SELECT id, path, missingSince FROM paper")?;
+            let papers = stmt
+                .query_map([], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            papers
+        };
+
+        for (paper_id, path, missing_since) in papers {
+            let exists = Path::new(&path).is_file();
+            if !exists && missing_since.is_none() {
+                self.mark_missing(&paper_id, &path)?;
+            } else if exists && missing_since.is_some() {
+                self.clear_missing(&paper_id, &path, &path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn removed(&self, path: &Path) -> Result<()> {
+        if !is_pdf(path) || path.exists() {
+            return Ok(());
+        }
+        let path = path.to_string_lossy();
+        let papers = {
+            let conn = self.db.connection();
+            let mut stmt =
+                conn.prepare("SELECT id FROM paper WHERE path = ?1 AND missingSince IS NULL")?;
+            let papers = stmt
+                .query_map(params![path.as_ref()], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+            papers
+        };
+        for paper_id in papers {
+            self.mark_missing(&paper_id, &path)?;
+        }
+        Ok(())
+    }
+
+    fn present(&self, path: &Path) -> Result<()> {
+        if !is_pdf(path) || !path.is_file() {
+            return Ok(());
+        }
+        let path_str = path.to_string_lossy().into_owned();
+        let filesize = fs::metadata(path)
+            .map(|metadata| i64::try_from(metadata.len()).unwrap_or(i64::MAX))
+            .with_context(|| format!("failed to read metadata of {}", path.display()))?;
+
+        // Only hash files that can belong to a paper: its own path, or the
+        // size of a paper whose file is gone.
+        let candidates = {
+            let conn = self.db.connection();
+            let mut stmt = conn.prepare(
+                "SELECT id, path, fileHash, missingSince FROM paper \
+                 WHERE path = ?1 OR filesize = ?2",
+            )?;
+            let candidates = stmt
+                .query_map(params![&path_str, filesize], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            candidates
+        };
+        let candidates = candidates
+            .into_iter()
+            .filter(|(_, paper_path, _, _)| {
+                *paper_path == path_str || !Path::new(paper_path).is_file()
+            })
+            .collect::<Vec<_>>();
+
+        let mut claimed = false;
+        if !candidates.is_empty() {
+            let file_hash = repo::compute_file_hash(path)?;
+            for (paper_id, paper_path, paper_hash, missing_since) in candidates {
+                if paper_path == path_str {
+                    claimed = true;
+                    if paper_hash != file_hash {
+                        self.changed(&paper_id, &path_str, &file_hash, filesize)?;
+                    } else if missing_since.is_some() {
+                        self.clear_missing(&paper_id, &path_str, &paper_path)?;
+                    }
+                } else if paper_hash == file_hash {
+                    claimed = true;
+                    self.clear_missing(&paper_id, &path_str, &paper_path)?;
+                }
+            }
+        }
+        if !claimed {
+            self.import(path)?;
+        }
+        Ok(())
+    }
+
+    /// Imports a file no paper points at when a library folder accepts it.
+    fn import(&self, path: &Path) -> Result<()> {
+        let root = library::root_for(&self.library.lock(), path).cloned();
+        let Some(root) = root else {
+            return Ok(());
+        };
+        if let Some(paper) = library::import_file(&self.db, &root, path)? {
+            (self.sink)(
+                FILE_IMPORTED_EVENT,
+                PaperFileEvent {
+                    paper_id: paper.id,
+                    path: paper.path,
+                    previous_path: None,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    fn changed(&self, paper_id: &str, path: &str, file_hash: &str, filesize: i64) -> Result<()> {
+        {
+            let conn = self.db.connection();
+            conn.execute(
+                "UPDATE paper SET fileHash = ?1, filesize = ?2, missingSince = NULL, \
+                 updatedAt = ?3 WHERE id = ?4",
+                params![file_hash, filesize, now_iso(), paper_id],
+            )?;
+        }
+        (self.sink)(
+            FILE_CHANGED_EVENT,
+            PaperFileEvent {
+                paper_id: paper_id.to_string(),
+                path: path.to_string(),
+                previous_path: None,
+            },
+        );
+
+        let mut papers = [repo::get_paper(&self.db, paper_id)?];
+        pdf::metadata::import_metadata(&self.db, &mut papers);
+        pdf::text::index_papers(&self.db, &papers);
+        Ok(())
+    }
+
+    fn mark_missing(&self, paper_id: &str, path: &str) -> Result<()> {
+        {
+            let conn = self.db.connection();
+            conn.execute(
+                "UPDATE paper SET missingSince = ?1 WHERE id = ?2",
+                params![now_iso(), paper_id],
+            )?;
+        }
+        (self.sink)(
+            FILE_MISSING_EVENT,
+            PaperFileEvent {
+                paper_id: paper_id.to_string(),
+                path: path.to_string(),
+                previous_path: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Points the paper at `path`, where its file was found again. A file back
+    /// at `previous_path` is restored rather than relinked.
+    fn clear_missing(&self, paper_id: &str, path: &str, previous_path: &str) -> Result<()> {
+        {
+            let conn = self.db.connection();
+            conn.execute(
+                "UPDATE paper SET path = ?1, lastSeenPath = ?1, missingSince = NULL, \
+                 updatedAt = ?2 WHERE id = ?3",
+                params![path, now_iso(), paper_id],
+            )?;
+        }
+        let (event, previous_path) = if path == previous_path {
+            (FILE_RESTORED_EVENT, None)
+        } else {
+            (FILE_RELINKED_EVENT, Some(previous_path.to_string()))
+        };
+        (self.sink)(
+            event,
+            PaperFileEvent {
+                paper_id: paper_id.to_string(),
+                path: path.to_string(),
+                previous_path,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Folders holding the file of at least one paper.
+fn paper_folders(db: &Db) -> Result<HashSet<PathBuf>> {
+    let conn = db.connection();
+    let mut stmt = conn.prepare("SELECT path, lastSeenPath FROM paper")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?))
+    })?;
+
+    let mut folders = HashSet::new();
+    for row in rows {
+        let (path, last_seen_path) = row?;
+        for path in [Some(path), last_seen_path].into_iter().flatten() {
+            if let Some(folder) = Path::new(&path).parent() {
+                if !folder.as_os_str().is_empty() {
+                    folders.insert(folder.to_path_buf());
+                }
+            }
+        }
+    }
+    Ok(folders)
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::mpsc::{self, Receiver},
+        time::Instant,
+    };
+
+    use super::*;
+    use crate::{
+        domain::{Paper, PaperImportRequest},
+        services::{migration, test_support::TempDir},
+    };
+
+    /// How long a test waits for the watcher to report a change.
+    const EVENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+    struct Fixture {
+        db: Db,
+        papers: Vec<Paper>,
+        events: Receiver<(&'static str, PaperFileEvent)>,
+        _watcher: FileWatcher,
+    }
+
+    impl Fixture {
+        /// Imports `paths` into an in-memory library and watches their folders.
+        fn start(paths: &[PathBuf]) -> Self {
+            let db = Db::in_memory().unwrap();
+            migration::migrate(&mut db.connection()).unwrap();
+            let workspace = repo::create_workspace(&db, "Watched").unwrap();
+            let papers = repo::import_papers(
+                &db,
+                &PaperImportRequest {
+                    paths: paths
+                        .iter()
+                        .map(|path| path.to_string_lossy().into_owned())
+                        .collect(),
+                    workspace_id: workspace.id,
+                },
+            )
+            .unwrap();
+
+            let (sender, events) = mpsc::channel();
+            let sender = Mutex::new(sender);
+            let watcher = FileWatcher::start(db.clone(), move |event, payload| {
+                let _ = sender.lock().send((event, payload));
+            })
+            .unwrap();
+            Self {
+                db,
+                papers,
+                events,
+                _watcher: watcher,
+            }
+        }
+
+        /// Waits for the next `event` about `paper_id`, skipping any other.
+        fn expect(&self, event: &str, paper_id: &str) -> PaperFileEvent {
+            let deadline = Instant::now() + EVENT_TIMEOUT;
+            while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                match self.events.recv_timeout(left) {
+                    Ok((name, payload)) if name == event && payload.paper_id == paper_id => {
+                        return payload
+                    }
+                    Ok(_) => continue,
+                    Err(_) => break,
+                }
+            }
+            panic!("no {event} event for paper {paper_id}");
+        }
+
+        /// Name of the next event about `paper_id`.
+        fn next(&self, paper_id: &str) -> &'static str {
+            let deadline = Instant::now() + EVENT_TIMEOUT;
+            while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                match self.events.recv_timeout(left) {
+                    Ok((name, payload)) if payload.paper_id == paper_id => return name,
+                    Ok(_) => continue,
+                    Err(_) => break,
+                }
+            }
+            panic!("no event for paper {paper_id}");
+        }
+
+        fn paper(&self, paper_id: &str) -> Paper {
+            repo::get_paper(&self.db, paper_id).unwrap()
+        }
+    }
+
+    #[test]
+    fn deleted_file_marks_paper_missing() {
+        let dir = TempDir::new();
+        let fixture = Fixture::start(&[dir.write_pdf("paper.pdf", "v1")]);
+        let paper = &fixture.papers[0];
+
+        fs::remove_file(&paper.path).unwrap();
+
+        let event = fixture.expect(FILE_MISSING_EVENT, &paper.id);
+        assert_eq!(event.path, paper.path);
+        assert!(fixture.paper(&paper.id).missing_since.is_some());
+    }
+
+    #[test]
+    fn file_moved_to_another_watched_folder_is_relinked() {
+        let dir = TempDir::new();
+        let fixture = Fixture::start(&[
+            dir.write_pdf("a/paper.pdf", "v1"),
+            dir.write_pdf("b/other.pdf", "v1"),
+        ]);
+        let paper = &fixture.papers[0];
+        let target = Path::new(&fixture.papers[1].path).with_file_name("paper.pdf");
+
+        fs::rename(&paper.path, &target).unwrap();
+
+        let event = fixture.expect(FILE_RELINKED_EVENT, &paper.id);
+        let target = target.to_string_lossy();
+        assert_eq!(event.path, target);
+        assert_eq!(event.previous_path.as_deref(), Some(paper.path.as_str()));
+        let relinked = fixture.paper(&paper.id);
+        assert_eq!(relinked.path, target);
+        assert_eq!(relinked.last_seen_path.as_deref(), Some(target.as_ref()));
+        assert!(relinked.missing_since.is_none());
+    }
+
+    #[test]
+    fn renamed_file_is_relinked_without_being_reported_missing() {
+        let dir = TempDir::new();
+        let fixture = Fixture::start(&[dir.write_pdf("paper.pdf", "v1")]);
+        let paper = &fixture.papers[0];
+        let target = dir.path().join("renamed.pdf");
+
+        fs::rename(&paper.path, &target).unwrap();
+
+        assert_eq!(fixture.next(&paper.id), FILE_RELINKED_EVENT);
+        let relinked = fixture.paper(&paper.id);
+        assert_eq!(relinked.path, target.to_string_lossy());
+        assert!(relinked.missing_since.is_none());
+    }
+
+    #[test]
+    fn file_rewritten_in_place_gets_new_hash() {
+        let dir = TempDir::new();
+        let fixture = Fixture::start(&[dir.write_pdf("paper.pdf", "v1")]);
+        let paper = &fixture.papers[0];
+
+        let path = dir.write_pdf("paper.pdf", "v2 with more bytes");
+
+        fixture.expect(FILE_CHANGED_EVENT, &paper.id);
+        let changed = fixture.paper(&paper.id);
+        assert_ne!(changed.file_hash, paper.file_hash);
+        assert_eq!(changed.file_hash, repo::compute_file_hash(&path).unwrap());
+        assert_eq!(
+            changed.filesize,
+            Some(fs::metadata(&path).unwrap().len() as i64)
+        );
+    }
+
+    #[test]
+    fn file_back_at_its_own_path_is_restored() {
+        let dir = TempDir::new();
+        let path = dir.write_pdf("paper.pdf", "v1");
+        let fixture = Fixture::start(std::slice::from_ref(&path));
+        let paper = &fixture.papers[0];
+
+        let saved = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        fixture.expect(FILE_MISSING_EVENT, &paper.id);
+        fs::write(&path, saved).unwrap();
+
+        let event = fixture.expect(FILE_RESTORED_EVENT, &paper.id);
+        assert_eq!(event.path, paper.path);
+        assert_eq!(event.previous_path, None);
+        assert!(fixture.paper(&paper.id).missing_since.is_none());
     }
 }
@@ -0,0 +1,278 @@
+//! Text highlights on papers.
+//!
+//! A highlight covers one or more rectangles, one per line box of the
+//! selection, and may span pages; `page` is the first page it covers. The
+//! rectangles are stored as JSON. The highlighted text is indexed as a
+//! [`search::HIGHLIGHT_REF_TYPE`] entry and its comment as the entry label.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::{
+    domain::{Highlight, HighlightRect, NewHighlight, UpdateHighlight},
+    telemetry::{IpcError, IpcResult, IpcStatus},
+    utils::time::now_iso,
+};
+
+use super::{pdf, search, Db};
+
+const HIGHLIGHT_COLUMNS: &str =
+    "id, paperId, page, rects, text, color, comment, createdAt, updatedAt";
+
+/// Highlights of a paper in reading order.
+pub fn list(db: &Db, paper_id: &str) -> IpcResult<Vec<Highlight>> {
+    if paper_id.trim().is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "paperId is required"));
+    }
+
+    let conn = db.connection();
+    ensure_paper_exists(&conn, paper_id)?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {HIGHLIGHT_COLUMNS} FROM highlight WHERE paperId = ?1 \
+             ORDER BY page ASC, datetime(createdAt) ASC"
+        ))
+        .map_err(db_error)?;
+    let highlights = stmt
+        .query_map(params![paper_id], map_highlight)
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+
+    Ok(highlights)
+}
+
+pub fn get(db: &Db, highlight_id: &str) -> IpcResult<Highlight> {
+    if highlight_id.trim().is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "highlightId is required",
+        ));
+    }
+
+    let conn = db.connection();
+    get_highlight(&conn, highlight_id)
+}
+
+pub fn create(db: &Db, highlight: &NewHighlight) -> IpcResult<Highlight> {
+    if highlight.paper_id.trim().is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "paperId is required"));
+    }
+    let text = validate_text(&highlight.text)?;
+    let page = validate_rects(&highlight.rects)?;
+
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    ensure_paper_exists(&tx, &highlight.paper_id)?;
+
+    let highlight_id = Uuid::new_v4().to_string();
+    let now = now_iso();
+    tx.execute(
+        "INSERT INTO highlight \
+         (id, paperId, page, rects, text, color, comment, createdAt, updatedAt) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+        params![
+            &highlight_id,
+            &highlight.paper_id,
+            page,
+            encode_rects(&highlight.rects)?,
+            text,
+            highlight.color.as_deref(),
+            non_empty(highlight.comment.as_deref()),
+            &now
+        ],
+    )
+    .map_err(db_error)?;
+
+    search::upsert_entry(&tx, search::HIGHLIGHT_REF_TYPE, &highlight_id, text).map_err(db_error)?;
+
+    let created = get_highlight(&tx, &highlight_id)?;
+    tx.commit().map_err(db_error)?;
+    Ok(created)
+}
+
+pub fn update(db: &Db, highlight: &UpdateHighlight) -> IpcResult<Highlight> {
+    if highlight.id.trim().is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "id is required"));
+    }
+
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    let mut existing = get_highlight(&tx, &highlight.id)?;
+
+    if let Some(rects) = &highlight.rects {
+        existing.page = validate_rects(rects)?;
+        existing.rects = rects.clone();
+    }
+    if let Some(text) = &highlight.text {
+        existing.text = validate_text(text)?.to_string();
+    }
+    if let Some(color) = &highlight.color {
+        existing.color = Some(color.clone());
+    }
+    if let Some(comment) = &highlight.comment {
+        existing.comment = non_empty(Some(comment)).map(str::to_string);
+    }
+    existing.updated_at = now_iso();
+
+    tx.execute(
+        "UPDATE highlight SET page = ?1, rects = ?2, text = ?3, color = ?4, comment = ?5, \
+         updatedAt = ?6 WHERE id = ?7",
+        params![
+            existing.page,
+            encode_rects(&existing.rects)?,
+            &existing.text,
+            existing.color.as_deref(),
+            existing.comment.as_deref(),
+            &existing.updated_at,
+            &existing.id
+        ],
+    )
+    .map_err(db_error)?;
+
+    // The comment is part of the entry label, so reindex on any change.
+    search::upsert_entry(
+        &tx,
+        search::HIGHLIGHT_REF_TYPE,
+        &existing.id,
+        &existing.text,
+    )
+    .map_err(db_error)?;
+
+    tx.commit().map_err(db_error)?;
+    Ok(existing)
+}
+
+pub fn delete(db: &Db, highlight_id: &str) -> IpcResult<()> {
+    if highlight_id.trim().is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "id is required"));
+    }
+
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+
+    let deleted = tx
+        .execute("DELETE FROM highlight WHERE id = ?1", params![highlight_id])
+        .map_err(db_error)?;
+    if deleted == 0 {
+        return Err(not_found(highlight_id));
+    }
+
+    search::remove_entry(&tx, search::HIGHLIGHT_REF_TYPE, highlight_id).map_err(db_error)?;
+
+    tx.commit().map_err(db_error)?;
+    Ok(())
+}
+
+/// Checks that the rectangles of a highlight lie within their pages, in
+/// normalized coordinates, and returns the first page they cover.
+pub(crate) fn validate_rects(rects: &[HighlightRect]) -> IpcResult<i32> {
+    if rects.is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "A highlight needs at least one rectangle",
+        ));
+    }
+    for rect in rects {
+        if rect.page < 1 {
+            return Err(IpcError::new(
+                IpcStatus::BadRequest,
+                format!("Invalid highlight page {}", rect.page),
+            ));
+        }
+        if !(rect.width > 0.0 && rect.height > 0.0) {
+            return Err(IpcError::new(
+                IpcStatus::BadRequest,
+                "Highlight rectangles need a positive size",
+            ));
+        }
+        pdf::geometry::validate_position(rect.x, rect.y)?;
+        pdf::geometry::validate_position(rect.x + rect.width, rect.y + rect.height)?;
+    }
+    Ok(rects.iter().map(|rect| rect.page).min().unwrap_or(1))
+}
+
+pub(crate) fn encode_rects(rects: &[HighlightRect]) -> IpcResult<String> {
+    serde_json::to_string(rects).map_err(|err| {
+        IpcError::new(
+            IpcStatus::Internal,
+            format!("Failed to encode highlight rectangles: {err}"),
+        )
+    })
+}
+
+fn validate_text(text: &str) -> IpcResult<&str> {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "Highlight text cannot be empty",
+        ));
+    }
+    Ok(trimmed)
+}
+
+fn get_highlight(conn: &Connection, highlight_id: &str) -> IpcResult<Highlight> {
+    conn.query_row(
+        &format!("SELECT {HIGHLIGHT_COLUMNS} FROM highlight WHERE id = ?1"),
+        params![highlight_id],
+        map_highlight,
+    )
+    .optional()
+    .map_err(db_error)?
+    .ok_or_else(|| not_found(highlight_id))
+}
+
+fn map_highlight(row: &rusqlite::Row<'_>) -> rusqlite::Result<Highlight> {
+    let rects: String = row.get("rects")?;
+    let rects = serde_json::from_str(&rects).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(err))
+    })?;
+    Ok(Highlight {
+        id: row.get("id")?,
+        paper_id: row.get("paperId")?,
+        page: row.get("page")?,
+        rects,
+        text: row.get("text")?,
+        color: row.get("color")?,
+        comment: row.get("comment")?,
+        created_at: row.get("createdAt")?,
+        updated_at: row.get("updatedAt")?,
+    })
+}
+
+fn non_empty(value: Option<&str>) -> Option<&str> {
+    value.map(str::trim).filter(|value| !value.is_empty())
+}
+
+fn ensure_paper_exists(conn: &Connection, paper_id: &str) -> IpcResult<()> {
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM paper WHERE id = ?1",
+            params![paper_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(db_error)?
+        .is_some();
+    if exists {
+        Ok(())
+    } else {
+        Err(IpcError::new(
+            IpcStatus::NotFound,
+            format!("Paper {paper_id} not found"),
+        ))
+    }
+}
+
+fn not_found(highlight_id: &str) -> IpcError {
+    IpcError::new(
+        IpcStatus::NotFound,
+        format!("Highlight {highlight_id} not found"),
+    )
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
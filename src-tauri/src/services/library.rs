@@ -0,0 +1,238 @@
+//! Library folders: folders whose PDFs are imported into a workspace without
+//! going through `paper_import`, e.g. a folder of papers synced between a team.
+//!
+//! [`scan`] imports the files already in the folders; the
+//! [`FileWatcher`](super::file_watch::FileWatcher) imports those that show up
+//! later. Both go through [`repo::import_papers`], so a file whose content is
+//! already a paper moves that paper instead of adding a new one.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use rusqlite::params;
+use walkdir::WalkDir;
+
+use crate::{
+    domain::{LibraryFolder, LibraryScanReport, Paper, PaperImportRequest},
+    telemetry::{IpcError, IpcResult, IpcStatus},
+};
+
+use super::{repo, Db};
+
+/// Include pattern of folders that declare none.
+const DEFAULT_INCLUDE: &str = "*.pdf";
+
+/// A [`LibraryFolder`] with its patterns compiled.
+#[derive(Debug, Clone)]
+pub struct LibraryRoot {
+    path: PathBuf,
+    workspace_id: String,
+    recursive: bool,
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+impl LibraryRoot {
+    pub fn new(folder: &LibraryFolder) -> IpcResult<Self> {
+        if folder.workspace_id.trim().is_empty() {
+            return Err(IpcError::new(
+                IpcStatus::BadRequest,
+                "Library folders need a workspace id",
+            ));
+        }
+        if folder.path.trim().is_empty() {
+            return Err(IpcError::new(
+                IpcStatus::BadRequest,
+                "Library folders need a path",
+            ));
+        }
+
+        let include = if folder.include.is_empty() {
+            glob_set(&[DEFAULT_INCLUDE.to_string()])?
+        } else {
+            glob_set(&folder.include)?
+        };
+        let path = Path::new(folder.path.trim());
+        Ok(Self {
+            path: fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+            workspace_id: folder.workspace_id.trim().to_string(),
+            recursive: folder.recursive,
+            include,
+            exclude: glob_set(&folder.exclude)?,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn recursive(&self) -> bool {
+        self.recursive
+    }
+
+    /// Whether the file at `path` belongs in this library folder.
+    pub fn accepts(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.path) else {
+            return false;
+        };
+        if relative.as_os_str().is_empty() || (!self.recursive && relative.components().count() > 1)
+        {
+            return false;
+        }
+        self.include.is_match(relative) && !self.excludes(relative)
+    }
+
+    /// Whether `relative` or one of the folders it lies in is excluded.
+    fn excludes(&self, relative: &Path) -> bool {
+        relative
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| self.exclude.is_match(ancestor))
+    }
+}
+
+/// Compiles the library folders of the settings, rejecting invalid patterns.
+pub fn library_roots(folders: &[LibraryFolder]) -> IpcResult<Vec<LibraryRoot>> {
+    folders.iter().map(LibraryRoot::new).collect()
+}
+
+/// Imports the files of the library folders that are not a paper yet.
+pub fn scan(db: &Db, roots: &[LibraryRoot]) -> IpcResult<LibraryScanReport> {
+    let mut report = LibraryScanReport::default();
+    let known = known_paths(db)?;
+
+    for root in roots {
+        let max_depth = if root.recursive { usize::MAX } else { 1 };
+        let entries = WalkDir::new(&root.path)
+            .follow_links(false)
+            .max_depth(max_depth)
+            .into_iter()
+            .filter_entry(|entry| {
+                entry.depth() == 0
+                    || entry
+                        .path()
+                        .strip_prefix(&root.path)
+                        .map_or(true, |relative| !root.excludes(relative))
+            });
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    report.warnings.push(format!("Failed to read {err}"));
+                    continue;
+                }
+            };
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            report.files_scanned += 1;
+
+            let path = entry.path();
+            if known.contains(path.to_string_lossy().as_ref()) || !root.accepts(path) {
+                continue;
+            }
+            match import_file(db, root, path) {
+                Ok(Some(paper)) => report.imported.push(paper),
+                Ok(None) => {}
+                Err(err) => report.warnings.push(format!(
+                    "Failed to import {}: {}",
+                    path.display(),
+                    err.message
+                )),
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Imports the file at `path` into the workspace of `root`, reading its
+/// metadata and text before returning. Returns `None` when the file is a copy
+/// of a paper whose own file is still in place, which would otherwise move
+/// that paper back and forth between the copies.
+pub(crate) fn import_file(db: &Db, root: &LibraryRoot, path: &Path) -> IpcResult<Option<Paper>> {
+    let file_hash = repo::compute_file_hash(path)?;
+    let existing_paths = {
+        let conn = db.connection();
+        let mut stmt = conn
+            .prepare("SELECT path FROM paper WHERE fileHash = ?1")
+            .map_err(db_error)?;
+        let paths = stmt
+            .query_map(params![&file_hash], |row| row.get::<_, String>(0))
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        paths
+    };
+    if existing_paths
+        .iter()
+        .any(|existing| Path::new(existing) != path && Path::new(existing).is_file())
+    {
+        return Ok(None);
+    }
+
+    let request = PaperImportRequest {
+        paths: vec![path.to_string_lossy().into_owned()],
+        workspace_id: root.workspace_id.clone(),
+    };
+    let mut imported = repo::import_papers(db, &request)?;
+    repo::index_imported(db, &mut imported);
+    Ok(imported.pop())
+}
+
+/// The most specific library folder that accepts the file at `path`.
+pub(crate) fn root_for<'a>(roots: &'a [LibraryRoot], path: &Path) -> Option<&'a LibraryRoot> {
+    roots
+        .iter()
+        .filter(|root| root.accepts(path))
+        .max_by_key(|root| root.path.components().count())
+}
+
+fn known_paths(db: &Db) -> IpcResult<HashSet<String>> {
+    let conn = db.connection();
+    let mut stmt = conn.prepare("SELECT path FROM paper").map_err(db_error)?;
+    let paths = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(db_error)?
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(db_error)?;
+    Ok(paths)
+}
+
+fn glob_set(patterns: &[String]) -> IpcResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim();
+        // Like .gitignore, a pattern without a separator matches names at any
+        // depth.
+        let anchored = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{pattern}")
+        };
+        let glob = GlobBuilder::new(&anchored)
+            .case_insensitive(true)
+            .literal_separator(true)
+            .build()
+            .map_err(|err| {
+                IpcError::new(
+                    IpcStatus::BadRequest,
+                    format!("Invalid library pattern \"{pattern}\": {err}"),
+                )
+            })?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|err| {
+        IpcError::new(
+            IpcStatus::BadRequest,
+            format!("Invalid library patterns: {err}"),
+        )
+    })
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
@@ -1,23 +1,294 @@
 use anyhow::Context;
+use rusqlite::Connection;
+use serde::Serialize;
+use thiserror::Error;
 
-use super::Db;
+use super::{backup, Db};
 
+/// Forward-only migration scripts. The position in this list (1-based) is the
+/// schema version recorded in `PRAGMA user_version` once the script is applied.
 const MIGRATIONS: &[(&str, &str)] = &[
-    ("0001_init.sql", include_str!("../migrations/0001_init.sql")),
+    (
+        "0001_init.sql",
+        include_str!("../../migrations/0001_init.sql"),
+    ),
     (
         "0002_add_tags.sql",
-        include_str!("../migrations/0002_add_tags.sql"),
+        include_str!("../../migrations/0002_add_tags.sql"),
+    ),
+    (
+        "0003_paper_passage.sql",
+        include_str!("../../migrations/0003_paper_passage.sql"),
+    ),
+    (
+        "0004_search_index_columns.sql",
+        include_str!("../../migrations/0004_search_index_columns.sql"),
+    ),
+    (
+        "0005_search_tokenizer.sql",
+        include_str!("../../migrations/0005_search_tokenizer.sql"),
+    ),
+    (
+        "0006_search_vocab.sql",
+        include_str!("../../migrations/0006_search_vocab.sql"),
+    ),
+    (
+        "0007_search_history.sql",
+        include_str!("../../migrations/0007_search_history.sql"),
+    ),
+    (
+        "0008_tag_hierarchy.sql",
+        include_str!("../../migrations/0008_tag_hierarchy.sql"),
+    ),
+    (
+        "0009_paper_tags.sql",
+        include_str!("../../migrations/0009_paper_tags.sql"),
+    ),
+    (
+        "0010_note_anchor.sql",
+        include_str!("../../migrations/0010_note_anchor.sql"),
+    ),
+    (
+        "0011_highlight.sql",
+        include_str!("../../migrations/0011_highlight.sql"),
     ),
+    (
+        "0012_note_position.sql",
+        include_str!("../../migrations/0012_note_position.sql"),
+    ),
+    (
+        "0013_paper_missing.sql",
+        include_str!("../../migrations/0013_paper_missing.sql"),
+    ),
+    (
+        "0014_paper_metadata.sql",
+        include_str!("../../migrations/0014_paper_metadata.sql"),
+    ),
+    (
+        "0015_paper_bibliography.sql",
+        include_str!("../../migrations/0015_paper_bibliography.sql"),
+    ),
+];
+
+/// Scripts that recreate `search_index` or add rows it has to cover; applying
+/// any of them requires a search rebuild before the index is usable again.
+const REINDEX_AFTER: &[&str] = &[
+    "0004_search_index_columns.sql",
+    "0005_search_tokenizer.sql",
+    "0008_tag_hierarchy.sql",
+    "0015_paper_bibliography.sql",
 ];
 
-pub fn ensure_initialized() -> anyhow::Result<()> {
+#[derive(Debug, Error)]
+#[error("database schema version {found} is newer than the supported version {supported}")]
+pub struct SchemaTooNew {
+    pub found: i64,
+    pub supported: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationReport {
+    pub from_version: i64,
+    pub to_version: i64,
+    pub applied: Vec<String>,
+    pub backup: Option<String>,
+    pub reindex_required: bool,
+}
+
+pub fn latest_version() -> i64 {
+    MIGRATIONS.len() as i64
+}
+
+/// Opens the on-disk database, snapshots it into the backups folder when a
+/// populated database is about to be upgraded, then applies pending scripts.
+pub fn ensure_initialized() -> anyhow::Result<MigrationReport> {
     let db = Db::connect()?;
-    let conn = db.connection();
+    let mut conn = db.connection();
+
+    let current = schema_version(&conn).context("failed to read schema version")?;
+    let backup = if current < latest_version() && has_user_tables(&conn)? {
+        let entry = backup::backup_connection(&conn)
+            .context("failed to back up database before migration")?;
+        Some(entry.file_name)
+    } else {
+        None
+    };
+
+    let mut report = migrate(&mut conn)?;
+    report.backup = backup;
+    Ok(report)
+}
 
-    for (name, sql) in MIGRATIONS {
-        conn.execute_batch(sql)
+pub fn schema_version(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+}
+
+/// Applies every script newer than the recorded `user_version`, each inside its
+/// own transaction so a failing script leaves the previous version intact.
+pub fn migrate(conn: &mut Connection) -> anyhow::Result<MigrationReport> {
+    let from_version = schema_version(conn).context("failed to read schema version")?;
+    let supported = latest_version();
+
+    if from_version > supported {
+        return Err(SchemaTooNew {
+            found: from_version,
+            supported,
+        }
+        .into());
+    }
+
+    let mut applied = Vec::new();
+    for (index, (name, sql)) in MIGRATIONS.iter().enumerate() {
+        let version = index as i64 + 1;
+        if version <= from_version {
+            continue;
+        }
+
+        let tx = conn
+            .transaction()
+            .with_context(|| format!("failed to begin migration {name}"))?;
+        tx.execute_batch(sql)
             .with_context(|| format!("failed to run migration {name}"))?;
+        tx.pragma_update(None, "user_version", version)
+            .with_context(|| format!("failed to record schema version {version}"))?;
+        tx.commit()
+            .with_context(|| format!("failed to commit migration {name}"))?;
+
+        tracing::info!(target = "migration", %name, version, "applied migration");
+        applied.push((*name).to_string());
+    }
+
+    Ok(MigrationReport {
+        from_version,
+        to_version: schema_version(conn).context("failed to read schema version")?,
+        reindex_required: applied
+            .iter()
+            .any(|name| REINDEX_AFTER.contains(&name.as_str())),
+        applied,
+        backup: None,
+    })
+}
+
+fn has_user_tables(conn: &Connection) -> anyhow::Result<bool> {
+    let count: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            [],
+            |row| row.get(0),
+        )
+        .context("failed to inspect database schema")?;
+    Ok(count > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{backup, test_support};
+
+    /// Applies the first `version` scripts the way an older build would have.
+    fn apply_until(conn: &Connection, version: i64) {
+        for (_, sql) in &MIGRATIONS[..version as usize] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.pragma_update(None, "user_version", version).unwrap();
+    }
+
+    fn names(scripts: &[(&str, &str)]) -> Vec<String> {
+        scripts.iter().map(|(name, _)| name.to_string()).collect()
     }
 
-    Ok(())
+    #[test]
+    fn fresh_database_applies_every_script() {
+        let db = Db::in_memory().unwrap();
+        let report = migrate(&mut db.connection()).unwrap();
+
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.to_version, latest_version());
+        assert_eq!(report.applied, names(MIGRATIONS));
+        assert!(report.reindex_required);
+    }
+
+    #[test]
+    fn only_pending_scripts_are_applied() {
+        let db = Db::in_memory().unwrap();
+        let mut conn = db.connection();
+        apply_until(&conn, 8);
+
+        let report = migrate(&mut conn).unwrap();
+
+        assert_eq!(report.from_version, 8);
+        assert_eq!(report.to_version, latest_version());
+        assert_eq!(report.applied, names(&MIGRATIONS[8..]));
+        assert_eq!(schema_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn reindex_is_required_only_after_scripts_that_touch_the_index() {
+        let db = Db::in_memory().unwrap();
+        let mut conn = db.connection();
+        apply_until(&conn, 13);
+
+        // 0014 leaves the index alone, 0015 adds columns it covers.
+        let report = migrate(&mut conn).unwrap();
+        assert_eq!(
+            report.applied,
+            ["0014_paper_metadata.sql", "0015_paper_bibliography.sql"]
+        );
+        assert!(report.reindex_required);
+
+        let report = migrate(&mut conn).unwrap();
+        assert!(report.applied.is_empty());
+        assert_eq!(report.from_version, report.to_version);
+        assert!(!report.reindex_required);
+    }
+
+    #[test]
+    fn newer_schema_is_rejected() {
+        let db = Db::in_memory().unwrap();
+        let mut conn = db.connection();
+        apply_until(&conn, latest_version());
+        conn.pragma_update(None, "user_version", latest_version() + 1)
+            .unwrap();
+
+        let err = migrate(&mut conn).unwrap_err();
+
+        let too_new = err.downcast_ref::<SchemaTooNew>().unwrap();
+        assert_eq!(too_new.found, latest_version() + 1);
+        assert_eq!(too_new.supported, latest_version());
+        assert_eq!(schema_version(&conn).unwrap(), latest_version() + 1);
+    }
+
+    #[test]
+    fn populated_database_is_backed_up_before_upgrading() {
+        let _data_dir = test_support::data_dir();
+        apply_until(&Db::connect().unwrap().connection(), 13);
+
+        let report = ensure_initialized().unwrap();
+
+        assert_eq!(report.from_version, 13);
+        assert_eq!(report.to_version, latest_version());
+        let backups = backup::list_backups().unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(
+            report.backup.as_deref(),
+            Some(backups[0].file_name.as_str())
+        );
+
+        // Nothing left to apply, so nothing to back up.
+        let report = ensure_initialized().unwrap();
+        assert_eq!(report.backup, None);
+        assert_eq!(backup::list_backups().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn empty_database_is_not_backed_up() {
+        let _data_dir = test_support::data_dir();
+
+        let report = ensure_initialized().unwrap();
+
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.backup, None);
+        assert!(backup::list_backups().unwrap().is_empty());
+    }
 }
@@ -1,11 +1,21 @@
+pub mod authors;
+pub mod backup;
+pub mod bundle;
 pub mod cache;
 pub mod config;
 pub mod db;
 pub mod file_watch;
+pub mod highlights;
+pub mod library;
 pub mod migration;
+pub mod pdf;
+pub mod relocate;
 pub mod repo;
 pub mod search;
 pub mod state;
 pub mod stats;
+pub mod tags;
+#[cfg(test)]
+pub(crate) mod test_support;
 
 pub use db::Db;
@@ -1,11 +1,599 @@
-#[derive(Debug, Default, Clone)]
-pub struct NoteAnchor {
-    pub page: i32,
-    pub x: f32,
-    pub y: f32,
-    pub text_hash: String,
+//! Text anchors for notes. A note created from a selection remembers the quoted
+//! text, hashes of the text around it and the paragraph holding it, so it can
+//! be found again when the PDF is replaced by a version whose layout no longer
+//! matches the stored page and coordinates.
+//!
+//! Text is compared after [`fts::fold`] with whitespace collapsed, so ligatures,
+//! diacritics and line breaks introduced by extraction do not break a match.
+//! The passages of a paper are read as one document in page order: a quote
+//! may span paragraphs and its context may come from the neighbouring ones.
+
+use std::collections::HashSet;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+use super::text::{stored_passages, Passage};
+use crate::{domain::NoteAnchor, services::search::fts};
+
+pub const STATUS_ANCHORED: &str = "anchored";
+pub const STATUS_RELOCATED: &str = "relocated";
+pub const STATUS_ORPHANED: &str = "orphaned";
+
+/// Characters of context hashed on each side of the quote.
+const CONTEXT_CHARS: usize = 32;
+/// Hex digits kept from the SHA-256 of a context window.
+const CONTEXT_HASH_LEN: usize = 16;
+/// Longer quotes are matched approximately on their first characters only.
+const FUZZY_QUOTE_CHARS: usize = 160;
+/// Edits allowed per quote character for an approximate match.
+const FUZZY_MAX_ERROR_RATE: f32 = 0.2;
+/// Passages scanned for an approximate match, picked by trigram overlap.
+const FUZZY_CANDIDATES: usize = 5;
+
+/// Builds the anchor of a new note from the passages of its paper. Without
+/// passages (text not extracted yet, or no text layer) the quote is kept
+/// unresolved and gets resolved by [`reanchor_notes`] once the text is indexed.
+pub fn derive_anchor(
+    passages: &[Passage],
+    file_hash: Option<&str>,
+    page: i32,
+    quote: &str,
+) -> Option<NoteAnchor> {
+    let normalized = normalize(quote);
+    if normalized.is_empty() {
+        return None;
+    }
+
+    let mut anchor = NoteAnchor {
+        quote: quote.trim().to_string(),
+        status: STATUS_ANCHORED.to_string(),
+        ..Default::default()
+    };
+    if passages.is_empty() {
+        return Some(anchor);
+    }
+
+    let document = Document::new(passages);
+    let hint = Hint {
+        page,
+        ..Default::default()
+    };
+    match document.resolve(&normalized, &hint) {
+        Some(found) => {
+            anchor.prefix_hash = found.prefix_hash;
+            anchor.suffix_hash = found.suffix_hash;
+            anchor.paragraph = Some(found.paragraph);
+        }
+        None => anchor.status = STATUS_ORPHANED.to_string(),
+    }
+    anchor.file_hash = file_hash.map(str::to_string);
+    Some(anchor)
+}
+
+/// Same as [`derive_anchor`], reading the passages already stored for the paper.
+pub fn derive_stored_anchor(
+    conn: &Connection,
+    paper_id: &str,
+    page: i32,
+    quote: &str,
+) -> rusqlite::Result<Option<NoteAnchor>> {
+    let text_hash: Option<String> = conn
+        .query_row(
+            "SELECT textHash FROM paper WHERE id = ?1",
+            params![paper_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    let passages = match text_hash {
+        Some(_) => stored_passages(conn, paper_id)?,
+        None => Vec::new(),
+    };
+    Ok(derive_anchor(&passages, text_hash.as_deref(), page, quote))
+}
+
+/// Resolves the anchors of a paper's notes against the passages of its file
+/// version `file_hash`. Notes already resolved against that version are left
+/// alone. A quote found elsewhere moves the note to the new page and marks it
+/// `relocated`; a quote that is gone marks it `orphaned` and keeps its place.
+/// Returns the number of notes whose anchor was resolved.
+pub fn reanchor_notes(
+    conn: &Connection,
+    paper_id: &str,
+    file_hash: &str,
+    passages: &[Passage],
+) -> rusqlite::Result<usize> {
+    if passages.is_empty() {
+        return Ok(0);
+    }
+
+    let pending = conn
+        .prepare(
+            "SELECT id, page, anchorQuote, anchorPrefixHash, anchorSuffixHash, \
+                    anchorParagraph, anchorFileHash \
+             FROM note \
+             WHERE paperId = ?1 AND anchorQuote IS NOT NULL \
+               AND (anchorFileHash IS NULL OR anchorFileHash <> ?2)",
+        )?
+        .query_map(params![paper_id, file_hash], |row| {
+            Ok(PendingNote {
+                id: row.get(0)?,
+                quote: row.get(2)?,
+                previous_hash: row.get(6)?,
+                hint: Hint {
+                    page: row.get(1)?,
+                    paragraph: row.get(5)?,
+                    prefix_hash: row.get(3)?,
+                    suffix_hash: row.get(4)?,
+                },
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let document = Document::new(passages);
+    let mut update = conn.prepare(
+        "UPDATE note SET page = ?1, anchorPrefixHash = ?2, anchorSuffixHash = ?3, \
+             anchorParagraph = ?4, anchorStatus = ?5, anchorFileHash = ?6 \
+         WHERE id = ?7",
+    )?;
+    for note in &pending {
+        let hint = &note.hint;
+        let resolved = document.resolve(&normalize(&note.quote), hint);
+        let (page, status, prefix_hash, suffix_hash, paragraph) = match resolved {
+            // First resolution: the stored page came from the viewer showing this
+            // very version, so the note stays where it was placed.
+            Some(found) if note.previous_hash.is_none() => (
+                hint.page,
+                STATUS_ANCHORED,
+                found.prefix_hash,
+                found.suffix_hash,
+                Some(found.paragraph),
+            ),
+            Some(found) => {
+                let moved = found.page != hint.page || Some(found.paragraph) != hint.paragraph;
+                (
+                    found.page,
+                    if moved {
+                        STATUS_RELOCATED
+                    } else {
+                        STATUS_ANCHORED
+                    },
+                    found.prefix_hash,
+                    found.suffix_hash,
+                    Some(found.paragraph),
+                )
+            }
+            None => (
+                hint.page,
+                STATUS_ORPHANED,
+                hint.prefix_hash.clone(),
+                hint.suffix_hash.clone(),
+                hint.paragraph,
+            ),
+        };
+        update.execute(params![
+            page,
+            prefix_hash,
+            suffix_hash,
+            paragraph,
+            status,
+            file_hash,
+            &note.id
+        ])?;
+    }
+    Ok(pending.len())
+}
+
+struct PendingNote {
+    id: String,
+    quote: String,
+    previous_hash: Option<String>,
+    hint: Hint,
+}
+
+/// What is known about where a quote used to be.
+#[derive(Default)]
+struct Hint {
+    page: i32,
+    paragraph: Option<i32>,
+    prefix_hash: Option<String>,
+    suffix_hash: Option<String>,
+}
+
+struct Found {
+    page: i32,
+    paragraph: i32,
+    prefix_hash: Option<String>,
+    suffix_hash: Option<String>,
+}
+
+struct Span {
+    page: i32,
+    paragraph: i32,
+    start: usize,
+    end: usize,
 }
 
-pub fn derive_anchor(_page: i32, _selection: &str) -> NoteAnchor {
-    NoteAnchor { text_hash: format!("hash:{}", _selection.len()), ..Default::default() }
+/// Normalized text of all passages, joined by single spaces.
+struct Document {
+    text: Vec<char>,
+    spans: Vec<Span>,
+}
+
+impl Document {
+    fn new(passages: &[Passage]) -> Self {
+        let mut text = Vec::new();
+        let mut spans = Vec::with_capacity(passages.len());
+        for passage in passages {
+            let content = normalize(&passage.content);
+            if content.is_empty() {
+                continue;
+            }
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            let start = text.len();
+            text.extend(content);
+            spans.push(Span {
+                page: passage.page,
+                paragraph: passage.paragraph,
+                start,
+                end: text.len(),
+            });
+        }
+        Self { text, spans }
+    }
+
+    /// Finds `quote` in the document: among exact occurrences the one whose
+    /// context and position best match `hint`, otherwise the closest
+    /// approximate match.
+    fn resolve(&self, quote: &[char], hint: &Hint) -> Option<Found> {
+        let exact = self
+            .occurrences(quote)
+            .into_iter()
+            .map(|start| (start, start + quote.len()))
+            .max_by_key(|&(start, end)| {
+                // Earlier occurrences win ties, hence the reversed position.
+                (self.score(start, end, hint), std::cmp::Reverse(start))
+            });
+        let (start, end) = exact.or_else(|| self.fuzzy(quote, hint))?;
+
+        let span = self.span_at(start);
+        let (prefix_hash, suffix_hash) = self.context_hashes(start, end);
+        Some(Found {
+            page: span.page,
+            paragraph: span.paragraph,
+            prefix_hash,
+            suffix_hash,
+        })
+    }
+
+    fn score(&self, start: usize, end: usize, hint: &Hint) -> u32 {
+        let span = self.span_at(start);
+        let (prefix_hash, suffix_hash) = self.context_hashes(start, end);
+        let mut score = 0;
+        if hint.prefix_hash.is_some() && prefix_hash == hint.prefix_hash {
+            score += 4;
+        }
+        if hint.suffix_hash.is_some() && suffix_hash == hint.suffix_hash {
+            score += 4;
+        }
+        if span.page == hint.page {
+            score += 2;
+            if Some(span.paragraph) == hint.paragraph {
+                score += 1;
+            }
+        }
+        score
+    }
+
+    fn occurrences(&self, quote: &[char]) -> Vec<usize> {
+        if quote.is_empty() || quote.len() > self.text.len() {
+            return Vec::new();
+        }
+        self.text
+            .windows(quote.len())
+            .enumerate()
+            .filter(|(_, window)| *window == quote)
+            .map(|(start, _)| start)
+            .collect()
+    }
+
+    /// Approximate match of (the head of) `quote` within the passages sharing
+    /// the most trigrams with it, allowing [`FUZZY_MAX_ERROR_RATE`] edits.
+    fn fuzzy(&self, quote: &[char], hint: &Hint) -> Option<(usize, usize)> {
+        let pattern = &quote[..quote.len().min(FUZZY_QUOTE_CHARS)];
+        let quote_trigrams = trigrams(pattern);
+        if quote_trigrams.is_empty() {
+            return None;
+        }
+
+        let mut candidates = self
+            .spans
+            .iter()
+            .map(|span| {
+                let shared = trigrams(&self.text[span.start..span.end])
+                    .intersection(&quote_trigrams)
+                    .count();
+                (shared, span)
+            })
+            .filter(|(shared, _)| *shared > 0)
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(shared, span)| {
+            (std::cmp::Reverse(*shared), (span.page - hint.page).abs())
+        });
+
+        let max_errors = (pattern.len() as f32 * FUZZY_MAX_ERROR_RATE).ceil() as usize;
+        candidates
+            .into_iter()
+            .take(FUZZY_CANDIDATES)
+            .filter_map(|(_, span)| {
+                let from = span.start.saturating_sub(pattern.len());
+                let to = (span.end + pattern.len()).min(self.text.len());
+                let (errors, start, end) = best_substring(pattern, &self.text[from..to])?;
+                (errors <= max_errors).then(|| {
+                    let distance = (self.span_at(from + start).page - hint.page).abs();
+                    (errors, distance, from + start, from + end)
+                })
+            })
+            .min_by_key(|&(errors, distance, start, _)| (errors, distance, start))
+            .map(|(_, _, start, end)| {
+                let end = (end + quote.len() - pattern.len()).min(self.text.len());
+                (start, end)
+            })
+    }
+
+    fn span_at(&self, position: usize) -> &Span {
+        let index = self.spans.partition_point(|span| span.end <= position);
+        &self.spans[index.min(self.spans.len() - 1)]
+    }
+
+    fn context_hashes(&self, start: usize, end: usize) -> (Option<String>, Option<String>) {
+        let prefix = &self.text[start.saturating_sub(CONTEXT_CHARS)..start];
+        let suffix = &self.text[end..(end + CONTEXT_CHARS).min(self.text.len())];
+        (context_hash(prefix), context_hash(suffix))
+    }
+}
+
+/// Folds `text` and collapses whitespace runs into single spaces.
+fn normalize(text: &str) -> Vec<char> {
+    let folded = fts::fold(text);
+    let mut normalized = Vec::with_capacity(folded.len());
+    for word in folded.split_whitespace() {
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.extend(word.chars());
+    }
+    normalized
+}
+
+fn context_hash(context: &[char]) -> Option<String> {
+    let context = context.iter().collect::<String>();
+    let context = context.trim();
+    if context.is_empty() {
+        return None;
+    }
+    let digest = format!("{:x}", Sha256::digest(context.as_bytes()));
+    Some(digest[..CONTEXT_HASH_LEN].to_string())
+}
+
+fn trigrams(text: &[char]) -> HashSet<[char; 3]> {
+    text.windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect()
+}
+
+/// Sellers' algorithm: the substring of `text` with the smallest edit
+/// distance to `pattern`, as `(distance, start, end)`.
+fn best_substring(pattern: &[char], text: &[char]) -> Option<(usize, usize, usize)> {
+    let rows = pattern.len() + 1;
+    let mut previous: Vec<usize> = (0..rows).collect();
+    let mut previous_start = vec![0; rows];
+    let mut current = vec![0; rows];
+    let mut current_start = vec![0; rows];
+    let mut best: Option<(usize, usize, usize)> = None;
+
+    for (column, ch) in text.iter().enumerate() {
+        current[0] = 0;
+        current_start[0] = column + 1;
+        for row in 1..rows {
+            let substitution = previous[row - 1] + usize::from(pattern[row - 1] != *ch);
+            let insertion = previous[row] + 1;
+            let deletion = current[row - 1] + 1;
+            (current[row], current_start[row]) =
+                if substitution <= insertion && substitution <= deletion {
+                    (substitution, previous_start[row - 1])
+                } else if deletion <= insertion {
+                    (deletion, current_start[row - 1])
+                } else {
+                    (insertion, previous_start[row])
+                };
+        }
+        let distance = current[rows - 1];
+        if best.is_none_or(|(errors, _, _)| distance < errors) {
+            best = Some((distance, current_start[rows - 1], column + 1));
+        }
+        std::mem::swap(&mut previous, &mut current);
+        std::mem::swap(&mut previous_start, &mut current_start);
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{repo, test_support::migrated_db};
+
+    const QUOTE: &str = "based solely on attention mechanisms";
+
+    fn passage(page: i32, paragraph: i32, content: &str) -> Passage {
+        Passage {
+            page,
+            paragraph,
+            content: content.to_string(),
+        }
+    }
+
+    /// The passages of a revised file: the quoted paragraph moved from page 1
+    /// to page 2.
+    fn revised() -> Vec<Passage> {
+        vec![
+            passage(1, 0, "Recurrent models process tokens one after another."),
+            passage(1, 1, "Convolutions limit the distance between positions."),
+            passage(
+                2,
+                0,
+                "We propose the Transformer, based solely on attention mechanisms, \
+                 dispensing with recurrence.",
+            ),
+        ]
+    }
+
+    fn found_page(passages: &[Passage], quote: &str, page: i32) -> Option<i32> {
+        let hint = Hint {
+            page,
+            ..Default::default()
+        };
+        Document::new(passages)
+            .resolve(&normalize(quote), &hint)
+            .map(|found| found.page)
+    }
+
+    #[test]
+    fn exact_matches_prefer_the_hinted_page() {
+        let passages = vec![
+            passage(1, 0, "Attention is all you need."),
+            passage(3, 0, "Attention is all you need, again."),
+        ];
+
+        assert_eq!(found_page(&passages, "attention is all", 3), Some(3));
+        assert_eq!(found_page(&passages, "attention is all", 2), Some(1));
+    }
+
+    #[test]
+    fn quotes_match_across_line_breaks_and_ligatures() {
+        let passages = vec![passage(4, 0, "The ﬁnal\nlayer normalizes   outputs.")];
+
+        assert_eq!(found_page(&passages, "final layer normalizes", 1), Some(4));
+    }
+
+    #[test]
+    fn edited_quotes_resolve_within_the_error_rate() {
+        let edited = "based only on attention mechanisms";
+        let errors = best_substring(&normalize(edited), &normalize(QUOTE))
+            .unwrap()
+            .0;
+        assert!(errors as f32 <= edited.chars().count() as f32 * FUZZY_MAX_ERROR_RATE);
+
+        assert_eq!(found_page(&revised(), edited, 1), Some(2));
+        assert_eq!(
+            found_page(&revised(), "built entirely from gated units", 1),
+            None
+        );
+    }
+
+    #[test]
+    fn best_substring_reports_distance_and_span() {
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+
+        assert_eq!(
+            best_substring(&chars("abc"), &chars("xxabcxx")),
+            Some((0, 2, 5))
+        );
+        assert_eq!(
+            best_substring(&chars("abcdef"), &chars("zzabXdefzz")),
+            Some((1, 2, 8))
+        );
+        assert_eq!(best_substring(&chars("abc"), &[]), None);
+    }
+
+    /// A paper with one note quoting `quote` on `page`, last resolved against
+    /// `file_hash`. Returns the paper and note ids.
+    fn note_on(
+        conn: &Connection,
+        page: i32,
+        quote: &str,
+        file_hash: Option<&str>,
+    ) -> (String, String) {
+        conn.execute(
+            "INSERT INTO paper (id, workspaceId, title, path, fileHash, createdAt, updatedAt) \
+             SELECT 'paper', id, 'Paper', '/paper.pdf', 'v1', createdAt, createdAt \
+             FROM workspace",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO note \
+             (id, paperId, page, content, createdAt, updatedAt, anchorQuote, anchorParagraph, \
+              anchorStatus, anchorFileHash) \
+             VALUES ('note', 'paper', ?1, 'Key claim', '', '', ?2, 1, 'anchored', ?3)",
+            params![page, quote, file_hash],
+        )
+        .unwrap();
+        ("paper".to_string(), "note".to_string())
+    }
+
+    fn reanchored(page: i32, quote: &str, file_hash: Option<&str>) -> (i32, String, String) {
+        let db = migrated_db();
+        repo::create_workspace(&db, "Anchors").unwrap();
+        let conn = db.connection();
+        let (paper_id, note_id) = note_on(&conn, page, quote, file_hash);
+
+        assert_eq!(
+            reanchor_notes(&conn, &paper_id, "v2", &revised()).unwrap(),
+            1
+        );
+        // Already resolved against this version.
+        assert_eq!(
+            reanchor_notes(&conn, &paper_id, "v2", &revised()).unwrap(),
+            0
+        );
+        conn.query_row(
+            "SELECT page, anchorStatus, anchorFileHash FROM note WHERE id = ?1",
+            params![note_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn quote_found_on_another_page_relocates_the_note() {
+        let (page, status, file_hash) = reanchored(1, QUOTE, Some("v1"));
+
+        assert_eq!(page, 2);
+        assert_eq!(status, STATUS_RELOCATED);
+        assert_eq!(file_hash, "v2");
+    }
+
+    #[test]
+    fn edited_quote_still_relocates_the_note() {
+        let (page, status, _) = reanchored(1, "based only on attention mechanisms", Some("v1"));
+
+        assert_eq!(page, 2);
+        assert_eq!(status, STATUS_RELOCATED);
+    }
+
+    #[test]
+    fn removed_quote_orphans_the_note_in_place() {
+        let (page, status, file_hash) =
+            reanchored(1, "built entirely from gated recurrent units", Some("v1"));
+
+        assert_eq!(page, 1);
+        assert_eq!(status, STATUS_ORPHANED);
+        assert_eq!(file_hash, "v2");
+    }
+
+    #[test]
+    fn first_resolution_keeps_the_page() {
+        let (page, status, file_hash) = reanchored(1, QUOTE, None);
+
+        assert_eq!(page, 1);
+        assert_eq!(status, STATUS_ANCHORED);
+        assert_eq!(file_hash, "v2");
+    }
 }
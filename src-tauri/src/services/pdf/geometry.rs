@@ -0,0 +1,220 @@
+//! Page geometry and the normalized page coordinates notes and highlights are
+//! stored in (see [`PageGeometry`]).
+//!
+//! Positions recorded before coordinates were normalized had no declared
+//! space. Those outside 0..1 are read as PDF user-space points, the unit the
+//! viewer reports page positions in, and converted against the page geometry
+//! of the paper's file by [`convert_legacy_notes`].
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use lopdf::{Document, Object, ObjectId};
+use rusqlite::params;
+
+use crate::{
+    domain::PageGeometry,
+    services::Db,
+    telemetry::{IpcError, IpcResult, IpcStatus},
+};
+
+/// US Letter, used when a page declares no usable MediaBox.
+const DEFAULT_PAGE_BOX: [f32; 4] = [0.0, 0.0, 612.0, 792.0];
+/// Upper bound on the page tree depth walked for inherited attributes.
+const MAX_TREE_DEPTH: usize = 32;
+
+/// Geometry of every page of the PDF at `path`, in page order.
+pub fn page_geometries(path: &Path) -> anyhow::Result<Vec<PageGeometry>> {
+    let document =
+        Document::load(path).with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(document
+        .get_pages()
+        .into_values()
+        .map(|page_id| read_geometry(&document, page_id))
+        .collect())
+}
+
+/// Geometry of the 1-based `page` of the PDF at `path`.
+pub fn page_geometry(path: &Path, page: i32) -> anyhow::Result<PageGeometry> {
+    let document =
+        Document::load(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let page_id = u32::try_from(page)
+        .ok()
+        .and_then(|page| document.get_pages().get(&page).copied())
+        .ok_or_else(|| anyhow!("{} has no page {page}", path.display()))?;
+    Ok(read_geometry(&document, page_id))
+}
+
+/// Maps a point in PDF user space to normalized page coordinates.
+pub fn to_normalized(geometry: &PageGeometry, x: f32, y: f32) -> (f32, f32) {
+    let [left, bottom, right, top] = geometry.page_box;
+    let u = (x - left) / (right - left);
+    let v = (top - y) / (top - bottom);
+    match geometry.rotation {
+        90 => (1.0 - v, u),
+        180 => (1.0 - u, 1.0 - v),
+        270 => (v, 1.0 - u),
+        _ => (u, v),
+    }
+}
+
+/// Rejects positions outside the normalized page.
+pub(crate) fn validate_position(x: f32, y: f32) -> IpcResult<()> {
+    let inside = |value: f32| value.is_finite() && (0.0..=1.0).contains(&value);
+    if inside(x) && inside(y) {
+        Ok(())
+    } else {
+        Err(IpcError::new(
+            IpcStatus::BadRequest,
+            format!(
+                "Position ({x}, {y}) is outside the page; expected coordinates between 0 and 1"
+            ),
+        ))
+    }
+}
+
+pub(crate) fn encode_geometry(geometry: &PageGeometry) -> IpcResult<String> {
+    serde_json::to_string(geometry).map_err(|err| {
+        IpcError::new(
+            IpcStatus::Internal,
+            format!("Failed to encode page geometry: {err}"),
+        )
+    })
+}
+
+/// Converts notes still flagged with a legacy position for which the paper
+/// file can be read. Unreadable files keep their notes flagged for a later run.
+/// Returns the number of converted notes.
+pub fn convert_legacy_notes(db: &Db) -> IpcResult<usize> {
+    let papers = {
+        let conn = db.connection();
+        let mut stmt = conn
+            .prepare(
+                "SELECT DISTINCT paper.id, paper.path, paper.lastSeenPath \
+                 FROM note JOIN paper ON paper.id = note.paperId \
+                 WHERE note.positionLegacy = 1",
+            )
+            .map_err(db_error)?;
+        let papers = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        papers
+    };
+
+    let mut converted = 0;
+    for (paper_id, path, last_seen_path) in papers {
+        let source = [Some(path), last_seen_path]
+            .into_iter()
+            .flatten()
+            .find(|candidate| Path::new(candidate).is_file());
+        let Some(source) = source else {
+            continue;
+        };
+        // Read the file before taking the DB lock.
+        let geometries = match page_geometries(Path::new(&source)) {
+            Ok(geometries) => geometries,
+            Err(err) => {
+                tracing::warn!(
+                    target = "pdf",
+                    paper_id = %paper_id,
+                    error = %format!("{err:#}"),
+                    "failed to read page geometry for legacy notes"
+                );
+                continue;
+            }
+        };
+
+        let mut conn = db.connection();
+        let tx = conn.transaction().map_err(db_error)?;
+        let notes = tx
+            .prepare("SELECT id, page, x, y FROM note WHERE paperId = ?1 AND positionLegacy = 1")
+            .map_err(db_error)?
+            .query_map(params![&paper_id], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i32>(1)?,
+                    row.get::<_, f32>(2)?,
+                    row.get::<_, f32>(3)?,
+                ))
+            })
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        for (note_id, page, x, y) in notes {
+            let Some(geometry) = usize::try_from(page - 1)
+                .ok()
+                .and_then(|index| geometries.get(index))
+            else {
+                continue;
+            };
+            let (x, y) = to_normalized(geometry, x, y);
+            tx.execute(
+                "UPDATE note SET x = ?1, y = ?2, pageGeometry = ?3, positionLegacy = 0 \
+                 WHERE id = ?4",
+                params![
+                    x.clamp(0.0, 1.0),
+                    y.clamp(0.0, 1.0),
+                    encode_geometry(geometry)?,
+                    &note_id
+                ],
+            )
+            .map_err(db_error)?;
+            converted += 1;
+        }
+        tx.commit().map_err(db_error)?;
+    }
+    Ok(converted)
+}
+
+fn read_geometry(document: &Document, page_id: ObjectId) -> PageGeometry {
+    let media_box = inherited(document, page_id, b"MediaBox").and_then(|box_| rect(document, box_));
+    let crop_box = inherited(document, page_id, b"CropBox").and_then(|box_| rect(document, box_));
+    let rotation = inherited(document, page_id, b"Rotate")
+        .and_then(|rotate| rotate.as_i64().ok())
+        .map(|degrees| (degrees.rem_euclid(360) / 90 * 90) as i32)
+        .unwrap_or(0);
+
+    PageGeometry {
+        page_box: crop_box.or(media_box).unwrap_or(DEFAULT_PAGE_BOX),
+        rotation,
+    }
+}
+
+/// Page attribute `key`, looked up on the page and then its ancestors.
+fn inherited<'a>(document: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node = document.get_dictionary(page_id).ok()?;
+    for _ in 0..MAX_TREE_DEPTH {
+        if let Ok(value) = node.get(key) {
+            return document.dereference(value).ok().map(|(_, value)| value);
+        }
+        let parent = node.get(b"Parent").ok()?.as_reference().ok()?;
+        node = document.get_dictionary(parent).ok()?;
+    }
+    None
+}
+
+/// A PDF rectangle as `[left, bottom, right, top]`, or `None` when it is
+/// malformed or empty.
+fn rect(document: &Document, value: &Object) -> Option<[f32; 4]> {
+    let values = value
+        .as_array()
+        .ok()?
+        .iter()
+        .map(|item| document.dereference(item).ok()?.1.as_float().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let [x1, y1, x2, y2] = <[f32; 4]>::try_from(values).ok()?;
+    let rect = [x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)];
+    (rect[2] > rect[0] && rect[3] > rect[1]).then_some(rect)
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
@@ -0,0 +1,652 @@
+//! Bibliographic metadata read from a PDF: the document Info dictionary, the
+//! XMP packet of the catalog and, for the DOI and arXiv identifier, the text of
+//! the first pages.
+//!
+//! Where Info and XMP both set a field, XMP wins since it is stored as Unicode.
+//! Metadata is read once per file: the page count follows the file, while the
+//! other fields only fill in what is still empty, and the title replaces the
+//! file-name title only the first time and never once the paper was edited, so
+//! later edits are kept.
+
+use std::{panic, path::Path};
+
+use anyhow::Context;
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone};
+use lopdf::{Dictionary, Document};
+use rusqlite::{params, OptionalExtension};
+
+use crate::{
+    domain::Paper,
+    services::{authors, repo, search, Db},
+    telemetry::{IpcError, IpcResult, IpcStatus},
+    utils::time::now_iso,
+};
+
+/// Pages whose text is searched for a DOI or arXiv identifier.
+const SCANNED_PAGES: u32 = 2;
+/// Titles tools write when the author set none, compared lowercased.
+const PLACEHOLDER_TITLES: &[&str] = &["untitled", "title", "no title", "document", "paper"];
+/// File extensions that give a title away as a file name.
+const FILE_NAME_SUFFIXES: &[&str] = &[".pdf", ".doc", ".docx", ".dvi", ".tex", ".ps", ".odt"];
+
+#[derive(Debug, Clone, Default)]
+pub struct PdfMetadata {
+    pub title: Option<String>,
+    pub authors: Vec<String>,
+    pub subject: Option<String>,
+    pub keywords: Option<String>,
+    /// RFC 3339.
+    pub created_at: Option<String>,
+    pub page_count: i32,
+    pub doi: Option<String>,
+    pub arxiv_id: Option<String>,
+}
+
+/// Reads the metadata of the PDF at `path`.
+pub fn read_metadata(path: &Path) -> anyhow::Result<PdfMetadata> {
+    let document =
+        Document::load(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    let info = info_dictionary(&document);
+    let info_text = |key: &[u8]| info.and_then(|info| dictionary_text(&document, info, key));
+    let xmp = xmp_packet(&document).unwrap_or_default();
+
+    let title = xmp_property(&xmp, "dc:title")
+        .filter(|title| plausible_title(title))
+        .or_else(|| info_text(b"Title").filter(|title| plausible_title(title)));
+
+    let mut authors = xmp_list(&xmp, "dc:creator");
+    if authors.len() == 1 {
+        authors = split_authors(&authors[0]);
+    }
+    if authors.is_empty() {
+        authors = info_text(b"Author")
+            .map(|author| split_authors(&author))
+            .unwrap_or_default();
+    }
+
+    let subject = xmp_property(&xmp, "dc:description").or_else(|| info_text(b"Subject"));
+    let keywords = xmp_property(&xmp, "pdf:Keywords")
+        .or_else(|| {
+            let subjects = xmp_list(&xmp, "dc:subject");
+            (!subjects.is_empty()).then(|| subjects.join(", "))
+        })
+        .or_else(|| info_text(b"Keywords"));
+    let created_at = xmp_property(&xmp, "xmp:CreateDate")
+        .and_then(|date| parse_xmp_date(&date))
+        .or_else(|| info_text(b"CreationDate").and_then(|date| parse_pdf_date(&date)));
+
+    let pages = document.get_pages();
+    let page_count = i32::try_from(pages.len()).unwrap_or(i32::MAX);
+
+    // Identifiers declared in the metadata come first, then those printed on
+    // the first pages.
+    let mut sources = vec![
+        xmp_property(&xmp, "prism:doi"),
+        xmp_property(&xmp, "pdfx:doi"),
+        xmp_property(&xmp, "dc:identifier"),
+        info_text(b"doi"),
+        info_text(b"DOI"),
+        subject.clone(),
+        keywords.clone(),
+    ];
+    sources.push(first_pages_text(&document, pages.keys().copied()));
+    let sources = sources.into_iter().flatten().collect::<Vec<_>>();
+
+    Ok(PdfMetadata {
+        title,
+        authors,
+        subject,
+        keywords,
+        created_at,
+        page_count,
+        doi: sources.iter().find_map(|source| find_doi(source)),
+        arxiv_id: sources.iter().find_map(|source| find_arxiv_id(source)),
+    })
+}
+
+/// Reads and stores the metadata of the papers whose file it was not read
+/// from yet, updating them in place. Best effort: failures are logged.
+pub fn import_metadata(db: &Db, papers: &mut [Paper]) {
+    for paper in papers.iter_mut() {
+        match import_paper_metadata(db, paper) {
+            Ok(Some(updated)) => *paper = updated,
+            Ok(None) => {}
+            Err(err) => tracing::warn!(
+                target = "pdf",
+                paper_id = %paper.id,
+                error = %err,
+                "failed to read paper metadata"
+            ),
+        }
+    }
+}
+
+/// Returns the updated paper, or `None` when the metadata of its file was
+/// already stored. Reads the file without holding the DB lock.
+fn import_paper_metadata(db: &Db, paper: &Paper) -> IpcResult<Option<Paper>> {
+    let metadata_hash: Option<String> = {
+        let conn = db.connection();
+        conn.query_row(
+            "SELECT metadataHash FROM paper WHERE id = ?1",
+            params![&paper.id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(db_error)?
+        .flatten()
+    };
+    if metadata_hash.as_deref() == Some(paper.file_hash.as_str()) {
+        return Ok(None);
+    }
+
+    let metadata = read_metadata(Path::new(&paper.path))?;
+    {
+        let mut conn = db.connection();
+        let tx = conn.transaction().map_err(db_error)?;
+        tx.execute(
+            "UPDATE paper SET \
+             title = CASE WHEN metadataHash IS NULL AND editedAt IS NULL \
+               THEN COALESCE(?1, title) ELSE title END, \
+             doi = COALESCE(NULLIF(trim(doi), ''), ?2), \
+             subject = COALESCE(subject, ?3), \
+             keywords = COALESCE(keywords, ?4), \
+             pdfCreatedAt = COALESCE(pdfCreatedAt, ?5), \
+             arxivId = COALESCE(arxivId, ?6), \
+             pageCount = ?7, metadataHash = ?8, updatedAt = ?9 \
+             WHERE id = ?10",
+            params![
+                metadata.title.as_deref(),
+                metadata.doi.as_deref(),
+                metadata.subject.as_deref(),
+                metadata.keywords.as_deref(),
+                metadata.created_at.as_deref(),
+                metadata.arxiv_id.as_deref(),
+                metadata.page_count,
+                &paper.file_hash,
+                now_iso(),
+                &paper.id
+            ],
+        )
+        .map_err(db_error)?;
+
+        let has_authors = tx
+            .query_row(
+                "SELECT 1 FROM paper_author WHERE paperId = ?1 LIMIT 1",
+                params![&paper.id],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(db_error)?
+            .is_some();
+        if !has_authors && !metadata.authors.is_empty() {
+            authors::set_paper_authors(&tx, &paper.id, &metadata.authors).map_err(db_error)?;
+        }
+        search::upsert_paper_entry(&tx, &paper.id).map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+    }
+
+    repo::get_paper(db, &paper.id).map(Some)
+}
+
+fn info_dictionary(document: &Document) -> Option<&Dictionary> {
+    let info = document.trailer.get(b"Info").ok()?;
+    document.dereference(info).ok()?.1.as_dict().ok()
+}
+
+fn dictionary_text(document: &Document, dictionary: &Dictionary, key: &[u8]) -> Option<String> {
+    let value = document.dereference(dictionary.get(key).ok()?).ok()?.1;
+    clean(&decode_text_string(value.as_str().ok()?))
+}
+
+/// The XMP packet referenced by the catalog, as text.
+fn xmp_packet(document: &Document) -> Option<String> {
+    let metadata = document.catalog().ok()?.get(b"Metadata").ok()?;
+    let stream = document.dereference(metadata).ok()?.1.as_stream().ok()?;
+    let content = stream.get_plain_content().ok()?;
+    Some(String::from_utf8_lossy(&content).into_owned())
+}
+
+/// Decodes a PDF text string: UTF-16BE or UTF-8 after a byte order mark,
+/// PDFDocEncoding otherwise, read here as Latin-1.
+fn decode_text_string(bytes: &[u8]) -> String {
+    if let Some(utf16) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        let units = utf16
+            .chunks_exact(2)
+            .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    } else if let Some(utf8) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        String::from_utf8_lossy(utf8).into_owned()
+    } else {
+        bytes.iter().map(|&byte| char::from(byte)).collect()
+    }
+}
+
+/// Text of the first pages. Extraction failures leave it out.
+fn first_pages_text(document: &Document, pages: impl Iterator<Item = u32>) -> Option<String> {
+    let pages = pages.take(SCANNED_PAGES as usize).collect::<Vec<_>>();
+    // Text extraction panics on some malformed fonts, like pdf-extract does.
+    panic::catch_unwind(panic::AssertUnwindSafe(|| document.extract_text(&pages)))
+        .ok()?
+        .ok()
+}
+
+/// Value of a simple XMP property, written either as an element or as an
+/// attribute of `rdf:Description`. For `rdf:Alt` and `rdf:Seq` values this is
+/// the first item.
+fn xmp_property(xmp: &str, name: &str) -> Option<String> {
+    if let Some(body) = xmp_element(xmp, name) {
+        let value = xmp_items(body)
+            .into_iter()
+            .next()
+            .or_else(|| clean(&decode_entities(body)));
+        if value.is_some() {
+            return value;
+        }
+    }
+
+    let attribute = format!("{name}=");
+    let start = xmp.find(&attribute)? + attribute.len();
+    let quote = xmp[start..]
+        .chars()
+        .next()
+        .filter(|c| *c == '"' || *c == '\'')?;
+    let value = &xmp[start + 1..];
+    clean(&decode_entities(&value[..value.find(quote)?]))
+}
+
+/// Items of an XMP list property such as `dc:creator`.
+fn xmp_list(xmp: &str, name: &str) -> Vec<String> {
+    xmp_element(xmp, name).map(xmp_items).unwrap_or_default()
+}
+
+/// Content of the element `name`, or `None` when it is missing or empty.
+fn xmp_element<'a>(xmp: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    let mut from = 0;
+    while let Some(offset) = xmp[from..].find(&open) {
+        let start = from + offset + open.len();
+        from = start;
+        // Skip longer names sharing the prefix, e.g. `dc:title` in `dc:titles`.
+        let rest = &xmp[start..];
+        if !rest.starts_with(['>', ' ', '\t', '\r', '\n']) {
+            continue;
+        }
+        let tag_end = rest.find('>')?;
+        if rest[..tag_end].ends_with('/') {
+            continue;
+        }
+        let body = &rest[tag_end + 1..];
+        return body.find(&close).map(|end| &body[..end]);
+    }
+    None
+}
+
+fn xmp_items(body: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("<rdf:li") {
+        let after = &rest[start..];
+        let Some(tag_end) = after.find('>') else {
+            break;
+        };
+        let content = &after[tag_end + 1..];
+        let Some(end) = content.find("</rdf:li>") else {
+            break;
+        };
+        if let Some(item) = clean(&decode_entities(&content[..end])) {
+            items.push(item);
+        }
+        rest = &content[end..];
+    }
+    items
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        let entity = &rest[start..];
+        let Some(end) = entity.find(';').filter(|end| *end <= 10) else {
+            decoded.push('&');
+            rest = &entity[1..];
+            continue;
+        };
+        let name = &entity[1..end];
+        let value = match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => name
+                .strip_prefix("#x")
+                .or_else(|| name.strip_prefix("#X"))
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| name.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match value {
+            Some(value) => {
+                decoded.push(value);
+                rest = &entity[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &entity[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Collapses whitespace; `None` when nothing is left.
+fn clean(text: &str) -> Option<String> {
+    let cleaned = text
+        .split(|c: char| c.is_whitespace() || c == '\0')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!cleaned.is_empty()).then_some(cleaned)
+}
+
+/// Rejects titles that are placeholders or file names.
+fn plausible_title(title: &str) -> bool {
+    let lower = title.to_lowercase();
+    title.chars().filter(|c| c.is_alphabetic()).count() >= 3
+        && !PLACEHOLDER_TITLES.contains(&lower.as_str())
+        && !lower.starts_with("microsoft word - ")
+        && !FILE_NAME_SUFFIXES
+            .iter()
+            .any(|suffix| lower.ends_with(suffix))
+}
+
+/// Splits an author field on `;`, or on `,` and ` and ` when every part then
+/// looks like a full name, so `Smith, John` stays one author.
+fn split_authors(field: &str) -> Vec<String> {
+    let parts = |separators: &[&str]| {
+        let mut parts = vec![field.to_string()];
+        for separator in separators {
+            parts = parts
+                .iter()
+                .flat_map(|part| part.split(separator))
+                .map(str::to_string)
+                .collect();
+        }
+        parts
+            .iter()
+            .filter_map(|part| clean(part))
+            .collect::<Vec<_>>()
+    };
+
+    if field.contains(';') {
+        return parts(&[";"]);
+    }
+    let split = parts(&[",", " and ", " & "]);
+    if split.len() > 1 && split.iter().all(|name| name.contains(' ')) {
+        split
+    } else {
+        clean(field).into_iter().collect()
+    }
+}
+
+/// Parses a PDF date, `D:YYYYMMDDHHmmSSOHH'mm'` with everything after the
+/// year optional.
+fn parse_pdf_date(date: &str) -> Option<String> {
+    let date = date.trim();
+    let date = date.strip_prefix("D:").unwrap_or(date);
+    let digits = date.chars().take_while(char::is_ascii_digit).count();
+    if digits < 4 {
+        return None;
+    }
+    let field = |start: usize, len: usize, default: u32| -> Option<u32> {
+        if start + len <= digits {
+            date[start..start + len].parse().ok()
+        } else {
+            Some(default)
+        }
+    };
+    let year = date[..4].parse().ok()?;
+    let (month, day) = (field(4, 2, 1)?, field(6, 2, 1)?);
+    let (hour, minute, second) = (field(8, 2, 0)?, field(10, 2, 0)?, field(12, 2, 0)?);
+
+    let zone = &date[digits..];
+    let offset_seconds = match zone.chars().next() {
+        Some(sign @ ('+' | '-')) => {
+            let numbers = zone[1..]
+                .split(|c: char| !c.is_ascii_digit())
+                .filter(|part| !part.is_empty())
+                .map(|part| part.parse::<i32>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            let hours = numbers.first().copied().unwrap_or(0);
+            let minutes = numbers.get(1).copied().unwrap_or(0);
+            let seconds = hours * 3600 + minutes * 60;
+            if sign == '-' {
+                -seconds
+            } else {
+                seconds
+            }
+        }
+        _ => 0,
+    };
+
+    let offset = FixedOffset::east_opt(offset_seconds)?;
+    let local = NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, minute, second)?;
+    let date = offset.from_local_datetime(&local).single()?;
+    Some(date.to_rfc3339())
+}
+
+fn parse_xmp_date(date: &str) -> Option<String> {
+    let date = date.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(date) {
+        return Some(date.to_rfc3339());
+    }
+    // XMP allows dropping the time or the seconds.
+    let day = date.get(..10)?;
+    let local = NaiveDate::parse_from_str(day, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?;
+    Some(local.and_utc().to_rfc3339())
+}
+
+/// First DOI in `text`: `10.`, a registrant code of 4 to 9 digits, `/` and a
+/// suffix running to the next whitespace.
+fn find_doi(text: &str) -> Option<String> {
+    for (start, _) in text.match_indices("10.") {
+        if text[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+        {
+            continue;
+        }
+        let rest = &text[start + 3..];
+        let registrant = rest.chars().take_while(char::is_ascii_digit).count();
+        if !(4..=9).contains(&registrant) || !rest[registrant..].starts_with('/') {
+            continue;
+        }
+        let suffix_start = registrant + 1;
+        let suffix_len = rest[suffix_start..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '"' | '<' | '>'))
+            .unwrap_or(rest.len() - suffix_start);
+        let mut suffix = &rest[suffix_start..suffix_start + suffix_len];
+        loop {
+            let trimmed = suffix.trim_end_matches(['.', ',', ';', ':', '\'', ']', '}']);
+            // Keep a closing parenthesis that belongs to the suffix.
+            let trimmed = match trimmed.strip_suffix(')') {
+                Some(inner) if !inner.contains('(') => inner,
+                _ => trimmed,
+            };
+            if trimmed.len() == suffix.len() {
+                break;
+            }
+            suffix = trimmed;
+        }
+        if !suffix.is_empty() {
+            return Some(format!("10.{}/{suffix}", &rest[..registrant]));
+        }
+    }
+    None
+}
+
+/// First arXiv identifier following an `arXiv` mention, without its version:
+/// `2101.00001` or old-style `hep-th/9901001`.
+fn find_arxiv_id(text: &str) -> Option<String> {
+    let lower = text.to_ascii_lowercase();
+    for (start, _) in lower.match_indices("arxiv") {
+        let rest = &lower[start + "arxiv".len()..];
+        let rest = rest
+            .strip_prefix(".org/abs/")
+            .or_else(|| rest.strip_prefix(".org/pdf/"))
+            .unwrap_or_else(|| rest.trim_start_matches([':', ' ']));
+
+        // New style: YYMM.NNNN or YYMM.NNNNN.
+        let digits = rest.chars().take_while(char::is_ascii_digit).count();
+        if digits == 4 && rest[4..].starts_with('.') {
+            let number = rest[5..].chars().take_while(char::is_ascii_digit).count();
+            if (4..=5).contains(&number) {
+                return Some(rest[..5 + number].to_string());
+            }
+        }
+
+        // Old style: archive(.SUBJECT)?/YYMMNNN.
+        let archive = rest
+            .find('/')
+            .map(|slash| &rest[..slash])
+            .filter(|archive| {
+                !archive.is_empty()
+                    && archive
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || matches!(c, '-' | '.'))
+            });
+        if let Some(archive) = archive {
+            let number = &rest[archive.len() + 1..];
+            if number.chars().take_while(char::is_ascii_digit).count() == 7 {
+                // Subject classes are upper case, e.g. `math.AG`; lowering
+                // ASCII keeps byte offsets, so slice the original text.
+                let offset = lower.len() - rest.len();
+                let archive = &text[offset..offset + archive.len()];
+                return Some(format!("{archive}/{}", &number[..7]));
+            }
+        }
+    }
+    None
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doi_is_found_without_trailing_punctuation() {
+        let cases = [
+            ("doi:10.1038/nature14539.", Some("10.1038/nature14539")),
+            ("(see 10.1145/3065386),", Some("10.1145/3065386")),
+            (
+                "[10.48550/arXiv.1706.03762]",
+                Some("10.48550/arXiv.1706.03762"),
+            ),
+            (
+                "https://doi.org/10.1000/xyz(2019).",
+                Some("10.1000/xyz(2019)"),
+            ),
+            ("DOI 10.1234/abc; accepted", Some("10.1234/abc")),
+            ("<a href=\"10.1234/abc\">", Some("10.1234/abc")),
+            ("version 10.12/abc", None),
+            ("x10.1234/abc", None),
+            ("10.1234 without a suffix", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(find_doi(text).as_deref(), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn arxiv_ids_of_both_styles_are_found_without_version() {
+        let cases = [
+            ("arXiv:1706.03762v5 [cs.CL]", Some("1706.03762")),
+            ("https://arxiv.org/abs/2101.00001", Some("2101.00001")),
+            ("arxiv.org/pdf/0704.0001v2", Some("0704.0001")),
+            ("ARXIV 2101.00001", Some("2101.00001")),
+            ("arXiv:hep-th/9901001v2", Some("hep-th/9901001")),
+            ("arXiv:math.AG/0309136", Some("math.AG/0309136")),
+            ("arXiv:1706.037", None),
+            ("arXiv:hep-th/99010", None),
+            ("1706.03762", None),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(find_arxiv_id(text).as_deref(), expected, "{text}");
+        }
+    }
+
+    #[test]
+    fn pdf_dates_parse_with_and_without_offsets() {
+        let cases = [
+            ("D:20170612173000Z", Some("2017-06-12T17:30:00+00:00")),
+            ("D:20170612173000+02'00'", Some("2017-06-12T17:30:00+02:00")),
+            ("D:20170612173000-05'30", Some("2017-06-12T17:30:00-05:30")),
+            ("D:20170612173000", Some("2017-06-12T17:30:00+00:00")),
+            ("20170612", Some("2017-06-12T00:00:00+00:00")),
+            ("D:2017", Some("2017-01-01T00:00:00+00:00")),
+            ("D:17", None),
+            ("D:20171312", None),
+        ];
+        for (date, expected) in cases {
+            assert_eq!(parse_pdf_date(date).as_deref(), expected, "{date}");
+        }
+    }
+
+    #[test]
+    fn authors_split_only_into_full_names() {
+        let cases: [(&str, &[&str]); 6] = [
+            ("Smith, John", &["Smith, John"]),
+            ("A B, C D", &["A B", "C D"]),
+            (
+                "Ashish Vaswani and Noam Shazeer & Niki Parmar",
+                &["Ashish Vaswani", "Noam Shazeer", "Niki Parmar"],
+            ),
+            ("Smith, J.; Doe, J.", &["Smith, J.", "Doe, J."]),
+            ("  Ada   Lovelace ", &["Ada Lovelace"]),
+            (" ", &[]),
+        ];
+        for (field, expected) in cases {
+            assert_eq!(split_authors(field), expected, "{field}");
+        }
+    }
+
+    #[test]
+    fn placeholder_and_file_name_titles_are_rejected() {
+        let cases = [
+            ("Attention Is All You Need", true),
+            ("Untitled", false),
+            ("DOCUMENT", false),
+            ("Microsoft Word - draft", false),
+            ("paper_v2.pdf", false),
+            ("A1", false),
+        ];
+        for (title, expected) in cases {
+            assert_eq!(plausible_title(title), expected, "{title}");
+        }
+    }
+
+    #[test]
+    fn text_strings_decode_by_byte_order_mark() {
+        let cases: [(&[u8], &str); 4] = [
+            (&[0xFE, 0xFF, 0x00, 0x41, 0x00, 0xE9], "Aé"),
+            (&[0xFE, 0xFF, 0xD8, 0x3D, 0xDE, 0x00], "😀"),
+            (&[0xEF, 0xBB, 0xBF, 0xC3, 0xA9], "é"),
+            (b"Caf\xE9", "Café"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(decode_text_string(bytes), expected, "{bytes:?}");
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod anchor;
+pub mod geometry;
+pub mod metadata;
+pub mod snapshot;
+pub mod text;
@@ -0,0 +1,340 @@
+use std::{fs, panic, path::Path};
+
+use anyhow::{anyhow, Context};
+use rusqlite::{params, OptionalExtension};
+use uuid::Uuid;
+
+use crate::{
+    domain::Paper,
+    telemetry::{IpcError, IpcResult, IpcStatus},
+    utils::time::now_iso,
+};
+
+use super::anchor;
+use crate::services::{repo, search, Db};
+
+/// Paragraphs longer than this are split on sentence boundaries.
+const MAX_PASSAGE_CHARS: usize = 1200;
+/// Fragments shorter than this (headers, page numbers) are merged forward.
+const MIN_PASSAGE_CHARS: usize = 40;
+
+#[derive(Debug, Clone)]
+pub struct Passage {
+    pub page: i32,
+    pub paragraph: i32,
+    pub content: String,
+}
+
+/// Extracts the text layer of a PDF page by page and splits it into passages.
+/// Scanned PDFs without a text layer yield an empty list.
+pub fn extract_passages(path: &Path) -> anyhow::Result<Vec<Passage>> {
+    let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+
+    // pdf-extract panics on some malformed fonts; treat that as an extraction error.
+    let pages = panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(&bytes))
+        .map_err(|_| anyhow!("text extraction panicked for {}", path.display()))?
+        .with_context(|| format!("failed to extract text from {}", path.display()))?;
+
+    let mut passages = Vec::new();
+    for (index, text) in pages.iter().enumerate() {
+        let page = index as i32 + 1;
+        for (paragraph, content) in split_paragraphs(text).into_iter().enumerate() {
+            passages.push(Passage {
+                page,
+                paragraph: paragraph as i32,
+                content,
+            });
+        }
+    }
+    Ok(passages)
+}
+
+/// Extracts and indexes the text of `paper` unless passages for its current
+/// `fileHash` are already stored, then re-anchors its notes against the new
+/// text. Extraction runs without holding the DB lock.
+pub fn index_paper(db: &Db, paper: &Paper) -> IpcResult<usize> {
+    let indexed_hash: Option<String> = {
+        let conn = db.connection();
+        conn.query_row(
+            "SELECT textHash FROM paper WHERE id = ?1",
+            params![&paper.id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(db_error)?
+        .flatten()
+    };
+    if indexed_hash.as_deref() == Some(paper.file_hash.as_str()) {
+        return Ok(0);
+    }
+
+    let passages = extract_passages(Path::new(&paper.path))
+        .map_err(|err| IpcError::new(IpcStatus::IoError, format!("{err:#}")))?;
+
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    store_passages(&tx, &paper.id, &paper.file_hash, &passages).map_err(db_error)?;
+    anchor::reanchor_notes(&tx, &paper.id, &paper.file_hash, &passages).map_err(db_error)?;
+    tx.commit().map_err(db_error)?;
+
+    Ok(passages.len())
+}
+
+/// Best-effort indexing used after imports; failures are logged, not returned.
+pub fn index_papers(db: &Db, papers: &[Paper]) {
+    for paper in papers {
+        if let Err(err) = index_paper(db, paper) {
+            tracing::warn!(
+                target = "pdf",
+                paper_id = %paper.id,
+                error = %err,
+                "failed to index paper text"
+            );
+        }
+    }
+}
+
+/// Indexes the text of the papers whose passages are missing or were extracted
+/// from another version of their file, e.g. papers imported before PDF text was
+/// indexed. Papers whose file is gone are left for later.
+pub fn index_stale_papers(db: &Db) -> IpcResult<()> {
+    let paper_ids = {
+        let conn = db.connection();
+        let mut stmt = conn
+            .prepare("SELECT id FROM paper WHERE textHash IS NULL OR textHash <> fileHash")
+            .map_err(db_error)?;
+        let paper_ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(db_error)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_error)?;
+        paper_ids
+    };
+
+    let mut papers = Vec::with_capacity(paper_ids.len());
+    for paper_id in &paper_ids {
+        papers.push(repo::get_paper(db, paper_id)?);
+    }
+    papers.retain(|paper| Path::new(&paper.path).is_file());
+    index_papers(db, &papers);
+    Ok(())
+}
+
+/// Replaces the stored passages of a paper and their `search_index` entries.
+pub fn store_passages(
+    conn: &rusqlite::Connection,
+    paper_id: &str,
+    file_hash: &str,
+    passages: &[Passage],
+) -> rusqlite::Result<()> {
+    remove_passages(conn, paper_id)?;
+
+    let now = now_iso();
+    let mut insert = conn.prepare(
+        "INSERT INTO paper_passage (id, paperId, page, paragraph, content, createdAt) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for passage in passages {
+        let passage_id = Uuid::new_v4().to_string();
+        insert.execute(params![
+            &passage_id,
+            paper_id,
+            passage.page,
+            passage.paragraph,
+            &passage.content,
+            &now
+        ])?;
+        search::insert_entry(conn, search::PDF_REF_TYPE, &passage_id, &passage.content)?;
+    }
+
+    conn.execute(
+        "UPDATE paper SET textHash = ?1 WHERE id = ?2",
+        params![file_hash, paper_id],
+    )?;
+    Ok(())
+}
+
+/// Stored passages of a paper in reading order.
+pub fn stored_passages(
+    conn: &rusqlite::Connection,
+    paper_id: &str,
+) -> rusqlite::Result<Vec<Passage>> {
+    conn.prepare(
+        "SELECT page, paragraph, content FROM paper_passage \
+         WHERE paperId = ?1 ORDER BY page ASC, paragraph ASC",
+    )?
+    .query_map(params![paper_id], |row| {
+        Ok(Passage {
+            page: row.get(0)?,
+            paragraph: row.get(1)?,
+            content: row.get(2)?,
+        })
+    })?
+    .collect()
+}
+
+pub fn remove_passages(conn: &rusqlite::Connection, paper_id: &str) -> rusqlite::Result<()> {
+    search::remove_passage_entries(conn, paper_id)?;
+    conn.execute(
+        "DELETE FROM paper_passage WHERE paperId = ?1",
+        params![paper_id],
+    )?;
+    Ok(())
+}
+
+/// Splits the text of one page into paragraphs: blank lines separate
+/// paragraphs, hyphenated line breaks are rejoined, short fragments are merged
+/// and oversized paragraphs are cut on sentence boundaries.
+fn split_paragraphs(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() {
+            if !current.is_empty() {
+                blocks.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        if current.ends_with('-') && !current.ends_with(" -") {
+            current.pop();
+        } else if let (Some(last), Some(first)) = (current.chars().last(), line.chars().next()) {
+            if last.is_ascii() || first.is_ascii() {
+                current.push(' ');
+            }
+        }
+        current.push_str(&line);
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    let mut paragraphs: Vec<String> = Vec::new();
+    let mut pending = String::new();
+    for block in blocks {
+        if !pending.is_empty() {
+            pending.push(' ');
+        }
+        pending.push_str(&block);
+        if pending.chars().count() < MIN_PASSAGE_CHARS {
+            continue;
+        }
+        paragraphs.extend(split_long(&std::mem::take(&mut pending)));
+    }
+    if !pending.is_empty() {
+        match paragraphs.last_mut() {
+            Some(last) if last.chars().count() + pending.chars().count() <= MAX_PASSAGE_CHARS => {
+                last.push(' ');
+                last.push_str(&pending);
+            }
+            _ => paragraphs.push(pending),
+        }
+    }
+
+    paragraphs
+}
+
+fn split_long(paragraph: &str) -> Vec<String> {
+    if paragraph.chars().count() <= MAX_PASSAGE_CHARS {
+        return vec![paragraph.to_string()];
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    let mut sentence = String::new();
+
+    for ch in paragraph.chars() {
+        sentence.push(ch);
+        let at_boundary = matches!(ch, '.' | '!' | '?' | '。' | '！' | '？');
+        if !at_boundary && sentence.chars().count() < MAX_PASSAGE_CHARS {
+            continue;
+        }
+
+        let sentence_len = sentence.chars().count();
+        if current_len + sentence_len > MAX_PASSAGE_CHARS && !current.is_empty() {
+            chunks.push(current.trim().to_string());
+            current.clear();
+            current_len = 0;
+        }
+        current.push_str(&sentence);
+        current_len += sentence_len;
+        sentence.clear();
+    }
+
+    current.push_str(&sentence);
+    if !current.trim().is_empty() {
+        chunks.push(current.trim().to_string());
+    }
+    chunks
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONG: &str = "Sequence transduction models are based on recurrent networks.";
+
+    #[test]
+    fn blank_lines_separate_paragraphs_and_line_breaks_join() {
+        let text = format!("{LONG}\nThey   include an encoder.\n\n\n{LONG}\n");
+
+        assert_eq!(
+            split_paragraphs(&text),
+            [format!("{LONG} They include an encoder."), LONG.to_string()]
+        );
+    }
+
+    #[test]
+    fn hyphenated_line_breaks_are_rejoined() {
+        let text = "The dominant sequence trans-\nduction models use attention -\nand recurrence.";
+
+        assert_eq!(
+            split_paragraphs(text),
+            ["The dominant sequence transduction models use attention - and recurrence."]
+        );
+    }
+
+    #[test]
+    fn cjk_lines_join_without_a_space() {
+        assert_eq!(
+            split_paragraphs("注意力机制\n是核心\nattention"),
+            ["注意力机制是核心 attention"]
+        );
+    }
+
+    #[test]
+    fn short_fragments_merge_into_their_neighbours() {
+        let text = format!("Abstract\n\n{LONG}\n\n{LONG}\n\n3");
+
+        assert_eq!(
+            split_paragraphs(&text),
+            [format!("Abstract {LONG}"), format!("{LONG} 3")]
+        );
+    }
+
+    #[test]
+    fn oversized_paragraphs_are_cut_on_sentence_boundaries() {
+        let text = [LONG; 30].join(" ");
+
+        let paragraphs = split_paragraphs(&text);
+
+        assert_eq!(paragraphs.len(), 2);
+        for paragraph in &paragraphs {
+            assert!(paragraph.chars().count() <= MAX_PASSAGE_CHARS);
+            assert!(paragraph.starts_with("Sequence") && paragraph.ends_with("networks."));
+        }
+        assert_eq!(paragraphs.join(" "), text);
+    }
+
+    #[test]
+    fn empty_text_has_no_paragraphs() {
+        assert!(split_paragraphs(" \n\n\t\n").is_empty());
+    }
+}
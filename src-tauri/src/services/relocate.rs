@@ -0,0 +1,401 @@
+//! Pointing papers at their file again after it was moved outside the watched
+//! folders. A file only counts as a paper's when its SHA-256 matches the
+//! paper's `fileHash`.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use rusqlite::params;
+
+use crate::{
+    domain::{LocateMissingReport, LocateMissingRequest, Paper},
+    telemetry::{IpcError, IpcResult, IpcStatus},
+    utils::time::now_iso,
+};
+
+use super::{repo, Db};
+
+/// Sets the `path` of a paper to `path` after checking the file there is the
+/// paper's.
+pub fn relocate(db: &Db, paper_id: &str, path: &str) -> IpcResult<Paper> {
+    if paper_id.trim().is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "paperId is required"));
+    }
+    if path.trim().is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "path is required"));
+    }
+
+    let paper = repo::get_paper(db, paper_id)?;
+    let resolved = resolve_path(path);
+    if !resolved.is_file() {
+        return Err(IpcError::new(
+            IpcStatus::NotFound,
+            format!("No file at {}", resolved.display()),
+        ));
+    }
+    let file_hash = repo::compute_file_hash(&resolved)?;
+    if file_hash != paper.file_hash {
+        return Err(IpcError::new(
+            IpcStatus::Conflict,
+            format!(
+                "{} is not the file of paper {paper_id}; its content differs",
+                resolved.display()
+            ),
+        ));
+    }
+
+    {
+        let conn = db.connection();
+        set_path(&conn, paper_id, &resolved.to_string_lossy(), &now_iso())?;
+    }
+    repo::get_paper(db, paper_id)
+}
+
+/// Searches `roots` for the files of papers whose `path` no longer exists.
+/// Only files with the size of a missing paper are hashed, plus every PDF while
+/// a missing paper has no recorded size; every paper found is updated in a
+/// single transaction.
+pub fn locate_missing(db: &Db, request: &LocateMissingRequest) -> IpcResult<LocateMissingReport> {
+    let roots = request
+        .roots
+        .iter()
+        .map(|root| root.trim())
+        .filter(|root| !root.is_empty())
+        .map(resolve_path)
+        .collect::<Vec<_>>();
+    if roots.is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "At least one folder to search is required",
+        ));
+    }
+    let workspace_id = request
+        .workspace_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|id| !id.is_empty());
+
+    let mut report = LocateMissingReport::default();
+    let missing = missing_papers(db, workspace_id)?;
+    // Papers by file size, dropped from the map once found. Papers imported
+    // without a size can only be recognized by hashing.
+    let mut by_size: HashMap<i64, Vec<usize>> = HashMap::new();
+    let mut without_size = Vec::new();
+    for (index, paper) in missing.iter().enumerate() {
+        match paper.filesize {
+            Some(filesize) => by_size.entry(filesize).or_default().push(index),
+            None => without_size.push(index),
+        }
+    }
+    let mut found: Vec<Option<(String, i64)>> = vec![None; missing.len()];
+
+    let mut pending = roots;
+    while let Some(dir) = pending.pop() {
+        if by_size.is_empty() && without_size.is_empty() {
+            break;
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) => {
+                report.warnings.push(io_warning(&dir, &err));
+                continue;
+            }
+        };
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    report.warnings.push(io_warning(&dir, &err));
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(err) => {
+                    report.warnings.push(io_warning(&path, &err));
+                    continue;
+                }
+            };
+            if file_type.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+
+            report.files_scanned += 1;
+            let filesize = match entry.metadata() {
+                Ok(metadata) => i64::try_from(metadata.len()).unwrap_or(i64::MAX),
+                Err(err) => {
+                    report.warnings.push(io_warning(&path, &err));
+                    continue;
+                }
+            };
+            if !by_size.contains_key(&filesize) && (without_size.is_empty() || !is_pdf(&path)) {
+                continue;
+            }
+
+            report.files_hashed += 1;
+            let file_hash = match repo::compute_file_hash(&path) {
+                Ok(file_hash) => file_hash,
+                Err(err) => {
+                    report.warnings.push(err.message);
+                    continue;
+                }
+            };
+            // Papers sharing a hash are duplicates of one file; all of them
+            // move to it.
+            let path_str = path.to_string_lossy().into_owned();
+            let mut unclaimed = |index: &usize| {
+                if missing[*index].file_hash == file_hash {
+                    found[*index] = Some((path_str.clone(), filesize));
+                    false
+                } else {
+                    true
+                }
+            };
+            if let Some(candidates) = by_size.get_mut(&filesize) {
+                candidates.retain(&mut unclaimed);
+                if candidates.is_empty() {
+                    by_size.remove(&filesize);
+                }
+            }
+            without_size.retain(unclaimed);
+        }
+    }
+
+    if found.iter().any(Option::is_some) {
+        let now = now_iso();
+        let mut conn = db.connection();
+        let tx = conn.transaction().map_err(db_error)?;
+        for (paper, found) in missing.iter().zip(&found) {
+            let Some((path, filesize)) = found else {
+                continue;
+            };
+            set_path(&tx, &paper.id, path, &now)?;
+            if paper.filesize.is_none() {
+                tx.execute(
+                    "UPDATE paper SET filesize = ?1 WHERE id = ?2",
+                    params![filesize, &paper.id],
+                )
+                .map_err(db_error)?;
+            }
+        }
+        tx.commit().map_err(db_error)?;
+    }
+
+    for (paper, path) in missing.into_iter().zip(found) {
+        if path.is_some() {
+            report.relocated.push(repo::get_paper(db, &paper.id)?);
+        } else {
+            report.still_missing.push(paper.id);
+        }
+    }
+    Ok(report)
+}
+
+struct MissingPaper {
+    id: String,
+    file_hash: String,
+    /// `None` for papers imported without a recorded size.
+    filesize: Option<i64>,
+}
+
+fn missing_papers(db: &Db, workspace_id: Option<&str>) -> IpcResult<Vec<MissingPaper>> {
+    let conn = db.connection();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, path, fileHash, filesize FROM paper \
+             WHERE ?1 IS NULL OR workspaceId = ?1",
+        )
+        .map_err(db_error)?;
+    let papers = stmt
+        .query_map(params![workspace_id], |row| {
+            Ok((
+                row.get::<_, String>(1)?,
+                MissingPaper {
+                    id: row.get(0)?,
+                    file_hash: row.get(2)?,
+                    filesize: row.get(3)?,
+                },
+            ))
+        })
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+
+    Ok(papers
+        .into_iter()
+        .filter(|(path, _)| !Path::new(path).is_file())
+        .map(|(_, paper)| paper)
+        .collect())
+}
+
+fn set_path(conn: &rusqlite::Connection, paper_id: &str, path: &str, now: &str) -> IpcResult<()> {
+    conn.execute(
+        "UPDATE paper SET path = ?1, lastSeenPath = ?1, missingSince = NULL, updatedAt = ?2 \
+         WHERE id = ?3",
+        params![path, now, paper_id],
+    )
+    .map_err(db_error)?;
+    Ok(())
+}
+
+fn resolve_path(path: &str) -> PathBuf {
+    let candidate = Path::new(path);
+    fs::canonicalize(candidate).unwrap_or_else(|_| candidate.to_path_buf())
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+fn io_warning(path: &Path, err: &io::Error) -> String {
+    format!("Failed to read {} ({err})", path.display())
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        domain::PaperImportRequest,
+        services::test_support::{migrated_db, TempDir},
+    };
+
+    fn import(db: &Db, paths: &[PathBuf]) -> Vec<Paper> {
+        let workspace = repo::create_workspace(db, "Relocate").unwrap();
+        repo::import_papers(
+            db,
+            &PaperImportRequest {
+                paths: paths
+                    .iter()
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .collect(),
+                workspace_id: workspace.id,
+            },
+        )
+        .unwrap()
+    }
+
+    fn locate(db: &Db, root: &Path) -> LocateMissingReport {
+        locate_missing(
+            db,
+            &LocateMissingRequest {
+                roots: vec![root.to_string_lossy().into_owned()],
+                workspace_id: None,
+            },
+        )
+        .unwrap()
+    }
+
+    /// Moves the file of `paper` to `to`, creating its folders.
+    fn move_file(paper: &Paper, to: &Path) -> PathBuf {
+        fs::create_dir_all(to.parent().unwrap()).unwrap();
+        fs::rename(&paper.path, to).unwrap();
+        fs::canonicalize(to).unwrap()
+    }
+
+    fn relocated<'a>(report: &'a LocateMissingReport, paper: &Paper) -> &'a Paper {
+        report
+            .relocated
+            .iter()
+            .find(|relocated| relocated.id == paper.id)
+            .unwrap()
+    }
+
+    #[test]
+    fn only_files_of_a_missing_size_are_hashed_and_the_hash_decides() {
+        let db = migrated_db();
+        let dir = TempDir::new();
+        let papers = import(
+            &db,
+            &[
+                dir.write_pdf("old/a.pdf", "1"),
+                dir.write_pdf("old/b.pdf", "2"),
+                dir.write_pdf("old/c.pdf", "3"),
+            ],
+        );
+        let (a, b, c) = (&papers[0], &papers[1], &papers[2]);
+        assert_eq!(a.filesize, b.filesize);
+
+        let new_a = move_file(a, &dir.path().join("new/nested/a.pdf"));
+        let new_b = move_file(b, &dir.path().join("new/b.pdf"));
+        fs::remove_file(&c.path).unwrap();
+        // Same size as the papers but different content.
+        let mut decoy = fs::read(&new_a).unwrap();
+        *decoy.last_mut().unwrap() ^= 1;
+        fs::write(dir.path().join("new/decoy.pdf"), decoy).unwrap();
+        fs::write(dir.path().join("new/notes.txt"), "unrelated").unwrap();
+
+        let report = locate(&db, &dir.path().join("new"));
+
+        assert_eq!(report.files_scanned, 4);
+        // a, b and the decoy; c keeps the size wanted until the scan ends.
+        assert_eq!(report.files_hashed, 3);
+        assert_eq!(report.still_missing, vec![c.id.clone()]);
+        assert!(report.warnings.is_empty());
+        assert_eq!(report.relocated.len(), 2);
+        let (moved_a, moved_b) = (relocated(&report, a), relocated(&report, b));
+        assert_eq!(moved_a.path, new_a.to_string_lossy());
+        assert_eq!(moved_b.path, new_b.to_string_lossy());
+        assert_eq!(
+            moved_a.last_seen_path.as_deref(),
+            Some(moved_a.path.as_str())
+        );
+        assert_eq!(moved_a.missing_since, None);
+        // Written in the same transaction.
+        assert_eq!(moved_a.updated_at, moved_b.updated_at);
+        assert_eq!(repo::get_paper(&db, &c.id).unwrap().path, c.path);
+    }
+
+    #[test]
+    fn papers_without_a_size_are_found_by_hashing_pdfs() {
+        let db = migrated_db();
+        let dir = TempDir::new();
+        let paper = import(&db, &[dir.write_pdf("old/a.pdf", "1")]).remove(0);
+        db.connection()
+            .execute("UPDATE paper SET filesize = NULL", [])
+            .unwrap();
+        let new_path = move_file(&paper, &dir.path().join("new/a.pdf"));
+        fs::write(dir.path().join("new/notes.txt"), "unrelated").unwrap();
+
+        let report = locate(&db, &dir.path().join("new"));
+
+        assert_eq!(report.files_scanned, 2);
+        assert_eq!(report.files_hashed, 1);
+        assert!(report.still_missing.is_empty());
+        let moved = relocated(&report, &paper);
+        assert_eq!(moved.path, new_path.to_string_lossy());
+        assert_eq!(moved.filesize, paper.filesize);
+    }
+
+    #[test]
+    fn papers_without_a_size_that_are_not_found_stay_missing() {
+        let db = migrated_db();
+        let dir = TempDir::new();
+        let paper = import(&db, &[dir.write_pdf("old/a.pdf", "1")]).remove(0);
+        db.connection()
+            .execute("UPDATE paper SET filesize = NULL", [])
+            .unwrap();
+        fs::remove_file(&paper.path).unwrap();
+        dir.write_pdf("new/other.pdf", "2");
+
+        let report = locate(&db, &dir.path().join("new"));
+
+        assert_eq!(report.files_hashed, 1);
+        assert!(report.relocated.is_empty());
+        assert_eq!(report.still_missing, vec![paper.id.clone()]);
+        assert_eq!(repo::get_paper(&db, &paper.id).unwrap().filesize, None);
+    }
+}
@@ -6,35 +6,93 @@ use std::{
 };
 
 use crate::{
-    domain::{NewNote, Note, Paper, PaperImportRequest, UpdateNote, Workspace},
+    domain::{
+        NewNote, Note, NoteAnchor, Paper, PaperImportRequest, PaperListQuery, PaperSort,
+        UpdateNote, UpdatePaper, Workspace,
+    },
     telemetry::{IpcError, IpcResult, IpcStatus},
     utils::time::now_iso,
 };
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, params_from_iter, types::Value, OptionalExtension};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
-use super::{search, Db};
+use super::{authors, pdf, search, tags, Db};
 
 const DEFAULT_WORKSPACE_ID: &str = "default_workspace";
+/// Values accepted for `paper.readStatus`.
+pub const READ_STATUSES: &[&str] = &["unread", "reading", "read"];
+const NOTE_COLUMNS: &str = "id, paperId, page, x, y, content, color, createdAt, updatedAt, \
+     pageGeometry, anchorQuote, anchorPrefixHash, anchorSuffixHash, anchorParagraph, anchorStatus, anchorFileHash";
+const PAPER_COLUMNS: &str = "paper.id, paper.workspaceId, paper.title, paper.doi, paper.path, \
+     paper.lastSeenPath, paper.fileHash, paper.filesize, paper.createdAt, paper.updatedAt, \
+     paper.readStatus, paper.missingSince, paper.subject, paper.keywords, paper.pdfCreatedAt, \
+     paper.pageCount, paper.arxivId, paper.venue, paper.year, paper.abstract, paper.url, \
+     paper.editedAt";
 
 pub fn list_papers(db: &Db, workspace_id: &str) -> IpcResult<Vec<Paper>> {
-    let mut conn = db.connection();
+    query_papers(db, workspace_id, &PaperListQuery::default())
+}
+
+/// Papers of a workspace narrowed by tags, read status and DOI presence, in
+/// the order requested by `query`.
+pub fn query_papers(db: &Db, workspace_id: &str, query: &PaperListQuery) -> IpcResult<Vec<Paper>> {
+    let mut conditions = vec!["paper.workspaceId = ?1".to_string()];
+    let mut values = vec![Value::Text(workspace_id.to_string())];
+
+    for tag_id in &query.tag_ids {
+        values.push(Value::Text(tag_id.clone()));
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM paper_tag \
+             JOIN tag ON tag.id = paper_tag.tagId \
+             JOIN tag selected ON selected.id = ?{} \
+             WHERE paper_tag.paperId = paper.id \
+             AND (tag.id = selected.id \
+               OR lower(substr(tag.name, 1, length(selected.name) + 1)) = lower(selected.name) || '/'))",
+            values.len()
+        ));
+    }
+    if let Some(status) = query.read_status.as_deref() {
+        values.push(Value::Text(validate_read_status(status)?.to_string()));
+        conditions.push(format!("paper.readStatus = ?{}", values.len()));
+    }
+    match query.has_doi {
+        Some(true) => conditions.push("COALESCE(trim(paper.doi), '') <> ''".into()),
+        Some(false) => conditions.push("COALESCE(trim(paper.doi), '') = ''".into()),
+        None => {}
+    }
+
+    let descending = query.descending.unwrap_or(query.sort != PaperSort::Title);
+    let direction = if descending { "DESC" } else { "ASC" };
+    let order = match query.sort {
+        PaperSort::UpdatedAt => format!("datetime(paper.updatedAt) {direction}"),
+        PaperSort::Title => format!("paper.title COLLATE NOCASE {direction}"),
+        PaperSort::CreatedAt => format!("datetime(paper.createdAt) {direction}"),
+        PaperSort::LastOpened => {
+            format!("datetime(paper_stats.lastOpenedAt) {direction} NULLS LAST")
+        }
+        PaperSort::ReadTime => format!("COALESCE(paper_stats.totalReadTime, 0) {direction}"),
+    };
+
+    let conn = db.connection();
     let mut stmt = conn
-        .prepare(
-            "SELECT id, workspaceId, title, doi, path, lastSeenPath, fileHash, filesize, \
-             createdAt, updatedAt \
+        .prepare(&format!(
+            "SELECT {PAPER_COLUMNS} \
              FROM paper \
-             WHERE workspaceId = ?1 \
-             ORDER BY datetime(updatedAt) DESC, title ASC",
-        )
+             LEFT JOIN paper_stats ON paper_stats.paperId = paper.id \
+             WHERE {} \
+             ORDER BY {order}, paper.title ASC, paper.id ASC",
+            conditions.join(" AND ")
+        ))
         .map_err(db_error)?;
 
-    let papers = stmt
-        .query_map(params![workspace_id], map_paper)
+    let mut papers = stmt
+        .query_map(params_from_iter(values), map_paper)
         .map_err(db_error)?
         .collect::<Result<Vec<_>, _>>()
         .map_err(db_error)?;
+    tags::attach_paper_tags(&conn, &mut papers).map_err(db_error)?;
+    authors::attach_paper_authors(&conn, &mut papers).map_err(db_error)?;
 
     Ok(papers)
 }
@@ -44,22 +102,22 @@ pub fn list_notes(db: &Db, paper_id: &str) -> IpcResult<Vec<Note>> {
         return Err(IpcError::new(IpcStatus::BadRequest, "paperId is required"));
     }
 
-    let mut conn = db.connection();
+    let conn = db.connection();
     ensure_paper_exists(&conn, paper_id)?;
 
     let mut stmt = conn
-        .prepare(
-            "SELECT id, paperId, page, x, y, content, color, createdAt, updatedAt \
-             FROM note WHERE paperId = ?1 \
-             ORDER BY datetime(createdAt) ASC",
-        )
+        .prepare(&format!(
+            "SELECT {NOTE_COLUMNS} FROM note WHERE paperId = ?1 \
+             ORDER BY datetime(createdAt) ASC"
+        ))
         .map_err(db_error)?;
 
-    let notes = stmt
+    let mut notes = stmt
         .query_map(params![paper_id], map_note)
         .map_err(db_error)?
         .collect::<Result<Vec<_>, _>>()
         .map_err(db_error)?;
+    tags::attach_tags(&conn, &mut notes).map_err(db_error)?;
 
     Ok(notes)
 }
@@ -69,18 +127,18 @@ pub fn get_note(db: &Db, note_id: &str) -> IpcResult<Note> {
         return Err(IpcError::new(IpcStatus::BadRequest, "noteId is required"));
     }
 
-    let mut conn = db.connection();
+    let conn = db.connection();
     let note = conn
-        .prepare(
-            "SELECT id, paperId, page, x, y, content, color, createdAt, updatedAt \
-             FROM note WHERE id = ?1 LIMIT 1",
-        )
+        .prepare(&format!(
+            "SELECT {NOTE_COLUMNS} FROM note WHERE id = ?1 LIMIT 1"
+        ))
         .map_err(db_error)?
         .query_row(params![note_id], map_note)
         .optional()
         .map_err(db_error)?;
 
-    if let Some(note) = note {
+    if let Some(mut note) = note {
+        tags::attach_tags(&conn, std::slice::from_mut(&mut note)).map_err(db_error)?;
         Ok(note)
     } else {
         Err(IpcError::new(
@@ -91,7 +149,7 @@ pub fn get_note(db: &Db, note_id: &str) -> IpcResult<Note> {
 }
 
 pub fn list_workspaces(db: &Db) -> IpcResult<Vec<Workspace>> {
-    let mut conn = db.connection();
+    let conn = db.connection();
     let mut stmt = conn
         .prepare(
             "SELECT id, name, createdAt, updatedAt \
@@ -109,6 +167,31 @@ pub fn list_workspaces(db: &Db) -> IpcResult<Vec<Workspace>> {
     Ok(workspaces)
 }
 
+pub fn get_workspace(db: &Db, workspace_id: &str) -> IpcResult<Workspace> {
+    let trimmed_id = workspace_id.trim();
+    if trimmed_id.is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "Workspace id is required",
+        ));
+    }
+
+    let conn = db.connection();
+    let workspace = conn
+        .prepare("SELECT id, name, createdAt, updatedAt FROM workspace WHERE id = ?1")
+        .map_err(db_error)?
+        .query_row(params![trimmed_id], map_workspace)
+        .optional()
+        .map_err(db_error)?;
+
+    workspace.ok_or_else(|| {
+        IpcError::new(
+            IpcStatus::NotFound,
+            format!("Workspace {trimmed_id} not found"),
+        )
+    })
+}
+
 pub fn create_workspace(db: &Db, name: &str) -> IpcResult<Workspace> {
     let trimmed = name.trim();
     if trimmed.is_empty() {
@@ -157,7 +240,7 @@ pub fn rename_workspace(db: &Db, workspace_id: &str, new_name: &str) -> IpcResul
         ));
     }
 
-    let mut conn = db.connection();
+    let conn = db.connection();
     let now = now_iso();
 
     let updated = conn
@@ -174,10 +257,13 @@ pub fn rename_workspace(db: &Db, workspace_id: &str, new_name: &str) -> IpcResul
         ));
     }
 
-    conn.prepare("SELECT id, name, createdAt, updatedAt FROM workspace WHERE id = ?1")
+    let workspace = conn
+        .prepare("SELECT id, name, createdAt, updatedAt FROM workspace WHERE id = ?1")
         .map_err(db_error)?
         .query_row(params![trimmed_id], map_workspace)
-        .map_err(db_error)
+        .map_err(db_error)?;
+
+    Ok(workspace)
 }
 
 pub fn delete_workspace(db: &Db, workspace_id: &str) -> IpcResult<()> {
@@ -196,7 +282,7 @@ pub fn delete_workspace(db: &Db, workspace_id: &str) -> IpcResult<()> {
         ));
     }
 
-    let mut conn = db.connection();
+    let conn = db.connection();
     let deleted = conn
         .execute("DELETE FROM workspace WHERE id = ?1", params![trimmed_id])
         .map_err(db_error)?;
@@ -210,6 +296,9 @@ pub fn delete_workspace(db: &Db, workspace_id: &str) -> IpcResult<()> {
 
     Ok(())
 }
+/// Adds the files at the requested paths as papers, or moves the paper that
+/// already has a file's content. Returns once the rows are committed; the
+/// metadata and text are read by [`index_imported`].
 pub fn import_papers(db: &Db, request: &PaperImportRequest) -> IpcResult<Vec<Paper>> {
     if request.paths.is_empty() {
         return Err(IpcError::new(IpcStatus::BadRequest, "No paths provided"));
@@ -245,13 +334,11 @@ pub fn import_papers(db: &Db, request: &PaperImportRequest) -> IpcResult<Vec<Pap
         let now = now_iso();
 
         let existing = tx
-            .prepare(
-                "SELECT id, workspaceId, title, doi, path, lastSeenPath, fileHash, filesize, \
-                 createdAt, updatedAt \
-                 FROM paper \
+            .prepare(&format!(
+                "SELECT {PAPER_COLUMNS} FROM paper \
                  WHERE fileHash = ?1 OR path = ?2 \
-                 LIMIT 1",
-            )
+                 LIMIT 1"
+            ))
             .map_err(db_error)?
             .query_row(params![&file_hash, &path_str], map_paper)
             .optional()
@@ -260,7 +347,8 @@ pub fn import_papers(db: &Db, request: &PaperImportRequest) -> IpcResult<Vec<Pap
         let paper_id = if let Some(existing) = existing {
             tx.execute(
                 "UPDATE paper \
-                 SET path = ?2, lastSeenPath = ?3, fileHash = ?4, filesize = ?5, updatedAt = ?6 \
+                 SET path = ?2, lastSeenPath = ?3, fileHash = ?4, filesize = ?5, updatedAt = ?6, \
+                     missingSince = NULL \
                  WHERE id = ?1",
                 params![
                     &existing.id,
@@ -300,37 +388,166 @@ pub fn import_papers(db: &Db, request: &PaperImportRequest) -> IpcResult<Vec<Pap
         };
 
         let paper = tx
-            .prepare(
-                "SELECT id, workspaceId, title, doi, path, lastSeenPath, fileHash, filesize, \
-                 createdAt, updatedAt \
-                 FROM paper WHERE id = ?1",
-            )
+            .prepare(&format!("SELECT {PAPER_COLUMNS} FROM paper WHERE id = ?1"))
             .map_err(db_error)?
             .query_row(params![&paper_id], map_paper)
             .map_err(db_error)?;
+        search::upsert_paper_entry(&tx, &paper_id).map_err(db_error)?;
 
         imported.push(paper);
     }
 
+    tags::attach_paper_tags(&tx, &mut imported).map_err(db_error)?;
+    authors::attach_paper_authors(&tx, &mut imported).map_err(db_error)?;
     tx.commit().map_err(db_error)?;
     Ok(imported)
 }
 
+/// Reads the metadata and indexes the text of papers returned by
+/// [`import_papers`], updating them in place. Parsing large PDFs is slow, so
+/// commands run this on a blocking thread. Best effort: failures are logged.
+pub fn index_imported(db: &Db, papers: &mut [Paper]) {
+    pdf::metadata::import_metadata(db, papers);
+    pdf::text::index_papers(db, papers);
+}
+
 pub fn get_paper(db: &Db, paper_id: &str) -> IpcResult<Paper> {
-    let mut conn = db.connection();
-    conn.prepare(
-        "SELECT id, workspaceId, title, doi, path, lastSeenPath, fileHash, filesize, \
-         createdAt, updatedAt \
-         FROM paper WHERE id = ?1",
-    )
-    .map_err(db_error)?
-    .query_row(params![paper_id], map_paper)
-    .map_err(|err| match err {
-        rusqlite::Error::QueryReturnedNoRows => {
-            IpcError::new(IpcStatus::NotFound, format!("Paper {paper_id} not found"))
+    let conn = db.connection();
+    let mut paper = conn
+        .prepare(&format!("SELECT {PAPER_COLUMNS} FROM paper WHERE id = ?1"))
+        .map_err(db_error)?
+        .query_row(params![paper_id], map_paper)
+        .map_err(|err| match err {
+            rusqlite::Error::QueryReturnedNoRows => {
+                IpcError::new(IpcStatus::NotFound, format!("Paper {paper_id} not found"))
+            }
+            other => db_error(other),
+        })?;
+    tags::attach_paper_tags(&conn, std::slice::from_mut(&mut paper)).map_err(db_error)?;
+    authors::attach_paper_authors(&conn, std::slice::from_mut(&mut paper)).map_err(db_error)?;
+
+    Ok(paper)
+}
+
+/// Records that a paper was opened and returns it.
+pub fn open_paper(db: &Db, paper_id: &str) -> IpcResult<Paper> {
+    {
+        let conn = db.connection();
+        ensure_paper_exists(&conn, paper_id)?;
+        conn.execute(
+            "INSERT INTO paper_stats (paperId, lastOpenedAt) VALUES (?1, ?2) \
+             ON CONFLICT(paperId) DO UPDATE SET lastOpenedAt = excluded.lastOpenedAt",
+            params![paper_id, now_iso()],
+        )
+        .map_err(db_error)?;
+    }
+    get_paper(db, paper_id)
+}
+
+pub fn set_read_status(db: &Db, paper_id: &str, status: &str) -> IpcResult<Paper> {
+    let status = validate_read_status(status)?;
+    {
+        let conn = db.connection();
+        let updated = conn
+            .execute(
+                "UPDATE paper SET readStatus = ?1, updatedAt = ?2 WHERE id = ?3",
+                params![status, now_iso(), paper_id],
+            )
+            .map_err(db_error)?;
+        if updated == 0 {
+            return Err(IpcError::new(
+                IpcStatus::NotFound,
+                format!("Paper {paper_id} not found"),
+            ));
         }
-        other => db_error(other),
-    })
+    }
+    get_paper(db, paper_id)
+}
+
+/// Applies the bibliographic fields set in `update`, marks the paper as edited
+/// so metadata read from its file no longer replaces the title, and reindexes
+/// it.
+pub fn update_paper(db: &Db, update: &UpdatePaper) -> IpcResult<Paper> {
+    if update.id.trim().is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "id is required"));
+    }
+    if update
+        .title
+        .as_deref()
+        .is_some_and(|title| title.trim().is_empty())
+    {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "Paper title cannot be empty",
+        ));
+    }
+    if let Some(year) = update.year.filter(|year| !(0..=9999).contains(year)) {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            format!("Invalid year {year}"),
+        ));
+    }
+
+    let text_fields = [
+        ("title", &update.title),
+        ("doi", &update.doi),
+        ("venue", &update.venue),
+        ("abstract", &update.r#abstract),
+        ("arxivId", &update.arxiv_id),
+        ("url", &update.url),
+        ("subject", &update.subject),
+        ("keywords", &update.keywords),
+    ];
+    let mut assignments = Vec::new();
+    let mut values = Vec::new();
+    for (column, value) in text_fields {
+        if let Some(value) = value {
+            let value = value.trim();
+            values.push(if value.is_empty() {
+                Value::Null
+            } else {
+                Value::Text(value.to_string())
+            });
+            assignments.push(format!("{column} = ?{}", values.len()));
+        }
+    }
+    if let Some(year) = update.year {
+        values.push(if year == 0 {
+            Value::Null
+        } else {
+            Value::Integer(i64::from(year))
+        });
+        assignments.push(format!("year = ?{}", values.len()));
+    }
+    if assignments.is_empty() && update.authors.is_none() {
+        return get_paper(db, &update.id);
+    }
+
+    {
+        let mut conn = db.connection();
+        let tx = conn.transaction().map_err(db_error)?;
+        ensure_paper_exists(&tx, &update.id)?;
+
+        values.push(Value::Text(now_iso()));
+        assignments.push(format!("editedAt = ?{0}, updatedAt = ?{0}", values.len()));
+        values.push(Value::Text(update.id.clone()));
+        tx.execute(
+            &format!(
+                "UPDATE paper SET {} WHERE id = ?{}",
+                assignments.join(", "),
+                values.len()
+            ),
+            params_from_iter(values),
+        )
+        .map_err(db_error)?;
+        if let Some(names) = &update.authors {
+            authors::set_paper_authors(&tx, &update.id, names).map_err(db_error)?;
+        }
+        search::upsert_paper_entry(&tx, &update.id).map_err(db_error)?;
+
+        tx.commit().map_err(db_error)?;
+    }
+    get_paper(db, &update.id)
 }
 
 pub fn create_note(db: &Db, note: &NewNote) -> IpcResult<Note> {
@@ -343,19 +560,61 @@ pub fn create_note(db: &Db, note: &NewNote) -> IpcResult<Note> {
             "Note content cannot be empty",
         ));
     }
+    match (note.x, note.y) {
+        (Some(x), Some(y)) => pdf::geometry::validate_position(x, y)?,
+        (None, None) => {}
+        _ => {
+            return Err(IpcError::new(
+                IpcStatus::BadRequest,
+                "x and y must be given together",
+            ))
+        }
+    }
+
+    // Read the page geometry before taking the lock for the insert.
+    let paper = get_paper(db, &note.paper_id)?;
+    let page_geometry = [Some(paper.path.as_str()), paper.last_seen_path.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(Path::new)
+        .find(|candidate| candidate.is_file())
+        .and_then(|path| match pdf::geometry::page_geometry(path, note.page) {
+            Ok(geometry) => Some(geometry),
+            Err(err) => {
+                tracing::warn!(
+                    target = "pdf",
+                    paper_id = %paper.id,
+                    error = %format!("{err:#}"),
+                    "failed to read page geometry"
+                );
+                None
+            }
+        });
+    let encoded_geometry = page_geometry
+        .as_ref()
+        .map(pdf::geometry::encode_geometry)
+        .transpose()?;
 
     let mut conn = db.connection();
     let tx = conn.transaction().map_err(db_error)?;
 
     ensure_paper_exists(&tx, &note.paper_id)?;
 
+    let anchor = match note.quote.as_deref() {
+        Some(quote) => pdf::anchor::derive_stored_anchor(&tx, &note.paper_id, note.page, quote)
+            .map_err(db_error)?,
+        None => None,
+    };
+
     let note_id = Uuid::new_v4().to_string();
     let now = now_iso();
 
     tx.execute(
         "INSERT INTO note \
-         (id, paperId, page, x, y, content, color, createdAt, updatedAt) \
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+         (id, paperId, page, x, y, content, color, createdAt, updatedAt, pageGeometry, \
+          anchorQuote, anchorPrefixHash, anchorSuffixHash, anchorParagraph, anchorStatus, \
+          anchorFileHash) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
         params![
             &note_id,
             &note.paper_id,
@@ -364,7 +623,20 @@ pub fn create_note(db: &Db, note: &NewNote) -> IpcResult<Note> {
             note.y,
             &note.content,
             note.color.as_deref(),
-            &now
+            &now,
+            encoded_geometry,
+            anchor.as_ref().map(|anchor| anchor.quote.as_str()),
+            anchor
+                .as_ref()
+                .and_then(|anchor| anchor.prefix_hash.as_deref()),
+            anchor
+                .as_ref()
+                .and_then(|anchor| anchor.suffix_hash.as_deref()),
+            anchor.as_ref().and_then(|anchor| anchor.paragraph),
+            anchor.as_ref().map(|anchor| anchor.status.as_str()),
+            anchor
+                .as_ref()
+                .and_then(|anchor| anchor.file_hash.as_deref())
         ],
     )
     .map_err(db_error)?;
@@ -378,10 +650,7 @@ pub fn create_note(db: &Db, note: &NewNote) -> IpcResult<Note> {
     search::upsert_entry(&tx, search::NOTE_REF_TYPE, &note_id, &note.content).map_err(db_error)?;
 
     let created = tx
-        .prepare(
-            "SELECT id, paperId, page, x, y, content, color, createdAt, updatedAt \
-             FROM note WHERE id = ?1",
-        )
+        .prepare(&format!("SELECT {NOTE_COLUMNS} FROM note WHERE id = ?1"))
         .map_err(db_error)?
         .query_row(params![&note_id], map_note)
         .map_err(db_error)?;
@@ -399,10 +668,7 @@ pub fn update_note(db: &Db, note: &UpdateNote) -> IpcResult<Note> {
     let tx = conn.transaction().map_err(db_error)?;
 
     let mut existing = tx
-        .prepare(
-            "SELECT id, paperId, page, x, y, content, color, createdAt, updatedAt \
-             FROM note WHERE id = ?1",
-        )
+        .prepare(&format!("SELECT {NOTE_COLUMNS} FROM note WHERE id = ?1"))
         .map_err(db_error)?
         .query_row(params![&note.id], map_note)
         .map_err(|err| match err {
@@ -441,6 +707,7 @@ pub fn update_note(db: &Db, note: &UpdateNote) -> IpcResult<Note> {
 
     search::upsert_entry(&tx, search::NOTE_REF_TYPE, &existing.id, &existing.content)
         .map_err(db_error)?;
+    tags::attach_tags(&tx, std::slice::from_mut(&mut existing)).map_err(db_error)?;
 
     tx.commit().map_err(db_error)?;
     Ok(existing)
@@ -483,6 +750,20 @@ fn map_paper(row: &rusqlite::Row<'_>) -> rusqlite::Result<Paper> {
         filesize: row.get("filesize")?,
         created_at: row.get("createdAt")?,
         updated_at: row.get("updatedAt")?,
+        read_status: row.get("readStatus")?,
+        missing_since: row.get("missingSince")?,
+        authors: Vec::new(),
+        subject: row.get("subject")?,
+        keywords: row.get("keywords")?,
+        pdf_created_at: row.get("pdfCreatedAt")?,
+        page_count: row.get("pageCount")?,
+        arxiv_id: row.get("arxivId")?,
+        venue: row.get("venue")?,
+        year: row.get("year")?,
+        r#abstract: row.get("abstract")?,
+        url: row.get("url")?,
+        edited_at: row.get("editedAt")?,
+        tags: Vec::new(),
     })
 }
 
@@ -497,9 +778,30 @@ fn map_note(row: &rusqlite::Row<'_>) -> rusqlite::Result<Note> {
         color: row.get("color")?,
         created_at: row.get("createdAt")?,
         updated_at: row.get("updatedAt")?,
+        page_geometry: row
+            .get::<_, Option<String>>("pageGeometry")?
+            .and_then(|geometry| serde_json::from_str(&geometry).ok()),
+        anchor: map_anchor(row)?,
+        tags: Vec::new(),
     })
 }
 
+fn map_anchor(row: &rusqlite::Row<'_>) -> rusqlite::Result<Option<NoteAnchor>> {
+    let Some(quote) = row.get::<_, Option<String>>("anchorQuote")? else {
+        return Ok(None);
+    };
+    Ok(Some(NoteAnchor {
+        quote,
+        prefix_hash: row.get("anchorPrefixHash")?,
+        suffix_hash: row.get("anchorSuffixHash")?,
+        paragraph: row.get("anchorParagraph")?,
+        status: row
+            .get::<_, Option<String>>("anchorStatus")?
+            .unwrap_or_else(|| pdf::anchor::STATUS_ANCHORED.to_string()),
+        file_hash: row.get("anchorFileHash")?,
+    }))
+}
+
 fn map_workspace(row: &rusqlite::Row<'_>) -> rusqlite::Result<Workspace> {
     Ok(Workspace {
         id: row.get("id")?,
@@ -516,7 +818,10 @@ fn workspace_exists(conn: &rusqlite::Connection, workspace_id: &str) -> rusqlite
         .map(|value| value.is_some())
 }
 
-fn generate_workspace_id(conn: &rusqlite::Connection, name: &str) -> rusqlite::Result<String> {
+pub(crate) fn generate_workspace_id(
+    conn: &rusqlite::Connection,
+    name: &str,
+) -> rusqlite::Result<String> {
     let base = slugify_workspace(name);
     if base.is_empty() {
         return Ok(Uuid::new_v4().to_string());
@@ -544,11 +849,12 @@ fn slugify_workspace(name: &str) -> String {
         if ch.is_ascii_alphanumeric() {
             slug.push(ch.to_ascii_lowercase());
             last_was_separator = false;
-        } else if ch.is_ascii_whitespace() || matches!(ch, '-' | '_' | '.') {
-            if !last_was_separator && !slug.is_empty() {
-                slug.push('-');
-                last_was_separator = true;
-            }
+        } else if (ch.is_ascii_whitespace() || matches!(ch, '-' | '_' | '.'))
+            && !last_was_separator
+            && !slug.is_empty()
+        {
+            slug.push('-');
+            last_was_separator = true;
         }
     }
 
@@ -558,7 +864,7 @@ fn slugify_workspace(name: &str) -> String {
     slug
 }
 
-fn compute_file_hash(path: &Path) -> Result<String, IpcError> {
+pub(crate) fn compute_file_hash(path: &Path) -> Result<String, IpcError> {
     let mut file =
         fs::File::open(path).map_err(|err| io_error(path, err, "open file for hashing"))?;
     let mut hasher = Sha256::new();
@@ -587,6 +893,23 @@ fn path_to_string(path: &Path) -> Result<String, IpcError> {
     Ok(path.to_string_lossy().into_owned())
 }
 
+pub(crate) fn validate_read_status(status: &str) -> IpcResult<&'static str> {
+    let status = status.trim();
+    READ_STATUSES
+        .iter()
+        .find(|candidate| candidate.eq_ignore_ascii_case(status))
+        .copied()
+        .ok_or_else(|| {
+            IpcError::new(
+                IpcStatus::BadRequest,
+                format!(
+                    "Unknown read status \"{status}\"; expected one of {}",
+                    READ_STATUSES.join(", ")
+                ),
+            )
+        })
+}
+
 fn db_error(err: rusqlite::Error) -> IpcError {
     IpcError::new(IpcStatus::DbError, err.to_string())
 }
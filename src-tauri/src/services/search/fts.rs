@@ -1,33 +1,166 @@
-/// Basic tokenizer that lowercases ASCII alphanumerics and splits on boundaries.
-/// For CJK ranges where whitespace is uncommon, each character becomes a token.
-pub fn tokenize(source: &str) -> Vec<String> {
-    let mut tokens = Vec::new();
-    let mut current = String::new();
-
-    for ch in source.chars() {
-        if ch.is_ascii_alphanumeric() {
-            current.push(ch.to_ascii_lowercase());
-            continue;
-        }
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+/// Length of the overlapping n-grams emitted for CJK runs. Only bigrams are
+/// supported and this is deliberately not a setting: the stored index is built
+/// with it, so a change must come with a bump of
+/// [`TOKENIZER_VERSION`](super::tokenizer::TOKENIZER_VERSION) to rebuild it.
+pub const CJK_NGRAM: usize = 2;
+
+/// A folded token and the byte range of the source text it was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A run of the source text as the index sees it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    /// Folded alphanumeric word in any non-CJK script.
+    Word(Token),
+    /// Overlapping n-grams of one run of CJK characters. Runs shorter than the
+    /// n-gram length yield the run itself as a single, shorter gram.
+    Cjk(Vec<Token>),
+}
+
+/// Tokenizer shared by indexing and querying. Text is folded with [`fold`],
+/// then split into alphanumeric words; runs of Han, Kana and Hangul, where
+/// whitespace is uncommon, are split into overlapping bigrams.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    segment(source, CJK_NGRAM)
+        .into_iter()
+        .flat_map(|segment| match segment {
+            Segment::Word(word) => vec![word],
+            Segment::Cjk(grams) => grams,
+        })
+        .collect()
+}
+
+/// Splits `source` into folded words and CJK runs, cutting each CJK run into
+/// overlapping n-grams of `ngram` characters. Folding is applied per character
+/// cluster so every token keeps the byte range it came from.
+pub fn segment(source: &str, ngram: usize) -> Vec<Segment> {
+    let ngram = ngram.max(1);
+    let mut segments = Vec::new();
+    let mut word: Option<Token> = None;
+    let mut run: Vec<(char, usize, usize)> = Vec::new();
+
+    for (start, cluster) in clusters(source) {
+        let end = start + cluster.len();
+        for ch in fold(cluster).chars() {
+            if is_cjk(ch) {
+                flush_word(&mut word, &mut segments);
+                run.push((ch, start, end));
+                continue;
+            }
 
-        if is_cjk(ch) {
-            if !current.is_empty() {
-                tokens.push(std::mem::take(&mut current));
+            flush_run(&mut run, ngram, &mut segments);
+            if ch.is_alphanumeric() || (is_combining_mark(ch) && word.is_some()) {
+                let token = word.get_or_insert_with(|| Token {
+                    text: String::new(),
+                    start,
+                    end,
+                });
+                token.text.push(ch);
+                token.end = end;
+            } else {
+                flush_word(&mut word, &mut segments);
             }
-            tokens.push(ch.to_string());
+        }
+    }
+
+    flush_word(&mut word, &mut segments);
+    flush_run(&mut run, ngram, &mut segments);
+    segments
+}
+
+/// Normalizes text so that visually equivalent spellings index and match the
+/// same way: NFKC (full-width and half-width forms, ligatures, compatibility
+/// characters), full lowercasing and removal of diacritics from Latin, Greek
+/// and Cyrillic letters. Marks in other scripts, such as kana voicing marks or
+/// Indic vowel signs, are significant and kept.
+pub fn fold(source: &str) -> String {
+    let mut folded = String::with_capacity(source.len());
+    for ch in source.nfkc().flat_map(char::to_lowercase) {
+        if !has_foldable_diacritics(ch) {
+            folded.push(ch);
             continue;
         }
+        for base in ch.nfd().filter(|c| !is_combining_mark(*c)) {
+            match base {
+                'ß' => folded.push_str("ss"),
+                'æ' => folded.push_str("ae"),
+                'œ' => folded.push_str("oe"),
+                'ø' => folded.push('o'),
+                'đ' | 'ð' => folded.push('d'),
+                'ł' => folded.push('l'),
+                'ı' => folded.push('i'),
+                'ς' => folded.push('σ'),
+                _ => folded.push(base),
+            }
+        }
+    }
+    folded
+}
 
-        if !current.is_empty() {
-            tokens.push(std::mem::take(&mut current));
+/// Splits `source` into a base character plus the marks that compose with it,
+/// so folding a cluster gives the same result as folding the whole text.
+fn clusters(source: &str) -> impl Iterator<Item = (usize, &str)> {
+    let mut chars = source.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, first) = chars.next()?;
+        let mut end = start + first.len_utf8();
+        while let Some(&(index, next)) = chars.peek() {
+            if !extends_cluster(next) {
+                break;
+            }
+            end = index + next.len_utf8();
+            chars.next();
         }
+        Some((start, &source[start..end]))
+    })
+}
+
+fn extends_cluster(ch: char) -> bool {
+    is_combining_mark(ch)
+        || matches!(
+            ch as u32,
+            0xFF9E..=0xFF9F // Halfwidth katakana voiced sound marks
+                | 0x1160..=0x11FF // Hangul medial vowels and final consonants
+        )
+}
+
+fn flush_word(word: &mut Option<Token>, segments: &mut Vec<Segment>) {
+    if let Some(token) = word.take() {
+        segments.push(Segment::Word(token));
     }
+}
 
-    if !current.is_empty() {
-        tokens.push(current);
+fn flush_run(run: &mut Vec<(char, usize, usize)>, ngram: usize, segments: &mut Vec<Segment>) {
+    if run.is_empty() {
+        return;
     }
 
-    tokens
+    let window = ngram.min(run.len());
+    let grams = run
+        .windows(window)
+        .map(|chars| Token {
+            text: chars.iter().map(|(ch, _, _)| ch).collect(),
+            start: chars[0].1,
+            end: chars[chars.len() - 1].2,
+        })
+        .collect();
+    segments.push(Segment::Cjk(grams));
+    run.clear();
+}
+
+fn has_foldable_diacritics(ch: char) -> bool {
+    matches!(
+        ch as u32,
+        0x00C0..=0x052F // Latin-1 Supplement through Cyrillic Supplement
+            | 0x1E00..=0x1FFF // Latin Extended Additional, Greek Extended
+    )
 }
 
 fn is_cjk(ch: char) -> bool {
@@ -41,5 +174,13 @@ fn is_cjk(ch: char) -> bool {
             | 0x2B820..=0x2CEAF // Extension E
             | 0xF900..=0xFAFF // CJK Compatibility Ideographs
             | 0x2F800..=0x2FA1F // CJK Compatibility Ideographs Supplement
+            | 0x3005 // Ideographic iteration mark
+            | 0x3041..=0x309F // Hiragana
+            | 0x30A0..=0x30FF // Katakana
+            | 0x31F0..=0x31FF // Katakana Phonetic Extensions
+            | 0xFF66..=0xFF9F // Halfwidth Katakana
+            | 0x1100..=0x11FF // Hangul Jamo
+            | 0x3130..=0x318F // Hangul Compatibility Jamo
+            | 0xAC00..=0xD7AF // Hangul Syllables
     )
 }
@@ -0,0 +1,261 @@
+//! "Did you mean" suggestions for queries with few hits.
+//!
+//! Candidate corrections come from `search_vocab`, an `fts5vocab` view of the
+//! terms stored in `search_index`. Terms within a length window are copied out
+//! of the database, so the DB lock is not held while they are prefiltered by
+//! trigram similarity and ranked by edit distance and document frequency.
+
+use std::collections::HashSet;
+
+use rusqlite::{params, Connection};
+
+use super::{fts, parser};
+use crate::services::Db;
+
+/// Queries with fewer hits than this get spelling suggestions.
+pub const FUZZY_MIN_HITS: usize = 3;
+const MAX_SUGGESTIONS: usize = 3;
+/// Words shorter than this are too ambiguous to correct.
+const MIN_WORD_CHARS: usize = 3;
+const MIN_TRIGRAM_SIMILARITY: f64 = 0.2;
+
+/// Full query strings in which unknown words are replaced by close vocabulary
+/// terms, best first. Only the letters of a corrected word change; filters,
+/// phrases, excluded words and punctuation are kept verbatim.
+pub fn suggest(db: &Db, query: &str) -> rusqlite::Result<Vec<String>> {
+    let Ok(words) = parser::word_spans(query) else {
+        return Ok(vec![]);
+    };
+
+    let mut corrections = Vec::new();
+    for (offset, word) in words {
+        let segments = fts::segment(&word, fts::CJK_NGRAM);
+        let [fts::Segment::Word(token)] = segments.as_slice() else {
+            continue;
+        };
+        if token.text.chars().count() < MIN_WORD_CHARS {
+            continue;
+        }
+        let terms = {
+            let conn = db.connection();
+            if is_known(&conn, &token.text)? {
+                continue;
+            }
+            vocabulary(&conn, &token.text)?
+        };
+        let candidates = candidates(&token.text, terms);
+        if !candidates.is_empty() {
+            corrections.push((offset + token.start, offset + token.end, candidates));
+        }
+    }
+    if corrections.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut suggestions = Vec::new();
+    let mut seen = HashSet::new();
+    for rank in 0..MAX_SUGGESTIONS {
+        if rank > 0 && corrections.iter().all(|(_, _, terms)| terms.len() <= rank) {
+            break;
+        }
+        let mut rewritten = String::new();
+        let mut cursor = 0;
+        for (start, end, terms) in &corrections {
+            rewritten.push_str(&query[cursor..*start]);
+            rewritten.push_str(&terms[rank.min(terms.len() - 1)]);
+            cursor = *end;
+        }
+        rewritten.push_str(&query[cursor..]);
+        if seen.insert(rewritten.clone()) {
+            suggestions.push(rewritten);
+        }
+    }
+    Ok(suggestions)
+}
+
+/// Whether some indexed term starts with `token`; bare words match as prefixes.
+fn is_known(conn: &Connection, token: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM search_vocab WHERE term >= ?1 AND term < ?2)",
+        params![token, format!("{token}\u{10FFFF}")],
+        |row| row.get(0),
+    )
+}
+
+/// Vocabulary terms whose length is within the allowed edit distance of
+/// `token`, with their document counts.
+fn vocabulary(conn: &Connection, token: &str) -> rusqlite::Result<Vec<(String, i64)>> {
+    let length = token.chars().count();
+    let max_distance = max_distance(length);
+    let mut stmt =
+        conn.prepare("SELECT term, doc FROM search_vocab WHERE length(term) BETWEEN ?1 AND ?2")?;
+    let terms = stmt
+        .query_map(
+            params![
+                length.saturating_sub(max_distance) as i64,
+                (length + max_distance) as i64
+            ],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        )?
+        .collect();
+    terms
+}
+
+/// Terms within the allowed edit distance of `token`, best first.
+fn candidates(token: &str, terms: Vec<(String, i64)>) -> Vec<String> {
+    let max_distance = max_distance(token.chars().count());
+    let token_trigrams = trigrams(token);
+
+    let mut scored = Vec::new();
+    for (term, doc_count) in terms {
+        let similarity = trigram_similarity(&token_trigrams, &trigrams(&term));
+        if similarity < MIN_TRIGRAM_SIMILARITY {
+            continue;
+        }
+        let distance = edit_distance(token, &term);
+        if distance <= max_distance {
+            scored.push((distance, similarity, doc_count, term));
+        }
+    }
+
+    scored.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.1.total_cmp(&a.1))
+            .then(b.2.cmp(&a.2))
+            .then(a.3.cmp(&b.3))
+    });
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, _, term)| term)
+        .collect()
+}
+
+fn max_distance(length: usize) -> usize {
+    if length <= 4 {
+        1
+    } else {
+        2
+    }
+}
+
+/// Character trigrams of `word` padded with two leading and one trailing space,
+/// so short words and word boundaries still produce trigrams.
+fn trigrams(word: &str) -> HashSet<[char; 3]> {
+    let padded = format!("  {word} ").chars().collect::<Vec<_>>();
+    padded
+        .windows(3)
+        .map(|window| [window[0], window[1], window[2]])
+        .collect()
+}
+
+fn trigram_similarity(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    let shared = a.intersection(b).count();
+    let union = a.len() + b.len() - shared;
+    if union == 0 {
+        0.0
+    } else {
+        shared as f64 / union as f64
+    }
+}
+
+/// Optimal string alignment distance: Levenshtein plus adjacent transpositions,
+/// the most common typing mistake.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{migration, search};
+
+    fn indexed(contents: &[&str]) -> Db {
+        let db = Db::in_memory().unwrap();
+        let mut conn = db.connection();
+        migration::migrate(&mut conn).unwrap();
+        for (index, content) in contents.iter().enumerate() {
+            search::insert_entry(&conn, search::NOTE_REF_TYPE, &index.to_string(), content)
+                .unwrap();
+        }
+        drop(conn);
+        db
+    }
+
+    #[test]
+    fn edit_distance_counts_transpositions_once() {
+        let cases = [
+            ("attention", "attention", 0),
+            ("atention", "attention", 1),
+            ("attnetion", "attention", 1),
+            ("atenttion", "attention", 2),
+            ("modèle", "modele", 1),
+            ("", "loss", 4),
+        ];
+        for (a, b, distance) in cases {
+            assert_eq!(edit_distance(a, b), distance, "{a} / {b}");
+            assert_eq!(edit_distance(b, a), distance, "{b} / {a}");
+        }
+    }
+
+    #[test]
+    fn suggestions_replace_only_the_misspelled_letters() {
+        let db = indexed(&[
+            "transformer attention mechanism",
+            "transformer models for translation",
+            "contrastive loss",
+        ]);
+        let cases = [
+            ("transfomer", vec!["transformer"]),
+            ("Transfomer attention", vec!["transformer attention"]),
+            ("(transfomer), loss", vec!["(transformer), loss"]),
+            ("transfomer* OR atention", vec!["transformer* OR attention"]),
+            (
+                "tag:method \"atention\" -contrastiv transfomer",
+                vec!["tag:method \"atention\" -contrastiv transformer"],
+            ),
+            ("ünïcode mechansim", vec!["ünïcode mechanism"]),
+            // Known words, prefixes of known words and short words stay.
+            ("attention", vec![]),
+            ("contrast", vec![]),
+            ("ls", vec![]),
+            // Unterminated quotes are left to the parser's error.
+            ("\"transfomer", vec![]),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(suggest(&db, query).unwrap(), expected, "{query}");
+        }
+    }
+
+    #[test]
+    fn suggestions_are_ranked_by_distance_then_frequency() {
+        // "model" and "modal" are one edit away and share as many trigrams.
+        let db = indexed(&["modal", "model", "model", "mode"]);
+        assert_eq!(
+            suggest(&db, "modxl").unwrap(),
+            vec!["model", "modal", "mode"]
+        );
+    }
+}
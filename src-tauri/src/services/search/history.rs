@@ -0,0 +1,204 @@
+//! Search history and saved searches.
+//!
+//! Both live in `search_history`: history entries are recorded whenever a
+//! search page is requested and have no name, saved searches are named by the
+//! user. Repeating a search bumps the existing history entry instead of adding
+//! a new one, and only the most recent unpinned history entries are kept.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::{
+    domain::{SaveSearchRequest, SavedSearch},
+    services::Db,
+    telemetry::{IpcError, IpcResult, IpcStatus},
+    utils::time::now_iso,
+};
+
+/// Unpinned, unnamed history entries kept per workspace.
+const HISTORY_RETENTION: i64 = 100;
+
+const SELECT_COLUMNS: &str =
+    "SELECT id, workspaceId, name, term, pinned, useCount, createdAt, lastUsedAt \
+     FROM search_history";
+
+/// Entries of a workspace (or of the all-workspaces scope when `None`):
+/// pinned first, then saved searches, then history by last use.
+pub fn list(db: &Db, workspace_id: Option<&str>, saved_only: bool) -> IpcResult<Vec<SavedSearch>> {
+    let conn = db.connection();
+    let mut stmt = conn
+        .prepare(&format!(
+            "{SELECT_COLUMNS} \
+             WHERE workspaceId IS ?1 AND (?2 = 0 OR name IS NOT NULL) \
+             ORDER BY pinned DESC, name IS NULL, datetime(lastUsedAt) DESC"
+        ))
+        .map_err(db_error)?;
+
+    let entries = stmt
+        .query_map(params![workspace_id, saved_only], map_saved_search)
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+
+    Ok(entries)
+}
+
+/// Adds `term` to the history of a workspace, or bumps the matching entry.
+pub fn record(db: &Db, workspace_id: Option<&str>, term: &str) -> IpcResult<()> {
+    let term = term.trim();
+    if term.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    let now = now_iso();
+
+    let updated = tx
+        .execute(
+            "UPDATE search_history SET useCount = useCount + 1, lastUsedAt = ?1 \
+             WHERE workspaceId IS ?2 AND term = ?3 AND name IS NULL",
+            params![&now, workspace_id, term],
+        )
+        .map_err(db_error)?;
+    if updated == 0 {
+        tx.execute(
+            "INSERT INTO search_history \
+             (id, workspaceId, name, term, pinned, useCount, createdAt, lastUsedAt) \
+             VALUES (?1, ?2, NULL, ?3, 0, 1, ?4, ?4)",
+            params![Uuid::new_v4().to_string(), workspace_id, term, &now],
+        )
+        .map_err(db_error)?;
+    }
+
+    tx.execute(
+        "DELETE FROM search_history WHERE id IN ( \
+           SELECT id FROM search_history \
+           WHERE workspaceId IS ?1 AND name IS NULL AND pinned = 0 \
+           ORDER BY datetime(lastUsedAt) DESC \
+           LIMIT -1 OFFSET ?2)",
+        params![workspace_id, HISTORY_RETENTION],
+    )
+    .map_err(db_error)?;
+
+    tx.commit().map_err(db_error)?;
+    Ok(())
+}
+
+pub fn save(db: &Db, request: &SaveSearchRequest) -> IpcResult<SavedSearch> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "name is required"));
+    }
+    let term = request.term.trim();
+    if term.is_empty() {
+        return Err(IpcError::new(IpcStatus::BadRequest, "term is required"));
+    }
+    let workspace_id = request
+        .workspace_id
+        .as_deref()
+        .filter(|id| !id.trim().is_empty());
+
+    let conn = db.connection();
+    if let Some(workspace_id) = workspace_id {
+        let exists = conn
+            .query_row(
+                "SELECT 1 FROM workspace WHERE id = ?1",
+                params![workspace_id],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(db_error)?
+            .is_some();
+        if !exists {
+            return Err(IpcError::new(
+                IpcStatus::NotFound,
+                format!("Workspace {workspace_id} not found"),
+            ));
+        }
+    }
+
+    let id = Uuid::new_v4().to_string();
+    let now = now_iso();
+    conn.execute(
+        "INSERT INTO search_history \
+         (id, workspaceId, name, term, pinned, useCount, createdAt, lastUsedAt) \
+         VALUES (?1, ?2, ?3, ?4, ?5, 0, ?6, ?6)",
+        params![&id, workspace_id, name, term, request.pinned, &now],
+    )
+    .map_err(db_error)?;
+
+    get_entry(&conn, &id)
+}
+
+pub fn set_pinned(db: &Db, id: &str, pinned: bool) -> IpcResult<SavedSearch> {
+    let conn = db.connection();
+    let updated = conn
+        .execute(
+            "UPDATE search_history SET pinned = ?1 WHERE id = ?2",
+            params![pinned, id],
+        )
+        .map_err(db_error)?;
+    if updated == 0 {
+        return Err(not_found(id));
+    }
+    get_entry(&conn, id)
+}
+
+/// Marks an entry as used and returns it so the caller can run its term.
+pub fn touch(db: &Db, id: &str) -> IpcResult<SavedSearch> {
+    let conn = db.connection();
+    let updated = conn
+        .execute(
+            "UPDATE search_history SET useCount = useCount + 1, lastUsedAt = ?1 WHERE id = ?2",
+            params![now_iso(), id],
+        )
+        .map_err(db_error)?;
+    if updated == 0 {
+        return Err(not_found(id));
+    }
+    get_entry(&conn, id)
+}
+
+pub fn delete(db: &Db, id: &str) -> IpcResult<()> {
+    let conn = db.connection();
+    let deleted = conn
+        .execute("DELETE FROM search_history WHERE id = ?1", params![id])
+        .map_err(db_error)?;
+    if deleted == 0 {
+        return Err(not_found(id));
+    }
+    Ok(())
+}
+
+fn get_entry(conn: &Connection, id: &str) -> IpcResult<SavedSearch> {
+    conn.query_row(
+        &format!("{SELECT_COLUMNS} WHERE id = ?1"),
+        params![id],
+        map_saved_search,
+    )
+    .optional()
+    .map_err(db_error)?
+    .ok_or_else(|| not_found(id))
+}
+
+fn map_saved_search(row: &rusqlite::Row<'_>) -> rusqlite::Result<SavedSearch> {
+    Ok(SavedSearch {
+        id: row.get("id")?,
+        workspace_id: row.get("workspaceId")?,
+        name: row.get("name")?,
+        term: row.get("term")?,
+        pinned: row.get("pinned")?,
+        use_count: row.get("useCount")?,
+        created_at: row.get("createdAt")?,
+        last_used_at: row.get("lastUsedAt")?,
+    })
+}
+
+fn not_found(id: &str) -> IpcError {
+    IpcError::new(IpcStatus::NotFound, format!("Saved search {id} not found"))
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
@@ -1,75 +1,372 @@
 pub mod fts;
+pub mod fuzzy;
+pub mod history;
+pub mod parser;
+pub mod rebuild;
+pub mod tokenizer;
+
+pub use rebuild::index_outdated;
 
 use crate::{
-    domain::SearchHit,
+    domain::{SearchHit, SearchPage, SearchRanking, SearchRequest, SearchResultHit},
     telemetry::{IpcError, IpcResult, IpcStatus},
 };
-use rusqlite::params;
+use rusqlite::{params, types::Value, OptionalExtension, ToSql};
 
 pub const NOTE_REF_TYPE: &str = "note";
+pub const HIGHLIGHT_REF_TYPE: &str = "highlight";
+pub const TAG_REF_TYPE: &str = "tag";
+pub const PDF_REF_TYPE: &str = "pdf";
+pub const PAPER_REF_TYPE: &str = "paper";
+
+/// Text indexed for a paper: its title and abstract. Its authors are the
+/// entry label.
+pub(super) const PAPER_CONTENT_SQL: &str = "trim(title || char(10) || COALESCE(abstract, ''))";
 
-pub fn query(db: &super::Db, term: &str, limit: u32) -> IpcResult<Vec<SearchHit>> {
-    let Some(match_expr) = build_match_expression(term) else {
+/// Maximum number of hits returned by one query or page.
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// Restricts a search to one workspace and/or a set of papers.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SearchScope<'a> {
+    pub workspace_id: Option<&'a str>,
+    pub paper_ids: &'a [String],
+}
+
+/// Runs a search query written in the syntax described in [`parser`]. Hits are
+/// ordered by a 0..1 score combining the column-weighted bm25 relevance, the
+/// weight of the hit's ref type and, for notes and highlights, a boost that
+/// decays with the time since they were last edited. Queries made only of filters skip the
+/// full-text match and score every remaining hit by type and recency alone.
+pub fn query(
+    db: &super::Db,
+    term: &str,
+    limit: u32,
+    ranking: &SearchRanking,
+) -> IpcResult<Vec<SearchHit>> {
+    let Some(mut plan) = SearchPlan::new(term, ranking, SearchScope::default())? else {
         return Ok(vec![]);
     };
+    plan.bind(
+        ":limit",
+        Value::Integer(limit.clamp(1, MAX_PAGE_SIZE) as i64),
+    );
 
-    let capped_limit = limit.clamp(1, 100) as i64;
-    let mut conn = db.connection();
+    let conn = db.connection();
     let mut stmt = conn
-        .prepare(
-            "SELECT refType, refId, snippet(search_index, 0, '<b>', '</b>', ' ... ', 10) AS snippet, \
-             bm25(search_index) AS rank \
-             FROM search_index \
-             WHERE search_index MATCH ?1 \
-             ORDER BY rank ASC \
-             LIMIT ?2",
-        )
+        .prepare(&format!(
+            "{} ORDER BY score DESC, refType, refId LIMIT :limit",
+            plan.select_sql()
+        ))
         .map_err(db_error)?;
+    let hits = stmt
+        .query_map(plan.params().as_slice(), read_hit)
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+
+    Ok(hits)
+}
 
+/// Runs [`query`] restricted to `request`'s workspace and papers and returns
+/// one page of enriched hits together with the total hit count.
+pub fn query_page(
+    db: &super::Db,
+    request: &SearchRequest,
+    ranking: &SearchRanking,
+) -> IpcResult<SearchPage> {
+    let offset = match request.cursor.as_deref().map(str::trim) {
+        None | Some("") => 0,
+        Some(cursor) => cursor.parse::<u32>().map_err(|_| {
+            IpcError::new(
+                IpcStatus::BadRequest,
+                format!("Invalid search cursor {cursor}"),
+            )
+        })?,
+    };
+    let limit = request.limit.unwrap_or(20).clamp(1, MAX_PAGE_SIZE);
+    let scope = SearchScope {
+        workspace_id: request
+            .workspace_id
+            .as_deref()
+            .filter(|id| !id.trim().is_empty()),
+        paper_ids: &request.paper_ids,
+    };
+
+    let Some(mut plan) = SearchPlan::new(&request.term, ranking, scope)? else {
+        return Ok(SearchPage::default());
+    };
+
+    let mut total = count_hits(&db.connection(), &plan).map_err(db_error)?;
+    let mut suggestions = Vec::new();
+    let mut corrected_term = None;
+    if total < fuzzy::FUZZY_MIN_HITS {
+        suggestions = fuzzy::suggest(db, &request.term).map_err(db_error)?;
+        // With no hits at all, show the hits of the best suggestion instead.
+        if total == 0 {
+            if let Some(suggestion) = suggestions.first() {
+                if let Some(corrected) = SearchPlan::new(suggestion, ranking, scope)? {
+                    total = count_hits(&db.connection(), &corrected).map_err(db_error)?;
+                    plan = corrected;
+                    corrected_term = Some(suggestion.clone());
+                }
+            }
+        }
+    }
+
+    let conn = db.connection();
+    plan.bind(":limit", Value::Integer(i64::from(limit)));
+    plan.bind(":offset", Value::Integer(i64::from(offset)));
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} ORDER BY score DESC, refType, refId LIMIT :limit OFFSET :offset",
+            plan.select_sql()
+        ))
+        .map_err(db_error)?;
     let hits = stmt
-        .query_map(params![match_expr, capped_limit], |row| {
-            let rank: f64 = row.get("rank")?;
-            let score = if rank.is_finite() {
-                (1.0 / (1.0 + rank)) as f32
-            } else {
-                0.0
-            };
-
-            Ok(SearchHit {
-                ref_type: row.get("refType")?,
-                ref_id: row.get("refId")?,
-                snippet: row.get::<_, Option<String>>("snippet")?,
-                score,
+        .query_map(plan.params().as_slice(), |row| {
+            Ok(SearchResultHit {
+                hit: read_hit(row)?,
+                paper_title: row.get("paperTitle")?,
+                note_color: row.get("noteColor")?,
+                workspace_id: row.get("workspaceId")?,
+                workspace_name: row.get("workspaceName")?,
             })
         })
         .map_err(db_error)?
         .collect::<Result<Vec<_>, _>>()
         .map_err(db_error)?;
 
-    Ok(hits)
+    let next_offset = offset as usize + hits.len();
+    Ok(SearchPage {
+        next_cursor: (!hits.is_empty() && next_offset < total).then(|| next_offset.to_string()),
+        hits,
+        total,
+        suggestions,
+        corrected_term,
+    })
 }
 
-pub fn rebuild(db: &super::Db) -> IpcResult<()> {
-    let mut conn = db.connection();
-    let tx = conn.transaction().map_err(db_error)?;
+fn count_hits(conn: &rusqlite::Connection, plan: &SearchPlan) -> rusqlite::Result<usize> {
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM ({})", plan.inner_sql()),
+        plan.inner_params().as_slice(),
+        |row| row.get(0),
+    )?;
+    Ok(total.max(0) as usize)
+}
 
-    tx.execute("DELETE FROM search_index", [])
-        .map_err(db_error)?;
+/// SQL and bound parameters shared by [`query`] and [`query_page`]. The inner
+/// select yields one row per matching entry; the outer select adds the score.
+struct SearchPlan {
+    snippet_sql: &'static str,
+    relevance_sql: &'static str,
+    conditions: Vec<String>,
+    /// Parameters referenced by the inner select.
+    inner: Vec<(String, Value)>,
+    /// Parameters only referenced by the outer select.
+    outer: Vec<(String, Value)>,
+}
 
-    let mut stmt = tx
-        .prepare("SELECT id, content FROM note")
-        .map_err(db_error)?;
-    let mut rows = stmt.query([]).map_err(db_error)?;
-    while let Some(row) = rows.next().map_err(db_error)? {
-        let note_id: String = row.get("id")?;
-        let content: String = row.get("content")?;
-        upsert_entry(&tx, NOTE_REF_TYPE, &note_id, &content)?;
+impl SearchPlan {
+    fn new(term: &str, ranking: &SearchRanking, scope: SearchScope) -> IpcResult<Option<Self>> {
+        let parsed = parser::parse(term).map_err(|err| {
+            IpcError::new(IpcStatus::BadRequest, err.message.clone())
+                .with_details(serde_json::json!({ "position": err.position, "query": term }))
+        })?;
+        if parsed.is_empty() {
+            return Ok(None);
+        }
+        let compiled = parser::compile(&parsed);
+        if compiled.match_expr.is_none() && compiled.conditions.is_empty() {
+            return Ok(None);
+        }
+
+        let note_weight = ranking.note_weight.max(0.0);
+        let highlight_weight = ranking.highlight_weight.max(0.0);
+        let tag_weight = ranking.tag_weight.max(0.0);
+        let pdf_weight = ranking.pdf_weight.max(0.0);
+        let paper_weight = ranking.paper_weight.max(0.0);
+        let max_type_weight = note_weight
+            .max(highlight_weight)
+            .max(tag_weight)
+            .max(pdf_weight)
+            .max(paper_weight);
+        let max_type_weight = if max_type_weight > 0.0 {
+            max_type_weight
+        } else {
+            1.0
+        };
+        let half_life = if ranking.recency_half_life_days > 0.0 {
+            ranking.recency_half_life_days
+        } else {
+            1.0
+        };
+
+        let mut plan = Self {
+            snippet_sql: "substr(COALESCE(note.content, highlight.text, paper_passage.content, \
+                 tag_entry.name, paper.abstract, paper.title), 1, 160)",
+            relevance_sql: "1.0",
+            conditions: vec!["(note.id IS NOT NULL OR highlight.id IS NOT NULL \
+                 OR paper_passage.id IS NOT NULL OR tag_entry.id IS NOT NULL \
+                 OR (search_index.refType = :paper_type AND paper.id IS NOT NULL))"
+                .to_string()],
+            inner: vec![
+                (":note_type".into(), Value::Text(NOTE_REF_TYPE.into())),
+                (
+                    ":highlight_type".into(),
+                    Value::Text(HIGHLIGHT_REF_TYPE.into()),
+                ),
+                (":tag_type".into(), Value::Text(TAG_REF_TYPE.into())),
+                (":pdf_type".into(), Value::Text(PDF_REF_TYPE.into())),
+                (":paper_type".into(), Value::Text(PAPER_REF_TYPE.into())),
+                (":note_weight".into(), Value::Real(note_weight)),
+                (":highlight_weight".into(), Value::Real(highlight_weight)),
+                (":tag_weight".into(), Value::Real(tag_weight)),
+                (":pdf_weight".into(), Value::Real(pdf_weight)),
+                (":paper_weight".into(), Value::Real(paper_weight)),
+                (":half_life".into(), Value::Real(half_life)),
+            ],
+            outer: vec![
+                (":max_type_weight".into(), Value::Real(max_type_weight)),
+                (
+                    ":recency_boost".into(),
+                    Value::Real(ranking.recency_boost.max(0.0)),
+                ),
+            ],
+        };
+
+        if let Some(match_expr) = compiled.match_expr {
+            plan.snippet_sql = "snippet(search_index, 0, '<b>', '</b>', ' ... ', 10)";
+            plan.relevance_sql = "MAX(-bm25(search_index, :content_weight, :label_weight), 0.0)";
+            plan.conditions.push("search_index MATCH :match".into());
+            plan.inner.extend([
+                (":match".into(), Value::Text(match_expr)),
+                (
+                    ":content_weight".into(),
+                    Value::Real(ranking.content_column_weight.max(0.0)),
+                ),
+                (
+                    ":label_weight".into(),
+                    Value::Real(ranking.label_column_weight.max(0.0)),
+                ),
+            ]);
+        }
+        plan.conditions.extend(compiled.conditions);
+        plan.inner.extend(compiled.params);
+
+        if let Some(workspace_id) = scope.workspace_id {
+            plan.conditions
+                .push("paper.workspaceId = :scope_workspace".into());
+            plan.inner
+                .push((":scope_workspace".into(), Value::Text(workspace_id.into())));
+        }
+        if !scope.paper_ids.is_empty() {
+            let names = scope
+                .paper_ids
+                .iter()
+                .enumerate()
+                .map(|(index, paper_id)| {
+                    let name = format!(":scope_paper{index}");
+                    plan.inner
+                        .push((name.clone(), Value::Text(paper_id.clone())));
+                    name
+                })
+                .collect::<Vec<_>>();
+            plan.conditions
+                .push(format!("paper.id IN ({})", names.join(", ")));
+        }
+
+        Ok(Some(plan))
     }
 
-    tx.commit().map_err(db_error)?;
-    Ok(())
+    fn bind(&mut self, name: &str, value: Value) {
+        self.outer.push((name.to_string(), value));
+    }
+
+    fn inner_sql(&self) -> String {
+        format!(
+            "SELECT search_index.refType AS refType, search_index.refId AS refId, \
+             {} AS snippet, \
+             {} AS relevance, \
+             CASE search_index.refType \
+               WHEN :note_type THEN :note_weight \
+               WHEN :highlight_type THEN :highlight_weight \
+               WHEN :tag_type THEN :tag_weight \
+               WHEN :paper_type THEN :paper_weight \
+               ELSE :pdf_weight END AS typeWeight, \
+             CASE WHEN COALESCE(note.updatedAt, highlight.updatedAt) IS NULL THEN 0.0 \
+               ELSE :half_life / (:half_life + MAX(julianday('now') \
+                 - julianday(COALESCE(note.updatedAt, highlight.updatedAt)), 0.0)) END \
+               AS recency, \
+             paper.id AS paperId, \
+             COALESCE(note.page, highlight.page, paper_passage.page) AS page, \
+             paper.title AS paperTitle, COALESCE(note.color, highlight.color) AS noteColor, \
+             paper.workspaceId AS workspaceId, workspace.name AS workspaceName \
+             FROM search_index \
+             LEFT JOIN note \
+               ON search_index.refType = :note_type AND note.id = search_index.refId \
+             LEFT JOIN highlight \
+               ON search_index.refType = :highlight_type AND highlight.id = search_index.refId \
+             LEFT JOIN paper_passage \
+               ON search_index.refType = :pdf_type AND paper_passage.id = search_index.refId \
+             LEFT JOIN tag AS tag_entry \
+               ON search_index.refType = :tag_type AND tag_entry.id = search_index.refId \
+             LEFT JOIN paper \
+               ON paper.id = COALESCE(note.paperId, highlight.paperId, paper_passage.paperId, \
+                 CASE WHEN search_index.refType = :paper_type THEN search_index.refId END) \
+             LEFT JOIN workspace ON workspace.id = paper.workspaceId \
+             WHERE {}",
+            self.snippet_sql,
+            self.relevance_sql,
+            self.conditions.join(" AND ")
+        )
+    }
+
+    fn select_sql(&self) -> String {
+        format!(
+            "SELECT *, \
+             (typeWeight / :max_type_weight) * (relevance / (1.0 + relevance)) \
+               * (1.0 + :recency_boost * recency) / (1.0 + :recency_boost) AS score \
+             FROM ({})",
+            self.inner_sql()
+        )
+    }
+
+    fn inner_params(&self) -> Vec<(&str, &dyn ToSql)> {
+        self.inner
+            .iter()
+            .map(|(name, value)| (name.as_str(), value as &dyn ToSql))
+            .collect()
+    }
+
+    fn params(&self) -> Vec<(&str, &dyn ToSql)> {
+        self.inner
+            .iter()
+            .chain(&self.outer)
+            .map(|(name, value)| (name.as_str(), value as &dyn ToSql))
+            .collect()
+    }
 }
 
+fn read_hit(row: &rusqlite::Row<'_>) -> rusqlite::Result<SearchHit> {
+    let score: f64 = row.get("score")?;
+    Ok(SearchHit {
+        ref_type: row.get("refType")?,
+        ref_id: row.get("refId")?,
+        snippet: row.get::<_, Option<String>>("snippet")?,
+        score: if score.is_finite() {
+            score.clamp(0.0, 1.0) as f32
+        } else {
+            0.0
+        },
+        paper_id: row.get("paperId")?,
+        page: row.get("page")?,
+    })
+}
+
+/// Replaces the index entry of `ref_id`. While a rebuild is running the entry
+/// is written to the rebuild table as well, so the swapped-in index keeps it.
 pub fn upsert_entry(
     conn: &rusqlite::Connection,
     ref_type: &str,
@@ -77,67 +374,193 @@ pub fn upsert_entry(
     raw_content: &str,
 ) -> rusqlite::Result<()> {
     remove_entry(conn, ref_type, ref_id)?;
+    for table in index_tables(conn)? {
+        write_entry(conn, table, ref_type, ref_id, raw_content)?;
+    }
+    Ok(())
+}
 
-    if let Some(normalized) = normalize_content(raw_content) {
-        conn.execute(
-            "INSERT INTO search_index (content, refType, refId) VALUES (?1, ?2, ?3)",
-            params![normalized, ref_type, ref_id],
-        )?;
+/// Writes the entry of a `ref_id` that has none yet, without the removal
+/// [`upsert_entry`] runs first.
+pub fn insert_entry(
+    conn: &rusqlite::Connection,
+    ref_type: &str,
+    ref_id: &str,
+    raw_content: &str,
+) -> rusqlite::Result<()> {
+    let tables = index_tables(conn)?;
+    if tables.len() > 1 {
+        rebuild::mark_touched(conn, ref_type, ref_id)?;
+    }
+    for table in tables {
+        write_entry(conn, table, ref_type, ref_id, raw_content)?;
     }
     Ok(())
 }
 
-pub fn remove_entry(
+fn write_entry(
     conn: &rusqlite::Connection,
+    table: &str,
     ref_type: &str,
     ref_id: &str,
+    raw_content: &str,
 ) -> rusqlite::Result<()> {
-    conn.execute(
-        "DELETE FROM search_index WHERE refType = ?1 AND refId = ?2",
-        params![ref_type, ref_id],
-    )?;
+    let content = raw_content.trim();
+    if !content.is_empty() {
+        let label = entry_label(conn, ref_type, ref_id)?;
+        conn.execute(
+            &format!(
+                "INSERT INTO {table} (content, label, refType, refId) VALUES (?1, ?2, ?3, ?4)"
+            ),
+            params![content, label, ref_type, ref_id],
+        )?;
+    }
     Ok(())
 }
 
-fn build_match_expression(term: &str) -> Option<String> {
-    let trimmed = term.trim();
-    if trimmed.is_empty() {
-        return None;
+/// `search_index` plus the rebuild table when a rebuild is in progress.
+fn index_tables(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<&'static str>> {
+    let mut tables = vec!["search_index"];
+    if rebuild::in_progress(conn)? {
+        tables.push(rebuild::REBUILD_TABLE);
     }
+    Ok(tables)
+}
 
-    let mut tokens = fts::tokenize(trimmed);
-    if tokens.is_empty() {
-        tokens = trimmed
-            .split_whitespace()
-            .map(|t| t.to_string())
-            .collect::<Vec<_>>();
+/// Short descriptive text indexed in the `label` column: the tag names of a
+/// note, the comment of a highlight or the authors of a paper.
+fn entry_label(
+    conn: &rusqlite::Connection,
+    ref_type: &str,
+    ref_id: &str,
+) -> rusqlite::Result<Option<String>> {
+    let sql = match ref_type {
+        NOTE_REF_TYPE => {
+            "SELECT group_concat(tag.name, ' ') FROM note_tag \
+             JOIN tag ON tag.id = note_tag.tagId \
+             WHERE note_tag.noteId = ?1"
+        }
+        HIGHLIGHT_REF_TYPE => "SELECT comment FROM highlight WHERE id = ?1",
+        PAPER_REF_TYPE => {
+            "SELECT group_concat(name, ', ') FROM (SELECT author.name AS name \
+             FROM paper_author JOIN author ON author.id = paper_author.authorId \
+             WHERE paper_author.paperId = ?1 ORDER BY paper_author.position)"
+        }
+        _ => return Ok(None),
+    };
+
+    conn.query_row(sql, params![ref_id], |row| row.get::<_, Option<String>>(0))
+        .optional()
+        .map(Option::flatten)
+}
+
+/// Reindexes a paper after its title, abstract or authors changed, or drops
+/// its entry when the paper is gone.
+pub fn upsert_paper_entry(conn: &rusqlite::Connection, paper_id: &str) -> rusqlite::Result<()> {
+    let content = conn
+        .query_row(
+            &format!("SELECT {PAPER_CONTENT_SQL} FROM paper WHERE id = ?1"),
+            params![paper_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    match content {
+        Some(content) => upsert_entry(conn, PAPER_REF_TYPE, paper_id, &content),
+        None => remove_entry(conn, PAPER_REF_TYPE, paper_id),
     }
+}
 
-    if tokens.is_empty() {
-        return None;
+/// Removes the entries of every stored passage of a paper, with one statement
+/// per index table.
+pub fn remove_passage_entries(conn: &rusqlite::Connection, paper_id: &str) -> rusqlite::Result<()> {
+    for table in index_tables(conn)? {
+        conn.execute(
+            &format!(
+                "DELETE FROM {table} WHERE refType = ?1 \
+                 AND refId IN (SELECT id FROM paper_passage WHERE paperId = ?2)"
+            ),
+            params![PDF_REF_TYPE, paper_id],
+        )?;
     }
+    Ok(())
+}
 
-    Some(
-        tokens
-            .into_iter()
-            .map(|token| format!("{token}*"))
-            .collect::<Vec<_>>()
-            .join(" "),
-    )
+pub fn remove_entry(
+    conn: &rusqlite::Connection,
+    ref_type: &str,
+    ref_id: &str,
+) -> rusqlite::Result<()> {
+    if rebuild::in_progress(conn)? {
+        rebuild::mark_touched(conn, ref_type, ref_id)?;
+    }
+    for table in index_tables(conn)? {
+        conn.execute(
+            &format!("DELETE FROM {table} WHERE refType = ?1 AND refId = ?2"),
+            params![ref_type, ref_id],
+        )?;
+    }
+    Ok(())
 }
 
-fn normalize_content(raw: &str) -> Option<String> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return None;
+/// FTS5 expression for one bare search word: words match as prefixes and
+/// each CJK run becomes a phrase of its n-grams, so its characters must appear
+/// contiguously. A CJK run shorter than an n-gram matches n-grams it starts.
+fn build_match_expression(term: &str) -> Option<String> {
+    let parts = fts::segment(term.trim(), fts::CJK_NGRAM)
+        .into_iter()
+        .map(|segment| match segment {
+            fts::Segment::Word(word) => format!("\"{}\"*", word.text),
+            fts::Segment::Cjk(grams) if grams.len() == 1 => {
+                if grams[0].text.chars().count() < fts::CJK_NGRAM {
+                    format!("\"{}\"*", grams[0].text)
+                } else {
+                    format!("\"{}\"", grams[0].text)
+                }
+            }
+            fts::Segment::Cjk(grams) => format!("\"{}\"", join_tokens(&grams)),
+        })
+        .collect::<Vec<_>>();
+
+    match parts.len() {
+        0 => None,
+        1 => parts.into_iter().next(),
+        _ => Some(format!("({})", parts.join(" AND "))),
     }
+}
 
-    let tokens = fts::tokenize(trimmed);
+/// FTS5 expression for a quoted phrase: its tokens must appear in order. A
+/// trailing CJK run shorter than an n-gram matches as a prefix.
+fn build_phrase_expression(phrase: &str) -> Option<String> {
+    let segments = fts::segment(phrase.trim(), fts::CJK_NGRAM);
+    let short_tail = match segments.last() {
+        Some(fts::Segment::Cjk(grams)) => {
+            grams.len() == 1 && grams[0].text.chars().count() < fts::CJK_NGRAM
+        }
+        _ => false,
+    };
+    let tokens = segments
+        .into_iter()
+        .flat_map(|segment| match segment {
+            fts::Segment::Word(word) => vec![word],
+            fts::Segment::Cjk(grams) => grams,
+        })
+        .collect::<Vec<_>>();
     if tokens.is_empty() {
-        Some(trimmed.replace('\n', " "))
-    } else {
-        Some(tokens.join(" "))
+        return None;
     }
+    Some(format!(
+        "\"{}\"{}",
+        join_tokens(&tokens),
+        if short_tail { "*" } else { "" }
+    ))
+}
+
+fn join_tokens(tokens: &[fts::Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| token.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
 fn db_error(err: rusqlite::Error) -> IpcError {
@@ -0,0 +1,710 @@
+//! Structured search syntax.
+//!
+//! ```text
+//! contrastive "attention mechanism" -survey tag:method page:10..20
+//! loss OR objective type:note|pdf after:2024-01-01 workspace:"Topic A"
+//! ```
+//!
+//! Bare words and quoted phrases are full-text terms and are ANDed together;
+//! `OR` joins neighbouring terms and `-` excludes a term or negates a filter.
+//! `key:value` filters narrow results by workspace, paper, paper author, tag
+//! (including its descendant tags), note color, page range, ref type and
+//! creation date.
+//! Values may be quoted and `|` separates alternatives. Unknown keys are
+//! treated as plain words.
+
+use chrono::NaiveDate;
+use rusqlite::types::Value;
+
+use super::{
+    build_match_expression, build_phrase_expression, HIGHLIGHT_REF_TYPE, NOTE_REF_TYPE,
+    PAPER_REF_TYPE, PDF_REF_TYPE, TAG_REF_TYPE,
+};
+use crate::services::authors::normalize_name;
+
+/// Ref types accepted by `type:`.
+const SEARCHABLE_TYPES: &[&str] = &[
+    NOTE_REF_TYPE,
+    HIGHLIGHT_REF_TYPE,
+    PDF_REF_TYPE,
+    TAG_REF_TYPE,
+    PAPER_REF_TYPE,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Word(String),
+    Phrase(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FilterKind {
+    Workspace(Vec<String>),
+    Paper(Vec<String>),
+    /// Normalized author names, matched as substrings.
+    Author(Vec<String>),
+    Tag(Vec<String>),
+    Color(Vec<String>),
+    Page {
+        from: Option<i32>,
+        to: Option<i32>,
+    },
+    Type(Vec<String>),
+    Before(NaiveDate),
+    After(NaiveDate),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub negated: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedQuery {
+    /// Positive terms as a conjunction of OR-groups.
+    pub groups: Vec<Vec<Term>>,
+    pub excluded: Vec<Term>,
+    pub filters: Vec<Filter>,
+}
+
+impl ParsedQuery {
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty() && self.excluded.is_empty() && self.filters.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    /// Character offset into the query where the problem starts.
+    pub position: usize,
+}
+
+impl ParseError {
+    fn new(message: impl Into<String>, position: usize) -> Self {
+        Self {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+/// FTS5 expression and SQL conditions produced from a [`ParsedQuery`]. The
+/// conditions reference the `search_index`, `note`, `highlight`,
+/// `paper_passage`, `paper` and `workspace` tables and the indexed tag `tag_entry`, and bind their
+/// values through `params`.
+#[derive(Debug, Default)]
+pub struct CompiledQuery {
+    pub match_expr: Option<String>,
+    pub conditions: Vec<String>,
+    pub params: Vec<(String, Value)>,
+}
+
+#[derive(Debug)]
+enum Lexeme {
+    Or,
+    Term {
+        term: Term,
+        negated: bool,
+    },
+    Filter {
+        key: String,
+        value: String,
+        value_position: usize,
+        quoted: bool,
+        negated: bool,
+    },
+}
+
+pub fn parse(input: &str) -> Result<ParsedQuery, ParseError> {
+    let lexemes = lex(input)?;
+    let mut parsed = ParsedQuery::default();
+    let mut pending_or: Option<usize> = None;
+    let mut last_was_positive_term = false;
+
+    for (position, lexeme) in lexemes {
+        match lexeme {
+            Lexeme::Or => {
+                if !last_was_positive_term || pending_or.is_some() {
+                    return Err(ParseError::new("OR must follow a search term", position));
+                }
+                pending_or = Some(position);
+                last_was_positive_term = false;
+            }
+            Lexeme::Term {
+                term,
+                negated: false,
+            } => {
+                match (pending_or.take(), parsed.groups.last_mut()) {
+                    (Some(_), Some(group)) => group.push(term),
+                    _ => parsed.groups.push(vec![term]),
+                }
+                last_was_positive_term = true;
+            }
+            Lexeme::Term {
+                term,
+                negated: true,
+            } => {
+                if pending_or.is_some() {
+                    return Err(ParseError::new(
+                        "OR must be followed by a search term",
+                        position,
+                    ));
+                }
+                parsed.excluded.push(term);
+                last_was_positive_term = false;
+            }
+            Lexeme::Filter {
+                key,
+                value,
+                value_position,
+                quoted,
+                negated,
+            } => {
+                if pending_or.is_some() {
+                    return Err(ParseError::new(
+                        "OR must be followed by a search term",
+                        position,
+                    ));
+                }
+                let kind = parse_filter(&key, &value, quoted, value_position)?;
+                parsed.filters.push(Filter { kind, negated });
+                last_was_positive_term = false;
+            }
+        }
+    }
+
+    if let Some(position) = pending_or {
+        return Err(ParseError::new(
+            "OR must be followed by a search term",
+            position,
+        ));
+    }
+
+    Ok(parsed)
+}
+
+/// Bare, non-excluded words of `input` with their byte offsets, in order.
+/// Used to rewrite a query word by word, e.g. for spelling suggestions.
+pub fn word_spans(input: &str) -> Result<Vec<(usize, String)>, ParseError> {
+    let offsets = input
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .collect::<Vec<_>>();
+    Ok(lex(input)?
+        .into_iter()
+        .filter_map(|(position, lexeme)| match lexeme {
+            Lexeme::Term {
+                term: Term::Word(word),
+                negated: false,
+            } => Some((offsets[position], word)),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Turns a parsed query into an FTS5 MATCH expression plus SQL filters.
+/// Parameter names are `:q0`, `:q1`, ... so they cannot clash with the caller's.
+pub fn compile(parsed: &ParsedQuery) -> CompiledQuery {
+    let mut compiled = CompiledQuery::default();
+
+    let groups = parsed
+        .groups
+        .iter()
+        .filter_map(|group| {
+            let alternatives = group.iter().filter_map(term_expression).collect::<Vec<_>>();
+            match alternatives.len() {
+                0 => None,
+                1 => alternatives.into_iter().next(),
+                _ => Some(format!("({})", alternatives.join(" OR "))),
+            }
+        })
+        .collect::<Vec<_>>();
+    let excluded = parsed
+        .excluded
+        .iter()
+        .filter_map(term_expression)
+        .collect::<Vec<_>>();
+
+    if !groups.is_empty() {
+        let positive = groups.join(" AND ");
+        compiled.match_expr = Some(if excluded.is_empty() {
+            positive
+        } else {
+            format!("({positive}) NOT ({})", excluded.join(" OR "))
+        });
+    } else if !excluded.is_empty() {
+        // FTS5 cannot evaluate a lone NOT, so exclusions become a rowid filter.
+        let name = compiled.bind(Value::Text(excluded.join(" OR ")));
+        compiled.conditions.push(format!(
+            "search_index.rowid NOT IN \
+             (SELECT rowid FROM search_index WHERE search_index MATCH {name})"
+        ));
+    }
+
+    for filter in &parsed.filters {
+        let condition = compiled.filter_condition(&filter.kind);
+        compiled.conditions.push(if filter.negated {
+            format!("NOT COALESCE(({condition}), 0)")
+        } else {
+            condition
+        });
+    }
+
+    compiled
+}
+
+impl CompiledQuery {
+    fn bind(&mut self, value: Value) -> String {
+        let name = format!(":q{}", self.params.len());
+        self.params.push((name.clone(), value));
+        name
+    }
+
+    fn any_of(&mut self, values: &[String], condition: impl Fn(&str) -> String) -> String {
+        let alternatives = values
+            .iter()
+            .map(|value| {
+                let name = self.bind(Value::Text(value.clone()));
+                condition(&name)
+            })
+            .collect::<Vec<_>>();
+        format!("({})", alternatives.join(" OR "))
+    }
+
+    fn filter_condition(&mut self, kind: &FilterKind) -> String {
+        match kind {
+            FilterKind::Workspace(values) => self.any_of(values, |name| {
+                format!("paper.workspaceId = {name} OR workspace.name = {name} COLLATE NOCASE")
+            }),
+            FilterKind::Paper(values) => self.any_of(values, |name| {
+                format!("paper.id = {name} OR instr(lower(paper.title), lower({name})) > 0")
+            }),
+            FilterKind::Author(values) => self.any_of(values, |name| {
+                format!(
+                    "EXISTS (SELECT 1 FROM paper_author \
+                     JOIN author ON author.id = paper_author.authorId \
+                     WHERE paper_author.paperId = paper.id \
+                     AND instr(author.normalizedName, {name}) > 0)"
+                )
+            }),
+            FilterKind::Tag(values) => self.any_of(values, |name| {
+                format!(
+                    "EXISTS (SELECT 1 FROM note_tag JOIN tag ON tag.id = note_tag.tagId \
+                     WHERE note_tag.noteId = note.id AND {}) OR {}",
+                    tag_path_condition("tag.name", name),
+                    tag_path_condition("tag_entry.name", name)
+                )
+            }),
+            FilterKind::Color(values) => self.any_of(values, |name| {
+                format!("COALESCE(note.color, highlight.color) = {name} COLLATE NOCASE")
+            }),
+            FilterKind::Type(values) => {
+                self.any_of(values, |name| format!("search_index.refType = {name}"))
+            }
+            FilterKind::Page { from, to } => {
+                let mut bounds = Vec::new();
+                if let Some(from) = from {
+                    let name = self.bind(Value::Integer(i64::from(*from)));
+                    bounds.push(format!(
+                        "COALESCE(note.page, highlight.page, paper_passage.page) >= {name}"
+                    ));
+                }
+                if let Some(to) = to {
+                    let name = self.bind(Value::Integer(i64::from(*to)));
+                    bounds.push(format!(
+                        "COALESCE(note.page, highlight.page, paper_passage.page) <= {name}"
+                    ));
+                }
+                format!("({})", bounds.join(" AND "))
+            }
+            FilterKind::Before(date) => {
+                let name = self.bind(Value::Text(date.format("%Y-%m-%d").to_string()));
+                format!(
+                    "date(COALESCE(note.createdAt, highlight.createdAt, paper.createdAt)) < {name}"
+                )
+            }
+            FilterKind::After(date) => {
+                let name = self.bind(Value::Text(date.format("%Y-%m-%d").to_string()));
+                format!(
+                    "date(COALESCE(note.createdAt, highlight.createdAt, paper.createdAt)) > {name}"
+                )
+            }
+        }
+    }
+}
+
+/// `column` is the tag path `name` or one of its descendants.
+fn tag_path_condition(column: &str, name: &str) -> String {
+    format!(
+        "({column} = {name} COLLATE NOCASE \
+         OR lower(substr({column}, 1, length({name}) + 1)) = lower({name}) || '/')"
+    )
+}
+
+fn term_expression(term: &Term) -> Option<String> {
+    match term {
+        Term::Word(word) => build_match_expression(word),
+        Term::Phrase(phrase) => build_phrase_expression(phrase),
+    }
+}
+
+fn lex(input: &str) -> Result<Vec<(usize, Lexeme)>, ParseError> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut lexemes = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        let negated = chars[i] == '-' && chars.get(i + 1).is_some_and(|c| !c.is_whitespace());
+        if negated {
+            i += 1;
+        }
+
+        if chars[i] == '"' {
+            let (phrase, next) = read_quoted(&chars, i)?;
+            i = next;
+            lexemes.push((
+                start,
+                Lexeme::Term {
+                    term: Term::Phrase(phrase),
+                    negated,
+                },
+            ));
+            continue;
+        }
+
+        let word_start = i;
+        while i < chars.len() && !chars[i].is_whitespace() && chars[i] != ':' {
+            i += 1;
+        }
+        let head = chars[word_start..i].iter().collect::<String>();
+
+        if i < chars.len() && chars[i] == ':' && is_filter_key(&head) {
+            i += 1;
+            let value_position = i;
+            let (value, quoted) = if chars.get(i) == Some(&'"') {
+                let (value, next) = read_quoted(&chars, i)?;
+                i = next;
+                (value, true)
+            } else {
+                let value_start = i;
+                while i < chars.len() && !chars[i].is_whitespace() {
+                    i += 1;
+                }
+                (chars[value_start..i].iter().collect(), false)
+            };
+            lexemes.push((
+                start,
+                Lexeme::Filter {
+                    key: head.to_ascii_lowercase(),
+                    value,
+                    value_position,
+                    quoted,
+                    negated,
+                },
+            ));
+            continue;
+        }
+
+        while i < chars.len() && !chars[i].is_whitespace() {
+            i += 1;
+        }
+        let word = chars[word_start..i].iter().collect::<String>();
+        if word == "OR" && !negated {
+            lexemes.push((start, Lexeme::Or));
+        } else {
+            lexemes.push((
+                start,
+                Lexeme::Term {
+                    term: Term::Word(word),
+                    negated,
+                },
+            ));
+        }
+    }
+
+    Ok(lexemes)
+}
+
+fn read_quoted(chars: &[char], open: usize) -> Result<(String, usize), ParseError> {
+    let mut i = open + 1;
+    while i < chars.len() && chars[i] != '"' {
+        i += 1;
+    }
+    if i >= chars.len() {
+        return Err(ParseError::new("Unterminated quote", open));
+    }
+    Ok((chars[open + 1..i].iter().collect(), i + 1))
+}
+
+fn is_filter_key(key: &str) -> bool {
+    matches!(
+        key.to_ascii_lowercase().as_str(),
+        "workspace" | "paper" | "author" | "tag" | "color" | "page" | "type" | "before" | "after"
+    )
+}
+
+fn parse_filter(
+    key: &str,
+    value: &str,
+    quoted: bool,
+    position: usize,
+) -> Result<FilterKind, ParseError> {
+    if value.trim().is_empty() {
+        return Err(ParseError::new(format!("{key}: needs a value"), position));
+    }
+
+    let values = if quoted {
+        vec![value.trim().to_string()]
+    } else {
+        let parts = value
+            .split('|')
+            .map(|part| part.trim().to_string())
+            .collect::<Vec<_>>();
+        if parts.iter().any(String::is_empty) {
+            return Err(ParseError::new(
+                format!("{key}: has an empty alternative"),
+                position,
+            ));
+        }
+        parts
+    };
+
+    match key {
+        "workspace" => Ok(FilterKind::Workspace(values)),
+        "paper" => Ok(FilterKind::Paper(values)),
+        "author" => Ok(FilterKind::Author(
+            values.iter().map(|value| normalize_name(value)).collect(),
+        )),
+        "tag" => Ok(FilterKind::Tag(values)),
+        "color" => Ok(FilterKind::Color(values)),
+        "type" => {
+            let types = values
+                .iter()
+                .map(|value| value.to_ascii_lowercase())
+                .collect::<Vec<_>>();
+            if let Some(unknown) = types
+                .iter()
+                .find(|value| !SEARCHABLE_TYPES.contains(&value.as_str()))
+            {
+                return Err(ParseError::new(
+                    format!(
+                        "Unknown type \"{unknown}\"; expected one of {}",
+                        SEARCHABLE_TYPES.join(", ")
+                    ),
+                    position,
+                ));
+            }
+            Ok(FilterKind::Type(types))
+        }
+        "page" => parse_page_range(value.trim(), position),
+        "before" => Ok(FilterKind::Before(parse_date(value.trim(), position)?)),
+        "after" => Ok(FilterKind::After(parse_date(value.trim(), position)?)),
+        _ => Err(ParseError::new(format!("Unknown filter {key}:"), position)),
+    }
+}
+
+fn parse_page_range(value: &str, position: usize) -> Result<FilterKind, ParseError> {
+    let parse_page = |raw: &str| -> Result<Option<i32>, ParseError> {
+        if raw.is_empty() {
+            return Ok(None);
+        }
+        raw.parse::<i32>()
+            .ok()
+            .filter(|page| *page >= 0)
+            .map(Some)
+            .ok_or_else(|| ParseError::new(format!("Invalid page number \"{raw}\""), position))
+    };
+
+    let (from, to) = match value.split_once("..") {
+        Some((from, to)) => (parse_page(from)?, parse_page(to)?),
+        None => {
+            let page = parse_page(value)?;
+            (page, page)
+        }
+    };
+
+    match (from, to) {
+        (None, None) => Err(ParseError::new("page: needs a page or range", position)),
+        (Some(from), Some(to)) if from > to => Err(ParseError::new(
+            format!("Invalid page range {from}..{to}"),
+            position,
+        )),
+        _ => Ok(FilterKind::Page { from, to }),
+    }
+}
+
+fn parse_date(value: &str, position: usize) -> Result<NaiveDate, ParseError> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        ParseError::new(
+            format!("Invalid date \"{value}\"; expected YYYY-MM-DD"),
+            position,
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str) -> Term {
+        Term::Word(text.to_string())
+    }
+
+    fn phrase(text: &str) -> Term {
+        Term::Phrase(text.to_string())
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_offending_character() {
+        let cases = [
+            ("\"attention", 0, "Unterminated quote"),
+            ("loss \"attention", 5, "Unterminated quote"),
+            // Offsets count characters, not bytes.
+            ("ünïcode \"attention", 8, "Unterminated quote"),
+            ("workspace:\"Topic A", 10, "Unterminated quote"),
+            ("OR loss", 0, "OR must follow a search term"),
+            ("loss OR OR objective", 8, "OR must follow a search term"),
+            ("-survey OR loss", 8, "OR must follow a search term"),
+            ("loss OR", 5, "OR must be followed by a search term"),
+            ("loss OR -survey", 8, "OR must be followed by a search term"),
+            (
+                "loss OR tag:method",
+                8,
+                "OR must be followed by a search term",
+            ),
+            ("loss tag:", 9, "tag: needs a value"),
+            ("tag:a||b", 4, "tag: has an empty alternative"),
+            ("loss type:note|book", 10, "Unknown type \"book\""),
+            ("page:abc", 5, "Invalid page number \"abc\""),
+            ("page:20..10", 5, "Invalid page range 20..10"),
+            ("page:..", 5, "page: needs a page or range"),
+            ("loss -after:2024-13-01", 12, "Invalid date \"2024-13-01\""),
+        ];
+        for (input, position, message) in cases {
+            let err = parse(input).expect_err(input);
+            assert_eq!(err.position, position, "{input}: {}", err.message);
+            assert!(err.message.starts_with(message), "{input}: {}", err.message);
+        }
+    }
+
+    #[test]
+    fn parses_terms_groups_and_filters() {
+        let cases = [
+            (
+                "contrastive \"attention mechanism\" -survey",
+                ParsedQuery {
+                    groups: vec![
+                        vec![word("contrastive")],
+                        vec![phrase("attention mechanism")],
+                    ],
+                    excluded: vec![word("survey")],
+                    filters: vec![],
+                },
+            ),
+            (
+                "loss OR objective OR \"cost function\" model",
+                ParsedQuery {
+                    groups: vec![
+                        vec![word("loss"), word("objective"), phrase("cost function")],
+                        vec![word("model")],
+                    ],
+                    ..ParsedQuery::default()
+                },
+            ),
+            (
+                "-tag:method TYPE:Note|pdf page:..20 workspace:\"Topic A\"",
+                ParsedQuery {
+                    filters: vec![
+                        Filter {
+                            kind: FilterKind::Tag(vec!["method".to_string()]),
+                            negated: true,
+                        },
+                        Filter {
+                            kind: FilterKind::Type(vec!["note".to_string(), "pdf".to_string()]),
+                            negated: false,
+                        },
+                        Filter {
+                            kind: FilterKind::Page {
+                                from: None,
+                                to: Some(20),
+                            },
+                            negated: false,
+                        },
+                        Filter {
+                            kind: FilterKind::Workspace(vec!["Topic A".to_string()]),
+                            negated: false,
+                        },
+                    ],
+                    ..ParsedQuery::default()
+                },
+            ),
+            (
+                // Unknown keys and a lone dash are plain words; `-OR` is excluded.
+                "note:x - -OR",
+                ParsedQuery {
+                    groups: vec![vec![word("note:x")], vec![word("-")]],
+                    excluded: vec![word("OR")],
+                    filters: vec![],
+                },
+            ),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input), Ok(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn exclusion_only_queries_become_a_rowid_filter() {
+        let cases = [
+            ("-survey", "\"survey\"*"),
+            (
+                "-survey -\"deep learning\"",
+                "\"survey\"* OR \"deep learning\"",
+            ),
+            ("-survey tag:method", "\"survey\"*"),
+        ];
+        for (input, excluded) in cases {
+            let parsed = parse(input).unwrap();
+            assert!(parsed.groups.is_empty(), "{input}");
+            assert!(!parsed.is_empty(), "{input}");
+
+            let compiled = compile(&parsed);
+            assert_eq!(compiled.match_expr, None, "{input}");
+            assert_eq!(
+                compiled.conditions[0],
+                "search_index.rowid NOT IN \
+                 (SELECT rowid FROM search_index WHERE search_index MATCH :q0)",
+                "{input}"
+            );
+            assert_eq!(
+                compiled.params[0],
+                (":q0".to_string(), Value::Text(excluded.to_string())),
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn exclusions_next_to_positive_terms_stay_in_the_match_expression() {
+        let compiled = compile(&parse("loss -survey").unwrap());
+        assert_eq!(
+            compiled.match_expr.as_deref(),
+            Some("(\"loss\"*) NOT (\"survey\"*)")
+        );
+        assert!(compiled.conditions.is_empty());
+    }
+
+    #[test]
+    fn word_spans_skip_phrases_exclusions_and_filters() {
+        let spans = word_spans("ünï \"a b\" -c tag:d OR éf").unwrap();
+        assert_eq!(spans, vec![(0, "ünï".to_string()), (24, "éf".to_string())]);
+    }
+}
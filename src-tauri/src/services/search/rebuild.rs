@@ -0,0 +1,492 @@
+//! Background rebuild of `search_index`.
+//!
+//! Entries are written in small batches into a second FTS table, releasing
+//! the database lock between batches so other commands keep running and
+//! searches keep using the old index. Note, highlight, passage, tag and paper
+//! changes made while a rebuild runs are mirrored into the second table by
+//! [`super::upsert_entry`] and [`super::remove_entry`], which also record the
+//! entry in a touched set so the batches do not index it a second time. Once
+//! every source row is indexed the second table is renamed to `search_index`,
+//! so the swap does not tokenize anything while holding the lock.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::{
+    domain::SearchRebuildProgress,
+    services::Db,
+    telemetry::{IpcError, IpcResult, IpcStatus},
+};
+
+use super::{
+    tokenizer::TOKENIZER_VERSION, write_entry, HIGHLIGHT_REF_TYPE, NOTE_REF_TYPE,
+    PAPER_CONTENT_SQL, PAPER_REF_TYPE, PDF_REF_TYPE, TAG_REF_TYPE,
+};
+
+/// Table the rebuild writes into. It lives in the main schema so it can be
+/// renamed to `search_index`; one left behind by an interrupted rebuild makes
+/// [`index_outdated`] report the index, and the next rebuild drops it.
+pub(super) const REBUILD_TABLE: &str = "main.search_index_rebuild";
+/// Entries written or removed by live edits during the rebuild; the batches
+/// skip them. Connection-local, so it only exists while a rebuild runs.
+const TOUCHED_TABLE: &str = "temp.search_index_rebuild_touched";
+/// Column layout of `search_index`; must match the latest migration.
+const INDEX_SCHEMA: &str =
+    "fts5(content, label, refType UNINDEXED, refId UNINDEXED, tokenize = 'paperflow')";
+/// `search_meta` key holding the tokenizer version the index was built with.
+const TOKENIZER_VERSION_KEY: &str = "tokenizerVersion";
+const BATCH_SIZE: i64 = 200;
+
+pub const STAGE_NOTES: &str = "notes";
+pub const STAGE_HIGHLIGHTS: &str = "highlights";
+pub const STAGE_PASSAGES: &str = "passages";
+pub const STAGE_TAGS: &str = "tags";
+pub const STAGE_PAPERS: &str = "papers";
+pub const STAGE_SWAP: &str = "swap";
+pub const STAGE_DONE: &str = "done";
+pub const STAGE_CANCELLED: &str = "cancelled";
+
+/// Rows indexed by the rebuild, in order: ref type, source table, indexed
+/// column or expression, progress stage.
+const SOURCES: &[(&str, &str, &str, &str)] = &[
+    (NOTE_REF_TYPE, "note", "content", STAGE_NOTES),
+    (HIGHLIGHT_REF_TYPE, "highlight", "text", STAGE_HIGHLIGHTS),
+    (PDF_REF_TYPE, "paper_passage", "content", STAGE_PASSAGES),
+    (TAG_REF_TYPE, "tag", "name", STAGE_TAGS),
+    (PAPER_REF_TYPE, "paper", PAPER_CONTENT_SQL, STAGE_PAPERS),
+];
+
+/// Tracks whether a rebuild is running and lets other commands cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct RebuildControl {
+    running: Arc<AtomicBool>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl RebuildControl {
+    /// Claims the control for a new rebuild, or `None` if one is already running.
+    pub fn begin(&self) -> Option<RebuildRun> {
+        self.running
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .ok()?;
+        self.cancelled.store(false, Ordering::Release);
+        Some(RebuildRun {
+            control: self.clone(),
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Requests cancellation; returns `false` when no rebuild is running.
+    pub fn cancel(&self) -> bool {
+        if !self.running.load(Ordering::Acquire) {
+            return false;
+        }
+        self.cancelled.store(true, Ordering::Release);
+        true
+    }
+}
+
+/// A claimed rebuild; releases the [`RebuildControl`] when dropped.
+#[derive(Debug)]
+pub struct RebuildRun {
+    control: RebuildControl,
+}
+
+impl RebuildRun {
+    fn is_cancelled(&self) -> bool {
+        self.control.cancelled.load(Ordering::Acquire)
+    }
+}
+
+impl Drop for RebuildRun {
+    fn drop(&mut self) {
+        self.control.running.store(false, Ordering::Release);
+    }
+}
+
+/// Rebuilds the index in batches, reporting progress after each batch. Returns
+/// the final progress, whose stage is [`STAGE_DONE`] or [`STAGE_CANCELLED`].
+pub fn rebuild_in_batches(
+    db: &Db,
+    run: &RebuildRun,
+    mut on_progress: impl FnMut(&SearchRebuildProgress),
+) -> IpcResult<SearchRebuildProgress> {
+    let total = {
+        let conn = db.connection();
+        conn.execute_batch(&format!(
+            "DROP TABLE IF EXISTS {REBUILD_TABLE}; \
+             DROP TABLE IF EXISTS {TOUCHED_TABLE}; \
+             CREATE VIRTUAL TABLE {REBUILD_TABLE} USING {INDEX_SCHEMA}; \
+             CREATE TABLE {TOUCHED_TABLE} \
+               (refType TEXT NOT NULL, refId TEXT NOT NULL, PRIMARY KEY (refType, refId));"
+        ))
+        .map_err(db_error)?;
+        count_sources(&conn).map_err(db_error)?
+    };
+
+    let result = fill_and_swap(db, run, total, &mut on_progress);
+    if !matches!(&result, Ok(progress) if progress.stage == STAGE_DONE) {
+        let conn = db.connection();
+        if let Err(err) = conn.execute_batch(&format!(
+            "DROP TABLE IF EXISTS {REBUILD_TABLE}; DROP TABLE IF EXISTS {TOUCHED_TABLE};"
+        )) {
+            tracing::warn!(target = "search", error = %err, "failed to drop rebuild table");
+        }
+    }
+    result
+}
+
+/// Whether `search_index` was built by a different tokenizer version than the
+/// running one, or a rebuild was interrupted, and the index has to be rebuilt.
+pub fn index_outdated(db: &Db) -> IpcResult<bool> {
+    let conn = db.connection();
+    let interrupted: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'search_index_rebuild')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+    if interrupted && !in_progress(&conn).map_err(db_error)? {
+        return Ok(true);
+    }
+    let version: Option<String> = conn
+        .query_row(
+            "SELECT value FROM search_meta WHERE key = ?1",
+            params![TOKENIZER_VERSION_KEY],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(db_error)?;
+    Ok(version.and_then(|version| version.parse::<i64>().ok()) != Some(TOKENIZER_VERSION))
+}
+
+/// Marks the index as outdated, so [`index_outdated`] reports it until the
+/// next rebuild completes.
+pub(crate) fn mark_outdated(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM search_meta WHERE key = ?1",
+        params![TOKENIZER_VERSION_KEY],
+    )?;
+    Ok(())
+}
+
+/// Drops the tables of a running rebuild, which then fails instead of swapping
+/// in entries read from a database that was replaced underneath it.
+pub(crate) fn discard(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(&format!(
+        "DROP TABLE IF EXISTS {REBUILD_TABLE}; DROP TABLE IF EXISTS {TOUCHED_TABLE};"
+    ))
+}
+
+/// Records that a live edit wrote or removed the entry of `ref_id` while a
+/// rebuild is running.
+pub(super) fn mark_touched(
+    conn: &Connection,
+    ref_type: &str,
+    ref_id: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!("INSERT OR IGNORE INTO {TOUCHED_TABLE} (refType, refId) VALUES (?1, ?2)"),
+        params![ref_type, ref_id],
+    )?;
+    Ok(())
+}
+
+/// Whether the touched set exists on this connection, i.e. a rebuild is running.
+pub(super) fn in_progress(conn: &Connection) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS \
+         (SELECT 1 FROM sqlite_temp_master WHERE name = 'search_index_rebuild_touched')",
+        [],
+        |row| row.get(0),
+    )
+}
+
+fn fill_and_swap(
+    db: &Db,
+    run: &RebuildRun,
+    total: usize,
+    on_progress: &mut impl FnMut(&SearchRebuildProgress),
+) -> IpcResult<SearchRebuildProgress> {
+    let mut progress = SearchRebuildProgress {
+        done: 0,
+        total,
+        stage: STAGE_NOTES.to_string(),
+    };
+    on_progress(&progress);
+
+    for (ref_type, table, column, stage) in SOURCES {
+        progress.stage = stage.to_string();
+        let mut last_rowid = 0_i64;
+        loop {
+            if run.is_cancelled() {
+                progress.stage = STAGE_CANCELLED.to_string();
+                on_progress(&progress);
+                return Ok(progress);
+            }
+
+            let indexed = {
+                let mut conn = db.connection();
+                let tx = conn.transaction().map_err(db_error)?;
+                let batch =
+                    index_batch(&tx, ref_type, table, column, last_rowid).map_err(db_error)?;
+                tx.commit().map_err(db_error)?;
+                batch
+            };
+            let Some((rows, rowid)) = indexed else {
+                break;
+            };
+            last_rowid = rowid;
+            progress.done = (progress.done + rows).min(progress.total);
+            on_progress(&progress);
+        }
+    }
+
+    progress.stage = STAGE_SWAP.to_string();
+    on_progress(&progress);
+    {
+        let mut conn = db.connection();
+        let tx = conn.transaction().map_err(db_error)?;
+        // `search_vocab` reads `search_index` by name, so it is recreated
+        // over the renamed table.
+        tx.execute_batch(&format!(
+            "DROP TABLE IF EXISTS search_vocab; \
+             DROP TABLE search_index; \
+             ALTER TABLE {REBUILD_TABLE} RENAME TO search_index; \
+             CREATE VIRTUAL TABLE search_vocab USING fts5vocab (search_index, 'row'); \
+             DROP TABLE {TOUCHED_TABLE};"
+        ))
+        .map_err(db_error)?;
+        tx.execute(
+            "INSERT INTO search_meta (key, value) VALUES (?1, ?2) \
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![TOKENIZER_VERSION_KEY, TOKENIZER_VERSION.to_string()],
+        )
+        .map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+    }
+
+    progress.done = progress.total;
+    progress.stage = STAGE_DONE.to_string();
+    on_progress(&progress);
+    Ok(progress)
+}
+
+/// Indexes the next batch of `table` rows after `after_rowid` into the rebuild
+/// table, skipping entries live edits already wrote. Returns the number of rows
+/// and the last rowid, or `None` when done.
+fn index_batch(
+    conn: &Connection,
+    ref_type: &str,
+    table: &str,
+    column: &str,
+    after_rowid: i64,
+) -> rusqlite::Result<Option<(usize, i64)>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT rowid, id, {column} FROM {table} WHERE rowid > ?1 ORDER BY rowid LIMIT ?2"
+    ))?;
+    let rows = stmt
+        .query_map(params![after_rowid, BATCH_SIZE], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let Some((last_rowid, _, _)) = rows.last() else {
+        return Ok(None);
+    };
+    let last_rowid = *last_rowid;
+
+    let mut touched = conn.prepare(&format!(
+        "SELECT EXISTS (SELECT 1 FROM {TOUCHED_TABLE} WHERE refType = ?1 AND refId = ?2)"
+    ))?;
+    for (_, ref_id, content) in &rows {
+        if !touched.query_row(params![ref_type, ref_id], |row| row.get::<_, bool>(0))? {
+            write_entry(conn, REBUILD_TABLE, ref_type, ref_id, content)?;
+        }
+    }
+    Ok(Some((rows.len(), last_rowid)))
+}
+
+fn count_sources(conn: &Connection) -> rusqlite::Result<usize> {
+    let mut total = 0_i64;
+    for (_, table, _, _) in SOURCES {
+        total += conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+    }
+    Ok(total.max(0) as usize)
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{migration, tags};
+
+    fn setup(tag_paths: &[&str]) -> Db {
+        let db = Db::in_memory().unwrap();
+        migration::migrate(&mut db.connection()).unwrap();
+        for path in tag_paths {
+            tags::create(&db, path, None).unwrap();
+        }
+        db
+    }
+
+    /// Indexed tag contents, sorted.
+    fn tag_entries(db: &Db) -> Vec<String> {
+        let conn = db.connection();
+        let mut stmt = conn
+            .prepare("SELECT content FROM search_index WHERE refType = ?1 ORDER BY content")
+            .unwrap();
+        let entries = stmt
+            .query_map(params![TAG_REF_TYPE], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        entries
+    }
+
+    fn vocabulary(db: &Db) -> Vec<String> {
+        let conn = db.connection();
+        let mut stmt = conn
+            .prepare("SELECT term FROM search_vocab ORDER BY term")
+            .unwrap();
+        let terms = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        terms
+    }
+
+    fn rebuild_table_exists(db: &Db) -> bool {
+        db.connection()
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'search_index_rebuild')",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn rebuild_replaces_the_index_and_its_vocabulary() {
+        let db = setup(&["method", "topic"]);
+        db.connection()
+            .execute(
+                "INSERT INTO search_index (content, label, refType, refId) \
+                 VALUES ('stale', NULL, ?1, 'gone')",
+                params![TAG_REF_TYPE],
+            )
+            .unwrap();
+
+        let control = RebuildControl::default();
+        let run = control.begin().unwrap();
+        let progress = rebuild_in_batches(&db, &run, |_| {}).unwrap();
+
+        assert_eq!(progress.stage, STAGE_DONE);
+        assert_eq!(tag_entries(&db), ["method", "topic"]);
+        assert_eq!(vocabulary(&db), ["method", "topic"]);
+        assert!(!rebuild_table_exists(&db));
+        assert!(!in_progress(&db.connection()).unwrap());
+        assert!(!index_outdated(&db).unwrap());
+    }
+
+    #[test]
+    fn edits_made_during_the_rebuild_survive_the_swap() {
+        let db = setup(&["method", "topic", "survey"]);
+        let ids = tags::list(&db)
+            .unwrap()
+            .into_iter()
+            .map(|tag| (tag.name, tag.id))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        let control = RebuildControl::default();
+        let run = control.begin().unwrap();
+        let mut edited = false;
+        let progress = rebuild_in_batches(&db, &run, |_| {
+            // The first report comes before any batch, with the lock released.
+            if !edited {
+                edited = true;
+                tags::rename(&db, &ids["method"], "approach").unwrap();
+                tags::delete(&db, &ids["survey"]).unwrap();
+                tags::create(&db, "dataset", None).unwrap();
+            }
+        })
+        .unwrap();
+
+        assert_eq!(progress.stage, STAGE_DONE);
+        assert_eq!(tag_entries(&db), ["approach", "dataset", "topic"]);
+    }
+
+    #[test]
+    fn cancelled_rebuild_keeps_the_old_index() {
+        let db = setup(&["method", "topic"]);
+        db.connection()
+            .execute(
+                "INSERT INTO search_index (content, label, refType, refId) \
+                 VALUES ('stale', NULL, ?1, 'gone')",
+                params![TAG_REF_TYPE],
+            )
+            .unwrap();
+
+        let control = RebuildControl::default();
+        let run = control.begin().unwrap();
+        let progress = rebuild_in_batches(&db, &run, |progress| {
+            if progress.stage == STAGE_TAGS {
+                control.cancel();
+            }
+        })
+        .unwrap();
+
+        assert_eq!(progress.stage, STAGE_CANCELLED);
+        assert_eq!(tag_entries(&db), ["method", "stale", "topic"]);
+        assert_eq!(vocabulary(&db), ["method", "stale", "topic"]);
+        assert!(!rebuild_table_exists(&db));
+        assert!(!in_progress(&db.connection()).unwrap());
+
+        // Live edits after the cancelled run only reach `search_index`.
+        tags::create(&db, "dataset", None).unwrap();
+        assert_eq!(tag_entries(&db), ["dataset", "method", "stale", "topic"]);
+    }
+
+    #[test]
+    fn interrupted_rebuild_marks_the_index_outdated() {
+        let db = setup(&["method"]);
+        let control = RebuildControl::default();
+        let run = control.begin().unwrap();
+        rebuild_in_batches(&db, &run, |_| {}).unwrap();
+        drop(run);
+        assert!(!index_outdated(&db).unwrap());
+
+        // What a crash mid-rebuild leaves behind: the table without the
+        // connection-local touched set.
+        db.connection()
+            .execute_batch(&format!(
+                "CREATE VIRTUAL TABLE {REBUILD_TABLE} USING {INDEX_SCHEMA};"
+            ))
+            .unwrap();
+        assert!(index_outdated(&db).unwrap());
+
+        let run = control.begin().unwrap();
+        rebuild_in_batches(&db, &run, |_| {}).unwrap();
+        assert!(!rebuild_table_exists(&db));
+        assert!(!index_outdated(&db).unwrap());
+        assert_eq!(tag_entries(&db), ["method"]);
+    }
+}
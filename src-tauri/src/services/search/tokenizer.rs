@@ -0,0 +1,257 @@
+//! Native FTS5 tokenizer backed by [`fts::tokenize`].
+//!
+//! `search_index` stores the original text and lets SQLite call back into Rust
+//! to tokenize it, so `snippet()` and highlight offsets refer to what the user
+//! wrote. The tokenizer must be registered on every connection before
+//! `search_index` is touched; [`crate::services::Db`] does this on open.
+
+use std::{
+    ffi::{c_char, c_int, c_void},
+    panic, ptr, slice,
+};
+
+use rusqlite::{ffi, Connection};
+
+use super::fts;
+
+/// Name used in `tokenize = '...'` when creating `search_index`.
+pub const TOKENIZER_NAME: &str = "paperflow";
+/// Bump whenever tokenization or folding changes, including
+/// [`fts::CJK_NGRAM`]; indexes built by another version are rebuilt on startup.
+pub const TOKENIZER_VERSION: i64 = 1;
+
+/// FTS5 asks for a tokenizer instance per table; the tokenizer is stateless,
+/// so every table shares this one.
+static INSTANCE: u8 = 0;
+
+/// Registers the tokenizer with the FTS5 module of `conn`.
+pub fn register(conn: &Connection) -> rusqlite::Result<()> {
+    let api = fts5_api(conn)?;
+    let mut tokenizer = ffi::fts5_tokenizer {
+        xCreate: Some(x_create),
+        xDelete: Some(x_delete),
+        xTokenize: Some(x_tokenize),
+    };
+    let name = format!("{TOKENIZER_NAME}\0");
+
+    // SAFETY: `api` was just returned by FTS5 for this connection, and FTS5
+    // copies the tokenizer struct before `xCreateTokenizer` returns.
+    let rc = unsafe {
+        match (*api).xCreateTokenizer {
+            Some(create) => create(
+                api,
+                name.as_ptr().cast(),
+                ptr::null_mut(),
+                &mut tokenizer,
+                None,
+            ),
+            None => ffi::SQLITE_MISUSE,
+        }
+    };
+    check(rc, "failed to register the FTS5 tokenizer")
+}
+
+/// Fetches the `fts5_api` pointer through `SELECT fts5(?)`, the documented way
+/// for applications to reach the FTS5 extension API.
+fn fts5_api(conn: &Connection) -> rusqlite::Result<*mut ffi::fts5_api> {
+    let mut api: *mut ffi::fts5_api = ptr::null_mut();
+
+    // SAFETY: the statement is prepared, bound, stepped and finalized on the
+    // connection's own handle, and `api` outlives the statement.
+    unsafe {
+        let db = conn.handle();
+        let mut stmt = ptr::null_mut();
+        let rc = ffi::sqlite3_prepare_v2(
+            db,
+            c"SELECT fts5(?1)".as_ptr(),
+            -1,
+            &mut stmt,
+            ptr::null_mut(),
+        );
+        check(rc, "failed to prepare the fts5_api query")?;
+
+        ffi::sqlite3_bind_pointer(
+            stmt,
+            1,
+            (&mut api as *mut *mut ffi::fts5_api).cast(),
+            c"fts5_api_ptr".as_ptr(),
+            None,
+        );
+        ffi::sqlite3_step(stmt);
+        ffi::sqlite3_finalize(stmt);
+    }
+
+    if api.is_null() {
+        return Err(rusqlite::Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_ERROR),
+            Some("FTS5 is not available".to_string()),
+        ));
+    }
+    Ok(api)
+}
+
+unsafe extern "C" fn x_create(
+    _user_data: *mut c_void,
+    _args: *mut *const c_char,
+    _arg_count: c_int,
+    out: *mut *mut ffi::Fts5Tokenizer,
+) -> c_int {
+    *out = ptr::addr_of!(INSTANCE).cast_mut().cast();
+    ffi::SQLITE_OK
+}
+
+unsafe extern "C" fn x_delete(_tokenizer: *mut ffi::Fts5Tokenizer) {}
+
+unsafe extern "C" fn x_tokenize(
+    _tokenizer: *mut ffi::Fts5Tokenizer,
+    ctx: *mut c_void,
+    _flags: c_int,
+    text: *const c_char,
+    text_len: c_int,
+    x_token: Option<
+        unsafe extern "C" fn(*mut c_void, c_int, *const c_char, c_int, c_int, c_int) -> c_int,
+    >,
+) -> c_int {
+    let Some(x_token) = x_token else {
+        return ffi::SQLITE_MISUSE;
+    };
+    if text.is_null() || text_len <= 0 {
+        return ffi::SQLITE_OK;
+    }
+
+    let bytes = slice::from_raw_parts(text.cast::<u8>(), text_len as usize);
+    // Offsets must index the original bytes, so invalid input is cut at the
+    // first bad sequence instead of being replaced.
+    let source = match std::str::from_utf8(bytes) {
+        Ok(source) => source,
+        Err(err) => std::str::from_utf8_unchecked(&bytes[..err.valid_up_to()]),
+    };
+
+    // Unwinding across the FFI boundary is undefined behaviour.
+    let Ok(tokens) = panic::catch_unwind(|| fts::tokenize(source)) else {
+        return ffi::SQLITE_ERROR;
+    };
+    for token in tokens {
+        let rc = x_token(
+            ctx,
+            0,
+            token.text.as_ptr().cast(),
+            token.text.len() as c_int,
+            token.start as c_int,
+            token.end as c_int,
+        );
+        if rc != ffi::SQLITE_OK {
+            return rc;
+        }
+    }
+    ffi::SQLITE_OK
+}
+
+fn check(rc: c_int, context: &str) -> rusqlite::Result<()> {
+    if rc == ffi::SQLITE_OK {
+        return Ok(());
+    }
+    Err(rusqlite::Error::SqliteFailure(
+        ffi::Error::new(rc),
+        Some(context.to_string()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(source: &str) -> Vec<(String, usize, usize)> {
+        fts::tokenize(source)
+            .into_iter()
+            .map(|token| (token.text, token.start, token.end))
+            .collect()
+    }
+
+    fn indexed(content: &str) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        register(&conn).unwrap();
+        conn.execute_batch(&format!(
+            "CREATE VIRTUAL TABLE doc USING fts5(content, tokenize = '{TOKENIZER_NAME}');"
+        ))
+        .unwrap();
+        conn.execute("INSERT INTO doc (content) VALUES (?1)", [content])
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn token_offsets_index_the_original_bytes() {
+        let cases = [
+            (
+                "Transformer模型的attention",
+                vec![
+                    ("transformer", 0, 11),
+                    ("模型", 11, 17),
+                    ("型的", 14, 20),
+                    ("attention", 20, 29),
+                ],
+            ),
+            ("注意 is", vec![("注意", 0, 6), ("is", 7, 9)]),
+            (
+                "BERT和GPT",
+                vec![("bert", 0, 4), ("和", 4, 7), ("gpt", 7, 10)],
+            ),
+            // Full-width letters and digits and the fi ligature fold under NFKC.
+            ("ＡＢＣ１２３ ﬁx", vec![("abc123", 0, 18), ("fix", 19, 23)]),
+            // A combining accent and a half-width voicing mark stay with their base.
+            (
+                "Cafe\u{301} ﾃﾞｰﾀ",
+                vec![("cafe", 0, 6), ("デー", 7, 16), ("ータ", 13, 19)],
+            ),
+            ("Straße", vec![("strasse", 0, 7)]),
+        ];
+        for (source, expected) in cases {
+            let expected = expected
+                .into_iter()
+                .map(|(text, start, end)| (text.to_string(), start, end))
+                .collect::<Vec<_>>();
+            assert_eq!(tokens(source), expected, "{source}");
+        }
+    }
+
+    #[test]
+    fn highlights_wrap_the_original_text() {
+        let cases = [
+            (
+                "Transformer模型的attention机制",
+                "模型",
+                "Transformer[模型]的attention机制",
+            ),
+            (
+                "Transformer模型的attention机制",
+                "ATTENTION",
+                "Transformer模型的[attention]机制",
+            ),
+            (
+                "注意力机制 attention",
+                "\"注意力\"",
+                "[注意力]机制 attention",
+            ),
+            (
+                "ＡＴＴＥＮＴＩＯＮ is all",
+                "attention",
+                "[ＡＴＴＥＮＴＩＯＮ] is all",
+            ),
+            ("ﬁne-tuning", "fine", "[ﬁne]-tuning"),
+            ("cafe\u{301} au lait", "café", "[cafe\u{301}] au lait"),
+            ("ﾃﾞｰﾀ分析", "\"データ\"", "[ﾃﾞｰﾀ]分析"),
+        ];
+        for (content, query, expected) in cases {
+            let conn = indexed(content);
+            let highlighted: String = conn
+                .query_row(
+                    "SELECT highlight(doc, 0, '[', ']') FROM doc WHERE doc MATCH ?1",
+                    [query],
+                    |row| row.get(0),
+                )
+                .unwrap_or_else(|err| panic!("{content} / {query}: {err}"));
+            assert_eq!(highlighted, expected, "{content} / {query}");
+        }
+    }
+}
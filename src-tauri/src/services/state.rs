@@ -5,12 +5,47 @@ use parking_lot::Mutex;
 use super::{
     cache::{PageCache, PageCacheConfig},
     db::Db,
+    file_watch::FileWatcher,
+    library::LibraryRoot,
+    search::rebuild::RebuildControl,
 };
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: Db,
     pub page_cache: Arc<Mutex<PageCache>>,
+    pub search_rebuild: RebuildControl,
+    /// Set once the watcher has started; `None` when it could not.
+    pub file_watcher: Arc<Mutex<Option<FileWatcher>>>,
+}
+
+impl AppState {
+    /// Brings the watched folders in line with the current paper paths.
+    pub fn sync_file_watch(&self) {
+        if let Some(watcher) = self.file_watcher.lock().as_mut() {
+            if let Err(err) = watcher.sync() {
+                tracing::warn!(
+                    target = "file_watch",
+                    error = %format!("{err:#}"),
+                    "failed to update watched folders"
+                );
+            }
+        }
+    }
+
+    /// Starts watching `roots` as the library folders, replacing the previous
+    /// ones.
+    pub fn set_library(&self, roots: Vec<LibraryRoot>) {
+        if let Some(watcher) = self.file_watcher.lock().as_mut() {
+            if let Err(err) = watcher.set_library(roots) {
+                tracing::warn!(
+                    target = "file_watch",
+                    error = %format!("{err:#}"),
+                    "failed to watch library folders"
+                );
+            }
+        }
+    }
 }
 
 impl Default for AppState {
@@ -19,6 +54,8 @@ impl Default for AppState {
         Self {
             db: Db::default(),
             page_cache: Arc::new(Mutex::new(cache)),
+            search_rebuild: RebuildControl::default(),
+            file_watcher: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -0,0 +1,787 @@
+//! Tags and their assignment to notes and papers.
+//!
+//! Tags nest: `name` is the full path of a tag (`method/attention`) and
+//! `parentId` points at the tag one level up. Creating a tag creates its
+//! missing ancestors, renaming or moving a tag rewrites the paths of its
+//! subtree, and deleting a tag deletes its subtree. Paths are unique regardless
+//! of case.
+//!
+//! Every tag is indexed as a [`search::TAG_REF_TYPE`] entry, and the tag names
+//! of a note are indexed in the `label` column of the note's entry, so any
+//! change to a tag path re-indexes the notes carrying it.
+
+use std::collections::HashMap;
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use crate::{
+    domain::{Note, Paper, Tag},
+    telemetry::{IpcError, IpcResult, IpcStatus},
+    utils::time::now_iso,
+};
+
+use super::{repo, search, Db};
+
+/// Separator between the segments of a tag path.
+pub const PATH_SEPARATOR: char = '/';
+
+/// Number of notes and papers carrying `tag`.
+const USAGE_COUNT_SQL: &str = "((SELECT COUNT(*) FROM note_tag WHERE note_tag.tagId = tag.id) \
+     + (SELECT COUNT(*) FROM paper_tag WHERE paper_tag.tagId = tag.id))";
+
+/// `subtree` holds the tag bound to `?1` and all of its descendants.
+const SUBTREE_CTE: &str = "WITH RECURSIVE subtree(id, depth) AS ( \
+       SELECT id, 0 FROM tag WHERE id = ?1 \
+       UNION \
+       SELECT tag.id, subtree.depth + 1 FROM tag JOIN subtree ON tag.parentId = subtree.id)";
+
+/// All tags ordered by path, so children follow their parent.
+pub fn list(db: &Db) -> IpcResult<Vec<Tag>> {
+    let conn = db.connection();
+    let mut stmt = conn
+        .prepare(&format!(
+            "{} ORDER BY tag.name COLLATE NOCASE ASC",
+            select_tags()
+        ))
+        .map_err(db_error)?;
+
+    let tags = stmt
+        .query_map([], map_tag)
+        .map_err(db_error)?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(db_error)?;
+
+    Ok(tags)
+}
+
+/// Creates the tag at path `name`, creating missing ancestors on the way.
+pub fn create(db: &Db, name: &str, color: Option<&str>) -> IpcResult<Tag> {
+    let path = normalize_path(name)?;
+
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    ensure_name_available(&tx, &path, None)?;
+
+    let (parent_id, path) = resolve_parent(&tx, &path)?;
+    let tag_id = Uuid::new_v4().to_string();
+    insert_tag(&tx, &tag_id, &path, parent_id.as_deref(), color)?;
+
+    let tag = get_tag(&tx, &tag_id)?;
+    tx.commit().map_err(db_error)?;
+    Ok(tag)
+}
+
+/// Renames the last segment of a tag's path; the tag keeps its parent.
+pub fn rename(db: &Db, tag_id: &str, name: &str) -> IpcResult<Tag> {
+    let segment = name.trim();
+    if segment.is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "Tag name cannot be empty",
+        ));
+    }
+    if segment.contains(PATH_SEPARATOR) {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            format!("Tag name cannot contain \"{PATH_SEPARATOR}\"; move the tag instead"),
+        ));
+    }
+
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    let tag = get_tag(&tx, tag_id)?;
+    let path = match parent_path(&tag.name) {
+        Some(parent) => format!("{parent}{PATH_SEPARATOR}{segment}"),
+        None => segment.to_string(),
+    };
+    rewrite_subtree(&tx, tag_id, &path)?;
+
+    let tag = get_tag(&tx, tag_id)?;
+    tx.commit().map_err(db_error)?;
+    Ok(tag)
+}
+
+/// Re-parents a tag, or makes it a root tag when `parent_id` is `None`.
+pub fn move_to(db: &Db, tag_id: &str, parent_id: Option<&str>) -> IpcResult<Tag> {
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    get_tag(&tx, tag_id)?;
+    let parent = parent_id
+        .map(|parent_id| get_tag(&tx, parent_id))
+        .transpose()?;
+    move_subtree(&tx, tag_id, parent.as_ref())?;
+
+    let tag = get_tag(&tx, tag_id)?;
+    tx.commit().map_err(db_error)?;
+    Ok(tag)
+}
+
+pub fn recolor(db: &Db, tag_id: &str, color: Option<&str>) -> IpcResult<Tag> {
+    let color = color.map(str::trim).filter(|color| !color.is_empty());
+
+    let conn = db.connection();
+    let updated = conn
+        .execute(
+            "UPDATE tag SET color = ?1 WHERE id = ?2",
+            params![color, tag_id],
+        )
+        .map_err(db_error)?;
+    if updated == 0 {
+        return Err(not_found(tag_id));
+    }
+    get_tag(&conn, tag_id)
+}
+
+/// Deletes a tag together with its descendants.
+pub fn delete(db: &Db, tag_id: &str) -> IpcResult<()> {
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+
+    let subtree = subtree(&tx, tag_id).map_err(db_error)?;
+    let notes = subtree_notes(&tx, tag_id).map_err(db_error)?;
+    let deleted = tx
+        .execute("DELETE FROM tag WHERE id = ?1", params![tag_id])
+        .map_err(db_error)?;
+    if deleted == 0 {
+        return Err(not_found(tag_id));
+    }
+
+    for (id, _) in &subtree {
+        search::remove_entry(&tx, search::TAG_REF_TYPE, id).map_err(db_error)?;
+    }
+    reindex_notes(&tx, &notes).map_err(db_error)?;
+
+    tx.commit().map_err(db_error)?;
+    Ok(())
+}
+
+/// Moves the notes, papers and child tags of every source tag onto `target_id` and
+/// deletes the source tags.
+pub fn merge(db: &Db, source_ids: &[String], target_id: &str) -> IpcResult<Tag> {
+    if source_ids.is_empty() {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "At least one source tag is required",
+        ));
+    }
+    if source_ids.iter().any(|source_id| source_id == target_id) {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            "A tag cannot be merged into itself",
+        ));
+    }
+
+    let mut conn = db.connection();
+    let tx = conn.transaction().map_err(db_error)?;
+    let target = get_tag(&tx, target_id)?;
+
+    let mut sources = source_ids.to_vec();
+    sources.sort();
+    sources.dedup();
+
+    let mut notes = Vec::new();
+    for source_id in &sources {
+        get_tag(&tx, source_id)?;
+        if in_subtree(&tx, source_id, target_id)? {
+            return Err(IpcError::new(
+                IpcStatus::BadRequest,
+                "A tag cannot be merged into one of its descendants",
+            ));
+        }
+        for child_id in children(&tx, source_id).map_err(db_error)? {
+            move_subtree(&tx, &child_id, Some(&target))?;
+        }
+        notes.extend(tagged_notes(&tx, source_id).map_err(db_error)?);
+
+        tx.execute(
+            "INSERT OR IGNORE INTO note_tag (noteId, tagId) \
+             SELECT noteId, ?1 FROM note_tag WHERE tagId = ?2",
+            params![target_id, source_id],
+        )
+        .map_err(db_error)?;
+        tx.execute(
+            "INSERT OR IGNORE INTO paper_tag (paperId, tagId) \
+             SELECT paperId, ?1 FROM paper_tag WHERE tagId = ?2",
+            params![target_id, source_id],
+        )
+        .map_err(db_error)?;
+        tx.execute("DELETE FROM tag WHERE id = ?1", params![source_id])
+            .map_err(db_error)?;
+        search::remove_entry(&tx, search::TAG_REF_TYPE, source_id).map_err(db_error)?;
+    }
+    notes.sort();
+    notes.dedup();
+    reindex_notes(&tx, &notes).map_err(db_error)?;
+
+    let tag = get_tag(&tx, target_id)?;
+    tx.commit().map_err(db_error)?;
+    Ok(tag)
+}
+
+pub fn add_to_note(db: &Db, note_id: &str, tag_id: &str) -> IpcResult<Note> {
+    {
+        let mut conn = db.connection();
+        let tx = conn.transaction().map_err(db_error)?;
+        ensure_note_exists(&tx, note_id)?;
+        get_tag(&tx, tag_id)?;
+
+        tx.execute(
+            "INSERT OR IGNORE INTO note_tag (noteId, tagId) VALUES (?1, ?2)",
+            params![note_id, tag_id],
+        )
+        .map_err(db_error)?;
+        reindex_notes(&tx, &[note_id.to_string()]).map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+    }
+    repo::get_note(db, note_id)
+}
+
+pub fn remove_from_note(db: &Db, note_id: &str, tag_id: &str) -> IpcResult<Note> {
+    {
+        let mut conn = db.connection();
+        let tx = conn.transaction().map_err(db_error)?;
+        ensure_note_exists(&tx, note_id)?;
+
+        let removed = tx
+            .execute(
+                "DELETE FROM note_tag WHERE noteId = ?1 AND tagId = ?2",
+                params![note_id, tag_id],
+            )
+            .map_err(db_error)?;
+        if removed == 0 {
+            return Err(IpcError::new(
+                IpcStatus::NotFound,
+                format!("Note {note_id} is not tagged with {tag_id}"),
+            ));
+        }
+        reindex_notes(&tx, &[note_id.to_string()]).map_err(db_error)?;
+        tx.commit().map_err(db_error)?;
+    }
+    repo::get_note(db, note_id)
+}
+
+pub fn add_to_paper(db: &Db, paper_id: &str, tag_id: &str) -> IpcResult<Paper> {
+    {
+        let conn = db.connection();
+        ensure_paper_exists(&conn, paper_id)?;
+        get_tag(&conn, tag_id)?;
+
+        conn.execute(
+            "INSERT OR IGNORE INTO paper_tag (paperId, tagId) VALUES (?1, ?2)",
+            params![paper_id, tag_id],
+        )
+        .map_err(db_error)?;
+    }
+    repo::get_paper(db, paper_id)
+}
+
+pub fn remove_from_paper(db: &Db, paper_id: &str, tag_id: &str) -> IpcResult<Paper> {
+    {
+        let conn = db.connection();
+        ensure_paper_exists(&conn, paper_id)?;
+
+        let removed = conn
+            .execute(
+                "DELETE FROM paper_tag WHERE paperId = ?1 AND tagId = ?2",
+                params![paper_id, tag_id],
+            )
+            .map_err(db_error)?;
+        if removed == 0 {
+            return Err(IpcError::new(
+                IpcStatus::NotFound,
+                format!("Paper {paper_id} is not tagged with {tag_id}"),
+            ));
+        }
+    }
+    repo::get_paper(db, paper_id)
+}
+
+/// Parent id and canonical path for a new tag at `path`. Missing ancestors
+/// are created; existing ones lend their spelling to the returned path.
+pub(crate) fn resolve_parent(conn: &Connection, path: &str) -> IpcResult<(Option<String>, String)> {
+    let Some((parent, segment)) = path.rsplit_once(PATH_SEPARATOR) else {
+        return Ok((None, path.to_string()));
+    };
+
+    let existing: Option<(String, String)> = conn
+        .query_row(
+            "SELECT id, name FROM tag WHERE name = ?1 COLLATE NOCASE",
+            params![parent],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(db_error)?;
+    let (parent_id, parent) = match existing {
+        Some(existing) => existing,
+        None => {
+            let (grandparent_id, parent) = resolve_parent(conn, parent)?;
+            let parent_id = Uuid::new_v4().to_string();
+            insert_tag(conn, &parent_id, &parent, grandparent_id.as_deref(), None)?;
+            (parent_id, parent)
+        }
+    };
+    Ok((
+        Some(parent_id),
+        format!("{parent}{PATH_SEPARATOR}{segment}"),
+    ))
+}
+
+fn insert_tag(
+    conn: &Connection,
+    tag_id: &str,
+    path: &str,
+    parent_id: Option<&str>,
+    color: Option<&str>,
+) -> IpcResult<()> {
+    conn.execute(
+        "INSERT INTO tag (id, name, parentId, color, createdAt) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![tag_id, path, parent_id, color, now_iso()],
+    )
+    .map_err(db_error)?;
+    search::upsert_entry(conn, search::TAG_REF_TYPE, tag_id, path).map_err(db_error)?;
+    Ok(())
+}
+
+/// Puts `tag_id` under `parent`, rejecting moves into its own subtree.
+fn move_subtree(conn: &Connection, tag_id: &str, parent: Option<&Tag>) -> IpcResult<()> {
+    let tag = get_tag(conn, tag_id)?;
+    let segment = last_segment(&tag.name);
+    let path = match parent {
+        Some(parent) => {
+            if in_subtree(conn, tag_id, &parent.id)? {
+                return Err(IpcError::new(
+                    IpcStatus::BadRequest,
+                    format!(
+                        "Cannot move {} under itself or one of its descendants",
+                        tag.name
+                    ),
+                ));
+            }
+            format!("{}{PATH_SEPARATOR}{segment}", parent.name)
+        }
+        None => segment.to_string(),
+    };
+
+    conn.execute(
+        "UPDATE tag SET parentId = ?1 WHERE id = ?2",
+        params![parent.map(|parent| parent.id.as_str()), tag_id],
+    )
+    .map_err(db_error)?;
+    rewrite_subtree(conn, tag_id, &path)
+}
+
+/// Gives `tag_id` the path `path` and rebuilds the paths of its descendants
+/// from it, re-indexing the tags and the notes carrying them.
+fn rewrite_subtree(conn: &Connection, tag_id: &str, path: &str) -> IpcResult<()> {
+    let subtree = subtree(conn, tag_id).map_err(db_error)?;
+    let parents = subtree_parents(conn, tag_id).map_err(db_error)?;
+
+    // Parents come before their children, so each parent's new path is known
+    // by the time its children are visited.
+    let mut paths: HashMap<&str, String> = HashMap::new();
+    for (id, name) in &subtree {
+        let new_path = match parents
+            .get(id.as_str())
+            .and_then(|parent| paths.get(parent.as_str()))
+        {
+            Some(parent_path) if id != tag_id => {
+                format!("{parent_path}{PATH_SEPARATOR}{}", last_segment(name))
+            }
+            _ => path.to_string(),
+        };
+        ensure_name_available(conn, &new_path, Some(tag_id))?;
+        paths.insert(id.as_str(), new_path);
+    }
+
+    for (id, name) in &subtree {
+        let new_path = &paths[id.as_str()];
+        if new_path == name {
+            continue;
+        }
+        conn.execute(
+            "UPDATE tag SET name = ?1 WHERE id = ?2",
+            params![new_path, id],
+        )
+        .map_err(db_error)?;
+        search::upsert_entry(conn, search::TAG_REF_TYPE, id, new_path).map_err(db_error)?;
+    }
+
+    let notes = subtree_notes(conn, tag_id).map_err(db_error)?;
+    reindex_notes(conn, &notes).map_err(db_error)
+}
+
+/// Ids and paths of `tag_id` and its descendants, parents before children.
+fn subtree(conn: &Connection, tag_id: &str) -> rusqlite::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(&format!(
+        "{SUBTREE_CTE} SELECT tag.id, tag.name FROM subtree JOIN tag ON tag.id = subtree.id \
+         ORDER BY subtree.depth"
+    ))?;
+    let tags = stmt
+        .query_map(params![tag_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tags)
+}
+
+fn subtree_parents(conn: &Connection, tag_id: &str) -> rusqlite::Result<HashMap<String, String>> {
+    let mut stmt = conn.prepare(&format!(
+        "{SUBTREE_CTE} SELECT tag.id, tag.parentId FROM subtree JOIN tag ON tag.id = subtree.id \
+         WHERE tag.parentId IS NOT NULL"
+    ))?;
+    let parents = stmt
+        .query_map(params![tag_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(parents)
+}
+
+/// Whether `candidate_id` is `tag_id` or one of its descendants.
+fn in_subtree(conn: &Connection, tag_id: &str, candidate_id: &str) -> IpcResult<bool> {
+    conn.query_row(
+        &format!("{SUBTREE_CTE} SELECT EXISTS (SELECT 1 FROM subtree WHERE id = ?2)"),
+        params![tag_id, candidate_id],
+        |row| row.get(0),
+    )
+    .map_err(db_error)
+}
+
+fn children(conn: &Connection, tag_id: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT id FROM tag WHERE parentId = ?1")?;
+    let children = stmt
+        .query_map(params![tag_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(children)
+}
+
+/// Notes carrying `tag_id` or any of its descendants.
+fn subtree_notes(conn: &Connection, tag_id: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!(
+        "{SUBTREE_CTE} SELECT DISTINCT noteId FROM note_tag \
+         WHERE tagId IN (SELECT id FROM subtree)"
+    ))?;
+    let notes = stmt
+        .query_map(params![tag_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+/// Tags linked to each of `owner_ids` through `link_table`, whose
+/// `owner_column` holds the owner id; ordered by name.
+fn linked_tags(
+    conn: &Connection,
+    link_table: &str,
+    owner_column: &str,
+    owner_ids: &[String],
+) -> rusqlite::Result<HashMap<String, Vec<Tag>>> {
+    let mut tags: HashMap<String, Vec<Tag>> = HashMap::new();
+    if owner_ids.is_empty() {
+        return Ok(tags);
+    }
+
+    let placeholders = vec!["?"; owner_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT link.{owner_column} AS ownerId, tag.id, tag.name, tag.parentId, tag.color, \
+         tag.createdAt, {USAGE_COUNT_SQL} AS usageCount \
+         FROM {link_table} link JOIN tag ON tag.id = link.tagId \
+         WHERE link.{owner_column} IN ({placeholders}) \
+         ORDER BY tag.name COLLATE NOCASE ASC"
+    ))?;
+    let rows = stmt.query_map(params_from_iter(owner_ids), |row| {
+        Ok((row.get::<_, String>("ownerId")?, map_tag(row)?))
+    })?;
+    for row in rows {
+        let (owner_id, tag) = row?;
+        tags.entry(owner_id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// Fills in the `tags` of each note.
+pub(crate) fn attach_tags(conn: &Connection, notes: &mut [Note]) -> rusqlite::Result<()> {
+    let note_ids = notes.iter().map(|note| note.id.clone()).collect::<Vec<_>>();
+    let mut tags = linked_tags(conn, "note_tag", "noteId", &note_ids)?;
+    for note in notes {
+        note.tags = tags.remove(&note.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Fills in the `tags` of each paper.
+pub(crate) fn attach_paper_tags(conn: &Connection, papers: &mut [Paper]) -> rusqlite::Result<()> {
+    let paper_ids = papers
+        .iter()
+        .map(|paper| paper.id.clone())
+        .collect::<Vec<_>>();
+    let mut tags = linked_tags(conn, "paper_tag", "paperId", &paper_ids)?;
+    for paper in papers {
+        paper.tags = tags.remove(&paper.id).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Re-indexes notes so their index labels carry their current tag names.
+pub(crate) fn reindex_notes(conn: &Connection, note_ids: &[String]) -> rusqlite::Result<()> {
+    for note_id in note_ids {
+        let content: Option<String> = conn
+            .query_row(
+                "SELECT content FROM note WHERE id = ?1",
+                params![note_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(content) = content {
+            search::upsert_entry(conn, search::NOTE_REF_TYPE, note_id, &content)?;
+        }
+    }
+    Ok(())
+}
+
+fn tagged_notes(conn: &Connection, tag_id: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT noteId FROM note_tag WHERE tagId = ?1")?;
+    let notes = stmt
+        .query_map(params![tag_id], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(notes)
+}
+
+fn select_tags() -> String {
+    format!(
+        "SELECT tag.id, tag.name, tag.parentId, tag.color, tag.createdAt, \
+         {USAGE_COUNT_SQL} AS usageCount FROM tag"
+    )
+}
+
+fn get_tag(conn: &Connection, tag_id: &str) -> IpcResult<Tag> {
+    conn.query_row(
+        &format!("{} WHERE tag.id = ?1", select_tags()),
+        params![tag_id],
+        map_tag,
+    )
+    .optional()
+    .map_err(db_error)?
+    .ok_or_else(|| not_found(tag_id))
+}
+
+fn map_tag(row: &rusqlite::Row<'_>) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        parent_id: row.get("parentId")?,
+        color: row.get("color")?,
+        created_at: row.get("createdAt")?,
+        usage_count: row.get("usageCount")?,
+    })
+}
+
+/// Trims every segment of a tag path and rejects empty segments.
+fn normalize_path(name: &str) -> IpcResult<String> {
+    let segments = name
+        .split(PATH_SEPARATOR)
+        .map(str::trim)
+        .collect::<Vec<_>>();
+    if segments.iter().any(|segment| segment.is_empty()) {
+        return Err(IpcError::new(
+            IpcStatus::BadRequest,
+            format!("Tag name \"{}\" has an empty segment", name.trim()),
+        ));
+    }
+    Ok(segments.join(&PATH_SEPARATOR.to_string()))
+}
+
+fn parent_path(path: &str) -> Option<&str> {
+    path.rsplit_once(PATH_SEPARATOR).map(|(parent, _)| parent)
+}
+
+fn last_segment(path: &str) -> &str {
+    path.rsplit_once(PATH_SEPARATOR)
+        .map_or(path, |(_, segment)| segment)
+}
+
+/// Fails with a conflict when another tag already uses `path`. Tags in the
+/// subtree of `moving_id` are ignored, since their paths are being rewritten.
+fn ensure_name_available(conn: &Connection, path: &str, moving_id: Option<&str>) -> IpcResult<()> {
+    let existing: Option<String> = conn
+        .query_row(
+            &format!(
+                "{SUBTREE_CTE} SELECT id FROM tag \
+                 WHERE name = ?2 COLLATE NOCASE AND id NOT IN (SELECT id FROM subtree)"
+            ),
+            params![moving_id, path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(db_error)?;
+    if existing.is_some() {
+        return Err(IpcError::new(
+            IpcStatus::Conflict,
+            format!("Tag {path} already exists"),
+        ));
+    }
+    Ok(())
+}
+
+fn ensure_note_exists(conn: &Connection, note_id: &str) -> IpcResult<()> {
+    let exists = conn
+        .query_row("SELECT 1 FROM note WHERE id = ?1", params![note_id], |_| {
+            Ok(())
+        })
+        .optional()
+        .map_err(db_error)?
+        .is_some();
+    if exists {
+        Ok(())
+    } else {
+        Err(IpcError::new(
+            IpcStatus::NotFound,
+            format!("Note {note_id} not found"),
+        ))
+    }
+}
+
+fn ensure_paper_exists(conn: &Connection, paper_id: &str) -> IpcResult<()> {
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM paper WHERE id = ?1",
+            params![paper_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(db_error)?
+        .is_some();
+    if exists {
+        Ok(())
+    } else {
+        Err(IpcError::new(
+            IpcStatus::NotFound,
+            format!("Paper {paper_id} not found"),
+        ))
+    }
+}
+
+fn not_found(tag_id: &str) -> IpcError {
+    IpcError::new(IpcStatus::NotFound, format!("Tag {tag_id} not found"))
+}
+
+fn db_error(err: rusqlite::Error) -> IpcError {
+    IpcError::new(IpcStatus::DbError, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::migration;
+
+    fn setup() -> Db {
+        let db = Db::in_memory().unwrap();
+        migration::migrate(&mut db.connection()).unwrap();
+        db
+    }
+
+    /// Creates the tags at `paths` and returns the ids of all tags by path.
+    fn create_all(db: &Db, paths: &[&str]) -> HashMap<String, String> {
+        for path in paths {
+            create(db, path, None).unwrap();
+        }
+        list(db)
+            .unwrap()
+            .into_iter()
+            .map(|tag| (tag.name, tag.id))
+            .collect()
+    }
+
+    fn paths(db: &Db) -> Vec<String> {
+        list(db).unwrap().into_iter().map(|tag| tag.name).collect()
+    }
+
+    #[test]
+    fn normalizes_paths() {
+        let cases = [
+            ("method", Some("method")),
+            (" method / attention ", Some("method/attention")),
+            ("a/b/c", Some("a/b/c")),
+            ("", None),
+            ("method/", None),
+            ("/method", None),
+            ("method//attention", None),
+            ("method/ /attention", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(normalize_path(name).ok().as_deref(), expected, "{name:?}");
+        }
+    }
+
+    #[test]
+    fn create_adds_missing_ancestors_and_rejects_case_duplicates() {
+        let db = setup();
+        create(&db, "Method/attention/self", None).unwrap();
+        let tag = create(&db, "method/Cross", None).unwrap();
+        assert_eq!(tag.name, "Method/Cross");
+        assert_eq!(
+            paths(&db),
+            [
+                "Method",
+                "Method/attention",
+                "Method/attention/self",
+                "Method/Cross"
+            ]
+        );
+
+        let err = create(&db, "METHOD/Attention", None).unwrap_err();
+        assert!(matches!(err.code, IpcStatus::Conflict), "{}", err.message);
+    }
+
+    #[test]
+    fn move_into_own_subtree_is_rejected() {
+        let db = setup();
+        let ids = create_all(&db, &["method/attention/self", "method/loss"]);
+        let before = paths(&db);
+
+        let cases = [
+            ("method", "method"),
+            ("method", "method/attention"),
+            ("method", "method/attention/self"),
+            ("method/attention", "method/attention/self"),
+        ];
+        for (tag, parent) in cases {
+            let err = move_to(&db, &ids[tag], Some(&ids[parent])).unwrap_err();
+            assert!(
+                matches!(err.code, IpcStatus::BadRequest),
+                "{tag} -> {parent}: {}",
+                err.message
+            );
+            assert_eq!(paths(&db), before, "{tag} -> {parent}");
+        }
+    }
+
+    #[test]
+    fn move_rewrites_the_subtree_paths() {
+        let db = setup();
+        let ids = create_all(&db, &["method/attention/self", "topic"]);
+
+        let moved = move_to(&db, &ids["method/attention"], Some(&ids["topic"])).unwrap();
+        assert_eq!(moved.name, "topic/attention");
+        assert_eq!(moved.parent_id.as_deref(), Some(ids["topic"].as_str()));
+        assert_eq!(
+            paths(&db),
+            ["method", "topic", "topic/attention", "topic/attention/self"]
+        );
+
+        let root = move_to(&db, &ids["method/attention"], None).unwrap();
+        assert_eq!(root.parent_id, None);
+        assert_eq!(
+            paths(&db),
+            ["attention", "attention/self", "method", "topic"]
+        );
+    }
+
+    #[test]
+    fn merge_into_a_descendant_is_rejected() {
+        let db = setup();
+        let ids = create_all(&db, &["method/attention", "topic"]);
+
+        let err = merge(&db, &[ids["method"].clone()], &ids["method/attention"]).unwrap_err();
+        assert!(matches!(err.code, IpcStatus::BadRequest), "{}", err.message);
+        let err = merge(&db, &[ids["topic"].clone()], &ids["topic"]).unwrap_err();
+        assert!(matches!(err.code, IpcStatus::BadRequest), "{}", err.message);
+        assert_eq!(paths(&db), ["method", "method/attention", "topic"]);
+    }
+}
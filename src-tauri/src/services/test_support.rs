@@ -0,0 +1,54 @@
+//! Fixtures shared by the service tests.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use uuid::Uuid;
+
+use super::{config, migration, Db};
+
+/// A folder under the system temp dir, removed on drop.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new() -> Self {
+        let path = std::env::temp_dir().join(format!("paperflow-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes a small PDF-like file whose content is unique to `name` and
+    /// `body`, creating missing folders.
+    pub fn write_pdf(&self, name: &str, body: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("%PDF-1.4\n% {name} {body}\n%%EOF\n")).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// An in-memory database migrated to the latest schema.
+pub fn migrated_db() -> Db {
+    let db = Db::in_memory().unwrap();
+    migration::migrate(&mut db.connection()).unwrap();
+    db
+}
+
+/// A temp folder standing in for the app data folder of the current thread.
+pub fn data_dir() -> TempDir {
+    let dir = TempDir::new();
+    config::set_test_data_dir(dir.path());
+    dir
+}
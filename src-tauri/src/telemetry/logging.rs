@@ -2,6 +2,8 @@ use once_cell::sync::OnceCell;
 use tracing::{error, info};
 use tracing_subscriber::{fmt, EnvFilter};
 
+use crate::services::migration::MigrationReport;
+
 static SUBSCRIBER: OnceCell<()> = OnceCell::new();
 
 pub fn init() {
@@ -23,3 +25,14 @@ pub fn init() {
 pub fn log_startup_error(stage: &str, err: &anyhow::Error) {
     error!(target = "startup", %stage, error = %err, "startup stage failed");
 }
+
+pub fn log_migration_report(report: &MigrationReport) {
+    info!(
+        target = "startup",
+        from = report.from_version,
+        to = report.to_version,
+        applied = ?report.applied,
+        backup = ?report.backup,
+        "schema migrations checked"
+    );
+}